@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+// Normalisation constant for turning the summed word valences into -1.0..=1.0
+const NORMALIZATION_ALPHA: f64 = 15.0;
+const MIN_KEYWORD_LENGTH: usize = 3;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SentimentScore {
+    // -1.0 (very negative) to 1.0 (very positive)
+    pub score: f64,
+    pub keywords: Vec<String>,
+}
+
+// Scores a chat message. Implementations must be cheap, they run for every message.
+pub trait SentimentScorer: Send + Sync {
+    fn score(&self, text: &str) -> SentimentScore;
+}
+
+#[derive(Deserialize)]
+struct Lexicon {
+    words: HashMap<String, i8>,
+    negations: HashSet<String>,
+    stopwords: HashSet<String>,
+}
+
+// Offline scorer: sums word valences from a bundled lexicon, flipping words that
+// follow a negation ("not good").
+pub struct LexiconSentimentScorer {
+    lexicon: Lexicon,
+}
+
+impl Default for LexiconSentimentScorer {
+    fn default() -> Self {
+        let lexicon: Lexicon =
+            serde_json::from_str(include_str!("../config/sentiment_lexicon.json"))
+                .expect("Bundled sentiment lexicon must be valid JSON");
+
+        LexiconSentimentScorer { lexicon }
+    }
+}
+
+impl SentimentScorer for LexiconSentimentScorer {
+    fn score(&self, text: &str) -> SentimentScore {
+        let tokens = tokenize(text);
+
+        let mut total = 0.0;
+        let mut negate_next = false;
+
+        for token in &tokens {
+            if self.lexicon.negations.contains(token) {
+                negate_next = true;
+                continue;
+            }
+
+            if let Some(valence) = self.lexicon.words.get(token) {
+                let valence = *valence as f64;
+                total += if negate_next { -valence } else { valence };
+                negate_next = false;
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let keywords = tokens
+            .into_iter()
+            .filter(|token| token.chars().count() >= MIN_KEYWORD_LENGTH)
+            .filter(|token| !token.chars().all(|c| c.is_ascii_digit()))
+            .filter(|token| !self.lexicon.stopwords.contains(token))
+            .filter(|token| seen.insert(token.clone()))
+            .collect();
+
+        SentimentScore {
+            score: total / (total * total + NORMALIZATION_ALPHA).sqrt(),
+            keywords,
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .filter(|word| !word.starts_with('@') && !word.starts_with("http"))
+        .map(|word| {
+            if word == "<3" {
+                return word.to_string();
+            }
+
+            word.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'')
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_by_word_valence() {
+        let scorer = LexiconSentimentScorer::default();
+
+        assert!(scorer.score("this stream is awesome <3").score > 0.5);
+        assert!(scorer.score("worst run ever, so boring").score < -0.5);
+        assert_eq!(scorer.score("the cat sat down").score, 0.0);
+        assert_eq!(scorer.score("").score, 0.0);
+
+        let score = scorer.score("wow wow wow wow wow wow wow wow wow wow").score;
+        assert!(score > 0.9 && score <= 1.0);
+    }
+
+    #[test]
+    fn negations_flip_the_next_scored_word() {
+        let scorer = LexiconSentimentScorer::default();
+
+        assert!(scorer.score("not good").score < 0.0);
+        assert!(scorer.score("no good").score < 0.0);
+        assert!(scorer.score("this is not bad").score > 0.0);
+        // A negation on its own scores nothing
+        assert_eq!(scorer.score("no").score, 0.0);
+    }
+
+    #[test]
+    fn every_word_has_one_role() {
+        let scorer = LexiconSentimentScorer::default();
+        let lexicon = &scorer.lexicon;
+
+        for negation in &lexicon.negations {
+            assert!(!lexicon.words.contains_key(negation), "{} is scored and a negation", negation);
+        }
+    }
+
+    #[test]
+    fn keywords_skip_mentions_links_and_stopwords() {
+        let scorer = LexiconSentimentScorer::default();
+        let score = scorer.score("@someone the Speedrun https://example.com speedrun is 1000 ok gg");
+
+        assert_eq!(score.keywords, vec!["speedrun".to_string()]);
+    }
+}
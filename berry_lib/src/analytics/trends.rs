@@ -0,0 +1,248 @@
+use super::sentiment::SentimentScore;
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+
+const RETENTION_MINUTES: usize = 24 * 60;
+const TOP_KEYWORDS: usize = 10;
+
+struct MinuteBucket {
+    minute: DateTime<Utc>,
+    messages: u32,
+    sentiment_sum: f64,
+    flagged: u32,
+    keywords: HashMap<String, u32>,
+}
+
+impl MinuteBucket {
+    fn new(minute: DateTime<Utc>) -> Self {
+        MinuteBucket {
+            minute,
+            messages: 0,
+            sentiment_sum: 0.0,
+            flagged: 0,
+            keywords: HashMap::new(),
+        }
+    }
+
+    fn point(&self) -> TrendPoint {
+        TrendPoint {
+            minute: self.minute,
+            messages: self.messages,
+            average_sentiment: average(self.sentiment_sum, self.messages),
+            toxicity_rate: self.flagged,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrendPoint {
+    pub minute: DateTime<Utc>,
+    pub messages: u32,
+    pub average_sentiment: f64,
+    // Flagged messages in this minute
+    pub toxicity_rate: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeywordCount {
+    pub keyword: String,
+    pub count: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrendSummary {
+    pub messages: u32,
+    pub average_sentiment: f64,
+    pub toxicity_per_minute: f64,
+    pub top_keywords: Vec<KeywordCount>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrendReport {
+    pub channel: String,
+    pub window_minutes: usize,
+    pub summary: TrendSummary,
+    pub series: Vec<TrendPoint>,
+}
+
+// Per-minute rolling aggregates of a channel's chat
+#[derive(Default)]
+pub struct ChannelTrends {
+    buckets: VecDeque<MinuteBucket>,
+}
+
+impl ChannelTrends {
+    fn bucket_for(&mut self, at: DateTime<Utc>) -> &mut MinuteBucket {
+        let minute = at
+            .duration_trunc(TimeDelta::minutes(1))
+            .unwrap_or(at);
+
+        if self.buckets.back().map(|bucket| bucket.minute) != Some(minute) {
+            if self.buckets.len() == RETENTION_MINUTES {
+                self.buckets.pop_front();
+            }
+            self.buckets.push_back(MinuteBucket::new(minute));
+        }
+
+        self.buckets.back_mut().expect("bucket was just pushed")
+    }
+
+    pub fn record_message(&mut self, at: DateTime<Utc>, sentiment: &SentimentScore) {
+        let bucket = self.bucket_for(at);
+        bucket.messages += 1;
+        bucket.sentiment_sum += sentiment.score;
+
+        for keyword in &sentiment.keywords {
+            *bucket.keywords.entry(keyword.clone()).or_insert(0) += 1;
+        }
+    }
+
+    pub fn record_flag(&mut self, at: DateTime<Utc>) {
+        self.bucket_for(at).flagged += 1;
+    }
+
+    pub fn report(&self, channel: &str, window_minutes: usize, now: DateTime<Utc>) -> TrendReport {
+        let since = now - TimeDelta::minutes(window_minutes as i64);
+        let window: Vec<&MinuteBucket> = self
+            .buckets
+            .iter()
+            .filter(|bucket| bucket.minute > since)
+            .collect();
+
+        let messages: u32 = window.iter().map(|bucket| bucket.messages).sum();
+        let sentiment_sum: f64 = window.iter().map(|bucket| bucket.sentiment_sum).sum();
+        let flagged: u32 = window.iter().map(|bucket| bucket.flagged).sum();
+
+        let mut keywords: HashMap<&str, u32> = HashMap::new();
+        for bucket in &window {
+            for (keyword, count) in &bucket.keywords {
+                *keywords.entry(keyword.as_str()).or_insert(0) += count;
+            }
+        }
+
+        let mut top_keywords: Vec<KeywordCount> = keywords
+            .into_iter()
+            .map(|(keyword, count)| KeywordCount {
+                keyword: keyword.to_string(),
+                count,
+            })
+            .collect();
+        top_keywords.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.keyword.cmp(&b.keyword)));
+        top_keywords.truncate(TOP_KEYWORDS);
+
+        TrendReport {
+            channel: channel.to_string(),
+            window_minutes,
+            summary: TrendSummary {
+                messages,
+                average_sentiment: average(sentiment_sum, messages),
+                toxicity_per_minute: flagged as f64 / window_minutes.max(1) as f64,
+                top_keywords,
+            },
+            series: window.iter().map(|bucket| bucket.point()).collect(),
+        }
+    }
+}
+
+fn average(sum: f64, count: u32) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f64
+    }
+}
+
+// Trends for every channel a bot is running in. Shared between the bots, which
+// record into it, and the API, which reads from it.
+#[derive(Clone, Default)]
+pub struct TrendRegistry {
+    channels: Arc<RwLock<HashMap<String, ChannelTrends>>>,
+}
+
+impl TrendRegistry {
+    pub fn record_message(&self, channel: &str, sentiment: &SentimentScore) {
+        if let Ok(mut channels) = self.channels.write() {
+            channels
+                .entry(channel.to_lowercase())
+                .or_default()
+                .record_message(Utc::now(), sentiment);
+        }
+    }
+
+    pub fn record_flag(&self, channel: &str) {
+        if let Ok(mut channels) = self.channels.write() {
+            channels
+                .entry(channel.to_lowercase())
+                .or_default()
+                .record_flag(Utc::now());
+        }
+    }
+
+    pub fn report(&self, channel: &str, window_minutes: usize) -> Option<TrendReport> {
+        let channels = self.channels.read().ok()?;
+        let channel = channel.to_lowercase();
+
+        channels
+            .get(&channel)
+            .map(|trends| trends.report(&channel, window_minutes, Utc::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn sentiment(score: f64, keywords: &[&str]) -> SentimentScore {
+        SentimentScore {
+            score,
+            keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn aggregates_messages_per_minute() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 19, 20, 0, 10).unwrap();
+        let mut trends = ChannelTrends::default();
+
+        trends.record_message(start, &sentiment(0.5, &["boss", "clutch"]));
+        trends.record_message(start + TimeDelta::seconds(20), &sentiment(-0.1, &["boss"]));
+        trends.record_flag(start + TimeDelta::seconds(30));
+        trends.record_message(start + TimeDelta::minutes(1), &sentiment(0.2, &["boss"]));
+
+        let report = trends.report("chan", 5, start + TimeDelta::minutes(2));
+
+        assert_eq!(report.series.len(), 2);
+        assert_eq!(report.series[0].messages, 2);
+        assert!((report.series[0].average_sentiment - 0.2).abs() < 1e-9);
+        assert_eq!(report.series[0].toxicity_rate, 1);
+        assert_eq!(report.series[1].messages, 1);
+
+        assert_eq!(report.summary.messages, 3);
+        assert!((report.summary.average_sentiment - 0.2).abs() < 1e-9);
+        assert!((report.summary.toxicity_per_minute - 0.2).abs() < 1e-9);
+        assert_eq!(report.summary.top_keywords[0].keyword, "boss");
+        assert_eq!(report.summary.top_keywords[0].count, 3);
+        assert_eq!(report.summary.top_keywords[1].keyword, "clutch");
+    }
+
+    #[test]
+    fn reports_only_the_window() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 19, 20, 0, 0).unwrap();
+        let mut trends = ChannelTrends::default();
+
+        trends.record_message(start, &sentiment(1.0, &[]));
+        trends.record_message(start + TimeDelta::minutes(30), &sentiment(-1.0, &[]));
+
+        let report = trends.report("chan", 10, start + TimeDelta::minutes(31));
+        assert_eq!(report.summary.messages, 1);
+        assert_eq!(report.summary.average_sentiment, -1.0);
+
+        let empty = trends.report("chan", 10, start + TimeDelta::hours(5));
+        assert_eq!(empty.summary.messages, 0);
+        assert_eq!(empty.summary.average_sentiment, 0.0);
+        assert!(empty.series.is_empty());
+    }
+}
@@ -29,6 +29,30 @@ where
             status_code,
         }
     }
+
+    // Logs a database error and answers with a 500 carrying `message`
+    pub fn db_error(message: &str, e: sqlx::Error) -> ApiResponse<T> {
+        let (status, message) = db_error(message, e);
+        ApiResponse::new(None, Some(message), Some(status))
+    }
+
+    // Like `db_error`, but a unique violation is a 409 carrying `conflict`, for
+    // writes that can clash with an existing row
+    pub fn db_error_or_conflict(message: &str, conflict: &str, e: sqlx::Error) -> ApiResponse<T> {
+        match e {
+            sqlx::Error::Database(db_error) if db_error.is_unique_violation() => {
+                ApiResponse::new(None, Some(conflict.to_string()), Some(StatusCode::CONFLICT))
+            }
+            e => ApiResponse::db_error(message, e),
+        }
+    }
+}
+
+// Logs a database error and gives the status and message to answer with, for
+// callers that don't answer with an ApiResponse directly
+pub fn db_error(message: &str, e: sqlx::Error) -> (StatusCode, String) {
+    eprintln!("{}: {}", message, e);
+    (StatusCode::INTERNAL_SERVER_ERROR, message.to_string())
 }
 
 impl<T> Responder for ApiResponse<T>
@@ -0,0 +1,241 @@
+// Envelope encryption for secrets we keep in the database, like users' Twitch
+// tokens. Each record gets its own random data key, which encrypts the values
+// with AES-256-GCM. The data key is in turn encrypted ("wrapped") with the
+// master key from CREDENTIALS_KEY, and only the wrapped copy is stored.
+//
+// The master key is 32 bytes, base64 encoded. To rotate it, move the old key to
+// CREDENTIALS_OLD_KEYS (comma separated) and set a new CREDENTIALS_KEY. Records
+// are re-wrapped with the new key the next time they're saved.
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::sync::{Arc, OnceLock};
+
+const KEY_LEN: usize = 32;
+
+#[derive(Debug)]
+pub enum EnvelopeError {
+    ConfigurationError(String),
+    // The record was wrapped with a master key we no longer have
+    UnknownKey(String),
+    // Tampered with, or sealed for a different record
+    DecryptionFailed,
+    EncryptionFailed,
+}
+
+impl std::fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EnvelopeError::ConfigurationError(e) => write!(f, "Envelope Configuration Error: {}", e),
+            EnvelopeError::UnknownKey(id) => write!(f, "Unknown Master Key: {}", id),
+            EnvelopeError::DecryptionFailed => write!(f, "Decryption Failed"),
+            EnvelopeError::EncryptionFailed => write!(f, "Encryption Failed"),
+        }
+    }
+}
+
+impl std::error::Error for EnvelopeError {}
+
+struct MasterKey {
+    // Derived from the key, so it never needs configuring
+    id: String,
+    key: LessSafeKey,
+}
+
+impl MasterKey {
+    fn new(bytes: &[u8]) -> Result<Self, EnvelopeError> {
+        let key = UnboundKey::new(&AES_256_GCM, bytes)
+            .map_err(|_| EnvelopeError::ConfigurationError(format!("Master keys must be {} bytes", KEY_LEN)))?;
+
+        let id = digest(&SHA256, bytes).as_ref()[..8]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        Ok(MasterKey {
+            id,
+            key: LessSafeKey::new(key),
+        })
+    }
+}
+
+// What's stored for a record: the wrapped data key and the encrypted values
+#[derive(Debug, Clone)]
+pub struct Sealed {
+    pub key_id: String,
+    pub wrapped_key: Vec<u8>,
+    pub values: Vec<Vec<u8>>,
+}
+
+pub struct Envelope {
+    // The first key wraps, all of them unwrap
+    master_keys: Vec<MasterKey>,
+    rng: SystemRandom,
+}
+
+static SHARED_ENVELOPE: OnceLock<Result<Arc<Envelope>, String>> = OnceLock::new();
+
+impl Envelope {
+    pub fn new(current: &[u8], old: &[Vec<u8>]) -> Result<Self, EnvelopeError> {
+        let mut master_keys = vec![MasterKey::new(current)?];
+        for key in old {
+            master_keys.push(MasterKey::new(key)?);
+        }
+
+        Ok(Envelope {
+            master_keys,
+            rng: SystemRandom::new(),
+        })
+    }
+
+    // Loaded from the environment once and kept for the life of the process
+    pub fn shared() -> Result<Arc<Envelope>, EnvelopeError> {
+        SHARED_ENVELOPE
+            .get_or_init(|| Envelope::from_env().map(Arc::new).map_err(|e| e.to_string()))
+            .clone()
+            .map_err(EnvelopeError::ConfigurationError)
+    }
+
+    pub fn from_env() -> Result<Self, EnvelopeError> {
+        let decode = |key: &str| {
+            STANDARD
+                .decode(key.trim())
+                .map_err(|e| EnvelopeError::ConfigurationError(format!("Master keys must be base64: {}", e)))
+        };
+
+        let current = std::env::var("CREDENTIALS_KEY")
+            .map_err(|_| EnvelopeError::ConfigurationError("CREDENTIALS_KEY must be set".to_string()))?;
+
+        let old = std::env::var("CREDENTIALS_OLD_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|key| !key.trim().is_empty())
+            .map(decode)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Envelope::new(&decode(&current)?, &old)
+    }
+
+    // `context` names the record, e.g. the user it belongs to, so sealed values
+    // can't be copied onto another record. Each value is also bound to its
+    // position, so they can't be swapped with each other.
+    pub fn seal(&self, context: &str, values: &[&[u8]]) -> Result<Sealed, EnvelopeError> {
+        let mut data_key = [0u8; KEY_LEN];
+        self.rng
+            .fill(&mut data_key)
+            .map_err(|_| EnvelopeError::EncryptionFailed)?;
+
+        let master = &self.master_keys[0];
+        let wrapped_key = self.encrypt(&master.key, context.as_bytes(), &data_key)?;
+
+        let data_key =
+            LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &data_key).map_err(|_| EnvelopeError::EncryptionFailed)?);
+
+        let values = values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| self.encrypt(&data_key, value_aad(context, index).as_bytes(), value))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Sealed {
+            key_id: master.id.clone(),
+            wrapped_key,
+            values,
+        })
+    }
+
+    pub fn open(&self, context: &str, sealed: &Sealed) -> Result<Vec<Vec<u8>>, EnvelopeError> {
+        let master = self
+            .master_keys
+            .iter()
+            .find(|key| key.id == sealed.key_id)
+            .ok_or_else(|| EnvelopeError::UnknownKey(sealed.key_id.clone()))?;
+
+        let data_key = decrypt(&master.key, context.as_bytes(), &sealed.wrapped_key)?;
+        let data_key =
+            LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &data_key).map_err(|_| EnvelopeError::DecryptionFailed)?);
+
+        sealed
+            .values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| decrypt(&data_key, value_aad(context, index).as_bytes(), value))
+            .collect()
+    }
+
+    // A fresh random nonce, stored in front of the ciphertext
+    fn encrypt(&self, key: &LessSafeKey, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, EnvelopeError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| EnvelopeError::EncryptionFailed)?;
+
+        let mut ciphertext = plaintext.to_vec();
+        key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(aad), &mut ciphertext)
+            .map_err(|_| EnvelopeError::EncryptionFailed)?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+}
+
+fn decrypt(key: &LessSafeKey, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, EnvelopeError> {
+    if sealed.len() < NONCE_LEN {
+        return Err(EnvelopeError::DecryptionFailed);
+    }
+
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| EnvelopeError::DecryptionFailed)?;
+
+    let mut buffer = ciphertext.to_vec();
+    let plaintext = key
+        .open_in_place(nonce, Aad::from(aad), &mut buffer)
+        .map_err(|_| EnvelopeError::DecryptionFailed)?;
+
+    Ok(plaintext.to_vec())
+}
+
+fn value_aad(context: &str, index: usize) -> String {
+    format!("{}#{}", context, index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_what_it_sealed_and_nothing_else() {
+        let envelope = Envelope::new(&[1u8; KEY_LEN], &[]).unwrap();
+        let sealed = envelope.seal("user-1", &[b"access", b"refresh"]).unwrap();
+
+        assert_ne!(sealed.values[0], b"access");
+        assert_eq!(
+            envelope.open("user-1", &sealed).unwrap(),
+            vec![b"access".to_vec(), b"refresh".to_vec()]
+        );
+
+        // Copied onto another user's record
+        assert!(envelope.open("user-2", &sealed).is_err());
+
+        // Values swapped with each other
+        let mut swapped = sealed.clone();
+        swapped.values.swap(0, 1);
+        assert!(envelope.open("user-1", &swapped).is_err());
+    }
+
+    #[test]
+    fn rotated_master_keys_still_open_old_records() {
+        let old = Envelope::new(&[1u8; KEY_LEN], &[]).unwrap();
+        let sealed = old.seal("user-1", &[b"access"]).unwrap();
+
+        let rotated = Envelope::new(&[2u8; KEY_LEN], &[vec![1u8; KEY_LEN]]).unwrap();
+        assert_eq!(rotated.open("user-1", &sealed).unwrap(), vec![b"access".to_vec()]);
+        assert_ne!(rotated.seal("user-1", &[b"access"]).unwrap().key_id, sealed.key_id);
+
+        let retired = Envelope::new(&[2u8; KEY_LEN], &[]).unwrap();
+        assert!(matches!(retired.open("user-1", &sealed), Err(EnvelopeError::UnknownKey(_))));
+    }
+}
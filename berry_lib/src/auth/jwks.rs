@@ -0,0 +1,233 @@
+// Publishing our public keys, and checking our tokens from other services
+// (the overlay server, the Discord bridge) without sharing any secret:
+//
+//     let verifier = JwksVerifier::from_issuer("https://berry.example.com", "berry");
+//     let token = verifier.verify::<Claims>(&bearer).await?;
+//
+// The key set is fetched on first use and kept for `max_age`. A token signed
+// with a key we haven't seen, as after a rotation, fetches it again sooner.
+use super::jwt::{self, JwtError};
+use super::keys::JwtKeys;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, TokenData, Validation};
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+pub const JWKS_PATH: &str = "/.well-known/jwks.json";
+pub const DISCOVERY_PATH: &str = "/.well-known/openid-configuration";
+
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+// Unknown key ids refetch at most this often, so bad tokens can't hammer us
+const MIN_REFETCH_INTERVAL: Duration = Duration::from_secs(30);
+
+// The parts of OpenID Connect discovery that apply to us. We issue tokens but
+// aren't an OpenID provider, so there's no authorization endpoint.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub jwks_uri: String,
+    pub id_token_signing_alg_values_supported: Vec<Algorithm>,
+    pub subject_types_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+}
+
+impl OpenIdConfiguration {
+    pub fn new(issuer: &str, keys: &JwtKeys) -> Self {
+        let issuer = issuer.trim_end_matches('/');
+
+        OpenIdConfiguration {
+            issuer: issuer.to_string(),
+            jwks_uri: format!("{}{}", issuer, JWKS_PATH),
+            id_token_signing_alg_values_supported: keys.public_algorithms(),
+            subject_types_supported: vec!["public".to_string()],
+            claims_supported: ["sub", "twitch_id", "sid", "iss", "aud", "iat", "nbf", "exp", "jti"].iter().map(|claim| claim.to_string()).collect(),
+        }
+    }
+}
+
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Option<Instant>,
+}
+
+pub struct JwksVerifier {
+    client: Client,
+    jwks_url: String,
+    max_age: Duration,
+    validation: Validation,
+    cache: RwLock<CachedJwks>,
+}
+
+impl JwksVerifier {
+    pub fn new(jwks_url: &str) -> Self {
+        JwksVerifier {
+            client: Client::new(),
+            jwks_url: jwks_url.to_string(),
+            max_age: DEFAULT_MAX_AGE,
+            validation: Validation::default(),
+            cache: RwLock::new(CachedJwks {
+                keys: JwkSet { keys: Vec::new() },
+                fetched_at: None,
+            }),
+        }
+    }
+
+    // Our tokens from `issuer`, meant for `audience`
+    pub fn from_issuer(issuer: &str, audience: &str) -> Self {
+        let issuer = issuer.trim_end_matches('/');
+        JwksVerifier::new(&format!("{}{}", issuer, JWKS_PATH))
+            .with_validation(jwt::validation(jwt::KEY_PAIR_ALGORITHMS, issuer, audience))
+    }
+
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    // Expiry, issuer, audience and leeway checks. The algorithm always comes
+    // from the key, whatever `algorithms` is set to.
+    pub fn with_validation(mut self, validation: Validation) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    pub async fn verify<C: DeserializeOwned>(&self, token: &str) -> Result<TokenData<C>, JwtError> {
+        let header = decode_header(token).map_err(JwtError::TokenValidationError)?;
+        let kid = header
+            .kid
+            .ok_or_else(|| JwtError::TokenValidationError(ErrorKind::InvalidToken.into()))?;
+
+        let keys = self.keys_for(&kid).await?;
+        verify_with_set(&keys, token, &self.validation)
+    }
+
+    async fn keys_for(&self, kid: &str) -> Result<JwkSet, JwtError> {
+        {
+            let cache = self.cache.read().await;
+            if cache.fetched_at.is_some_and(|at| at.elapsed() < self.max_age) && cache.keys.find(kid).is_some() {
+                return Ok(cache.keys.clone());
+            }
+        }
+
+        let mut cache = self.cache.write().await;
+
+        // Someone else may have fetched while we waited for the lock
+        let fresh = cache.fetched_at.is_some_and(|at| at.elapsed() < self.max_age);
+        let recent = cache.fetched_at.is_some_and(|at| at.elapsed() < MIN_REFETCH_INTERVAL);
+        if (fresh && cache.keys.find(kid).is_some()) || recent {
+            return Ok(cache.keys.clone());
+        }
+
+        match self.fetch().await {
+            Ok(keys) => {
+                cache.keys = keys;
+                cache.fetched_at = Some(Instant::now());
+            }
+            // Keep using what we have if the issuer is down
+            Err(e) if cache.keys.find(kid).is_some() => {
+                eprintln!("Error Refreshing JWKS: {}", e);
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(cache.keys.clone())
+    }
+
+    async fn fetch(&self) -> Result<JwkSet, JwtError> {
+        let fetch_error = |e: reqwest::Error| JwtError::KeyFetchError(format!("{}: {}", self.jwks_url, e));
+
+        self.client
+            .get(&self.jwks_url)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(fetch_error)?
+            .json::<JwkSet>()
+            .await
+            .map_err(fetch_error)
+    }
+}
+
+pub fn verify_with_set<C: DeserializeOwned>(
+    keys: &JwkSet,
+    token: &str,
+    validation: &Validation,
+) -> Result<TokenData<C>, JwtError> {
+    let header = decode_header(token).map_err(JwtError::TokenValidationError)?;
+
+    let jwk = header
+        .kid
+        .as_deref()
+        .and_then(|kid| keys.find(kid))
+        .ok_or_else(|| JwtError::TokenValidationError(ErrorKind::InvalidToken.into()))?;
+
+    // Only asymmetric keys belong in a JWKS, and a key that names its
+    // algorithm only verifies that one
+    let algorithm_allowed = !matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+        && jwk
+            .common
+            .key_algorithm
+            .is_none_or(|algorithm| algorithm.to_string() == format!("{:?}", header.alg));
+    if !algorithm_allowed {
+        return Err(JwtError::TokenValidationError(ErrorKind::InvalidAlgorithm.into()));
+    }
+
+    let key = DecodingKey::from_jwk(jwk).map_err(JwtError::TokenValidationError)?;
+
+    let mut validation = validation.clone();
+    validation.algorithms = vec![header.alg];
+
+    decode::<C>(token, &key, &validation).map_err(JwtError::TokenValidationError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::jwt::{self, Claims, JwtAlgorithm, JwtConfig};
+    use crate::auth::keys::JwtKey;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, ECDSA_P384_SHA384_FIXED_SIGNING};
+    use std::sync::Arc;
+
+    #[test]
+    fn published_keys_verify_our_tokens() {
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P384_SHA384_FIXED_SIGNING, &SystemRandom::new()).unwrap();
+        let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref()));
+        let keys = Arc::new(JwtKeys::new(
+            vec![JwtKey::from_pem("2026-10-19", pem.as_bytes()).unwrap(), JwtKey::from_secret(b"secret")],
+            None,
+        ));
+
+        // The shared secret is never published
+        let jwks = keys.jwks();
+        assert_eq!(jwks.keys.len(), 1);
+        assert_eq!(keys.public_algorithms(), vec![Algorithm::ES384]);
+
+        let claims = Claims::new("unxid", "1", "session", chrono::Duration::minutes(15));
+        let validation = jwt::validation(&[Algorithm::ES384], &claims.iss, &claims.aud);
+        let token = JwtConfig::with_keys(keys.clone(), JwtAlgorithm::ES384)
+            .unwrap()
+            .generate_token(&claims)
+            .unwrap();
+        let verified = verify_with_set::<Claims>(&jwks, &token, &validation).unwrap();
+        assert_eq!(verified.claims.sub, "unxid");
+
+        let other_audience = jwt::validation(&[Algorithm::ES384], &claims.iss, "elsewhere");
+        assert!(verify_with_set::<Claims>(&jwks, &token, &other_audience).is_err());
+
+        let hmac_token = JwtConfig::with_keys(keys, JwtAlgorithm::HS256)
+            .unwrap()
+            .generate_token(&claims)
+            .unwrap();
+        assert!(verify_with_set::<Claims>(&jwks, &hmac_token, &validation).is_err());
+    }
+}
@@ -1,12 +1,17 @@
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use super::keys::JwtKeys;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, Header, TokenData, Validation};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct JwtConfig {
-    secret: String,
+    keys: Arc<JwtKeys>,
     algorithm: Algorithm,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum JwtAlgorithm {
     HS256,
     HS384,
@@ -19,28 +24,91 @@ pub enum JwtAlgorithm {
     ES512,
 }
 
-impl From<JwtAlgorithm> for Algorithm {
-    fn from(algorithm: JwtAlgorithm) -> Self {
+impl TryFrom<JwtAlgorithm> for Algorithm {
+    type Error = JwtError;
+
+    fn try_from(algorithm: JwtAlgorithm) -> Result<Self, Self::Error> {
         match algorithm {
-            JwtAlgorithm::HS256 => Algorithm::HS256,
-            JwtAlgorithm::HS384 => Algorithm::HS384,
-            JwtAlgorithm::HS512 => Algorithm::HS512,
-            JwtAlgorithm::RS256 => Algorithm::RS256,
-            JwtAlgorithm::RS384 => Algorithm::RS384,
-            JwtAlgorithm::RS512 => Algorithm::RS512,
-            JwtAlgorithm::ES256 => Algorithm::ES256,
-            JwtAlgorithm::ES384 => Algorithm::ES384,
-            _ => panic!("JWT Algorithm not supported"),
+            JwtAlgorithm::HS256 => Ok(Algorithm::HS256),
+            JwtAlgorithm::HS384 => Ok(Algorithm::HS384),
+            JwtAlgorithm::HS512 => Ok(Algorithm::HS512),
+            JwtAlgorithm::RS256 => Ok(Algorithm::RS256),
+            JwtAlgorithm::RS384 => Ok(Algorithm::RS384),
+            JwtAlgorithm::RS512 => Ok(Algorithm::RS512),
+            JwtAlgorithm::ES256 => Ok(Algorithm::ES256),
+            JwtAlgorithm::ES384 => Ok(Algorithm::ES384),
+            // P-521 isn't implemented by jsonwebtoken or ring
+            JwtAlgorithm::ES512 => Err(JwtError::ConfigurationError(
+                "ES512 isn't supported, use ES256 or ES384".to_string(),
+            )),
+        }
+    }
+}
+
+impl std::str::FromStr for JwtAlgorithm {
+    type Err = JwtError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_uppercase().as_str() {
+            "HS256" => Ok(JwtAlgorithm::HS256),
+            "HS384" => Ok(JwtAlgorithm::HS384),
+            "HS512" => Ok(JwtAlgorithm::HS512),
+            "RS256" => Ok(JwtAlgorithm::RS256),
+            "RS384" => Ok(JwtAlgorithm::RS384),
+            "RS512" => Ok(JwtAlgorithm::RS512),
+            "ES256" => Ok(JwtAlgorithm::ES256),
+            "ES384" => Ok(JwtAlgorithm::ES384),
+            "ES512" => Ok(JwtAlgorithm::ES512),
+            _ => Err(JwtError::ConfigurationError(format!("Unknown JWT algorithm {}", name))),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+pub const DEFAULT_AUDIENCE: &str = "berry";
+
+// Everything but the HS algorithms, whose secret could be shared elsewhere
+pub const KEY_PAIR_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+];
+
+// Who the user is and nothing they could misuse. Their Twitch tokens stay on
+// the server, see db::twitch_credentials.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
-    pub unxid: String,
-    pub exp: usize,
-    pub twitch_access_token: String,
+    // The user's unxid
+    pub sub: String,
     pub twitch_id: String,
+    // The session the token was issued to, see db::sessions
+    pub sid: String,
+    pub iss: String,
+    pub aud: String,
+    pub iat: usize,
+    pub nbf: usize,
+    pub exp: usize,
+    // Unique per token, so one can be told apart from another in logs and revoked
+    pub jti: String,
+}
+
+impl Claims {
+    pub fn new(unxid: &str, twitch_id: &str, session_id: &str, lifetime: chrono::Duration) -> Self {
+        let now = chrono::Utc::now().timestamp() as usize;
+
+        Claims {
+            sub: unxid.to_string(),
+            twitch_id: twitch_id.to_string(),
+            sid: session_id.to_string(),
+            iss: issuer(),
+            aud: audience(),
+            iat: now,
+            nbf: now,
+            exp: now + lifetime.num_seconds() as usize,
+            jti: format!("{:032x}", rand::random::<u128>()),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -48,44 +116,223 @@ pub enum JwtError {
     TokenCreationError(jsonwebtoken::errors::Error),
     TokenValidationError(jsonwebtoken::errors::Error),
     ConfigurationError(String),
+    KeyFetchError(String),
 }
 
+impl std::fmt::Display for JwtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            JwtError::TokenCreationError(e) => write!(f, "Token Creation Error: {}", e),
+            JwtError::TokenValidationError(e) => write!(f, "Token Validation Error: {}", e),
+            JwtError::ConfigurationError(e) => write!(f, "JWT Configuration Error: {}", e),
+            JwtError::KeyFetchError(e) => write!(f, "JWKS Fetch Error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for JwtError {}
+
 impl JwtConfig {
-    pub fn new(algorithm: Algorithm) -> Self {
-        let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-        Self { secret, algorithm }
-    }
-
-    pub fn generate_token(&self, claims: &Claims) -> Result<String, JwtError> {
-        encode(
-            &Header::default(),
-            claims,
-            &EncodingKey::from_secret(self.secret.as_ref()),
-        )
-        .map_err(JwtError::TokenCreationError)
-    }
-
-    pub fn validate_token(&self, token: &str) -> Result<jsonwebtoken::TokenData<Claims>, JwtError> {
-        match decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(self.secret.as_ref()),
-            &Validation::new(self.algorithm),
-        ) {
-            Ok(token_data) => Ok(token_data),
-            Err(err) => {
-                match err.kind() {
-                    jsonwebtoken::errors::ErrorKind::InvalidToken => Err(JwtError::TokenValidationError(err)),
-                    jsonwebtoken::errors::ErrorKind::InvalidIssuer => Err(JwtError::TokenValidationError(err)),
-                    jsonwebtoken::errors::ErrorKind::InvalidAudience => Err(JwtError::TokenValidationError(err)),
-                    jsonwebtoken::errors::ErrorKind::ExpiredSignature => Err(JwtError::TokenValidationError(err)),
-                    jsonwebtoken::errors::ErrorKind::InvalidAlgorithm => Err(JwtError::TokenValidationError(err)),
-                    _ => Err(JwtError::TokenValidationError(err)),
-                }
+    pub fn new(algorithm: JwtAlgorithm) -> Result<Self, JwtError> {
+        JwtConfig::with_keys(JwtKeys::shared()?, algorithm)
+    }
+
+    // JWT_ALGORITHM if it's set, otherwise the strongest algorithm the signing
+    // key supports. Built once at startup so a bad setup stops the server.
+    pub fn from_env() -> Result<Self, JwtError> {
+        let keys = JwtKeys::shared()?;
+
+        match std::env::var("JWT_ALGORITHM") {
+            Ok(name) if !name.trim().is_empty() => JwtConfig::with_keys(keys, name.parse()?),
+            _ => {
+                let algorithm = keys.default_algorithm()?;
+                JwtConfig::with_algorithm(keys, algorithm)
             }
         }
     }
+
+    pub fn with_keys(keys: Arc<JwtKeys>, algorithm: JwtAlgorithm) -> Result<Self, JwtError> {
+        JwtConfig::with_algorithm(keys, Algorithm::try_from(algorithm)?)
+    }
+
+    fn with_algorithm(keys: Arc<JwtKeys>, algorithm: Algorithm) -> Result<Self, JwtError> {
+        // Fail here rather than on the first login
+        keys.signing_key(algorithm)?;
+        Ok(Self { keys, algorithm })
+    }
+
+    pub fn keys(&self) -> &JwtKeys {
+        &self.keys
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn generate_token<C: Serialize>(&self, claims: &C) -> Result<String, JwtError> {
+        let key = self.keys.signing_key(self.algorithm)?;
+
+        let mut header = Header::new(self.algorithm);
+        header.kid = Some(key.kid.clone());
+
+        encode(&header, claims, &key.encoding).map_err(JwtError::TokenCreationError)
+    }
+
+    // Checks a token against the key named by its `kid`, so tokens signed with a
+    // key that's since been replaced keep working for as long as the key is loaded
+    pub fn validate_token(&self, token: &str) -> Result<TokenData<Claims>, JwtError> {
+        decode_with(&self.keys, token, &validation(&self.accepted_algorithms(), &issuer(), &audience()))
+    }
+
+    // Tokens signed with JWT_SECRET only pass when we sign with it too, so the
+    // secret can't mint tokens on a server that uses key pairs
+    pub fn accepted_algorithms(&self) -> Vec<Algorithm> {
+        let mut algorithms = KEY_PAIR_ALGORITHMS.to_vec();
+        if !algorithms.contains(&self.algorithm) {
+            algorithms.push(self.algorithm);
+        }
+        algorithms
+    }
+
     pub fn generate_exp_time(days: usize) -> usize {
         let now = chrono::Utc::now().timestamp();
         now as usize + (days * 24 * 60 * 60)
     }
 }
+
+// The public URL tokens are issued from, JWT_ISSUER. Other services find our
+// keys under it.
+pub fn issuer() -> String {
+    match std::env::var("JWT_ISSUER") {
+        Ok(issuer) => issuer.trim_end_matches('/').to_string(),
+        Err(_) => {
+            let port = std::env::var("PORT").unwrap_or("8080".to_string());
+            format!("http://localhost:{}", port)
+        }
+    }
+}
+
+// Who tokens are for, JWT_AUDIENCE. Services checking our tokens expect it.
+pub fn audience() -> String {
+    std::env::var("JWT_AUDIENCE").unwrap_or(DEFAULT_AUDIENCE.to_string())
+}
+
+// Checks expiry, not-before, issuer and audience, and that they're all present.
+// A token signed with an algorithm outside `algorithms` is rejected.
+pub fn validation(algorithms: &[Algorithm], issuer: &str, audience: &str) -> Validation {
+    let mut validation = Validation::default();
+    validation.algorithms = algorithms.to_vec();
+    validation.set_issuer(&[issuer]);
+    validation.set_audience(&[audience]);
+    validation.set_required_spec_claims(&["sub", "iss", "aud", "nbf", "exp"]);
+    validation.validate_nbf = true;
+    validation
+}
+
+// Decodes any token we signed, like the login state in auth::oauth. The token's
+// algorithm has to suit its key and be one `validation` allows.
+pub fn decode_with<C: DeserializeOwned>(
+    keys: &JwtKeys,
+    token: &str,
+    validation: &Validation,
+) -> Result<TokenData<C>, JwtError> {
+    let header = decode_header(token).map_err(JwtError::TokenValidationError)?;
+
+    let key = header
+        .kid
+        .as_deref()
+        .and_then(|kid| keys.get(kid))
+        .ok_or_else(|| JwtError::TokenValidationError(ErrorKind::InvalidToken.into()))?;
+
+    // The key decides the algorithm, never the token alone
+    if !key.algorithms.contains(&header.alg) || !validation.algorithms.contains(&header.alg) {
+        return Err(JwtError::TokenValidationError(ErrorKind::InvalidAlgorithm.into()));
+    }
+
+    let mut validation = validation.clone();
+    validation.algorithms = vec![header.alg];
+
+    decode::<C>(token, &key.decoding, &validation).map_err(JwtError::TokenValidationError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::keys::JwtKey;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+    fn ec_key(kid: &str) -> JwtKey {
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new()).unwrap();
+        let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref()));
+        JwtKey::from_pem(kid, pem.as_bytes()).unwrap()
+    }
+
+    fn claims() -> Claims {
+        Claims::new("unxid", "1", "session", chrono::Duration::minutes(15))
+    }
+
+    #[test]
+    fn signs_with_the_newest_key_and_accepts_rotated_keys() {
+        let old_key = ec_key("2026-01-01");
+        let old = Arc::new(JwtKeys::new(vec![old_key.clone()], None));
+        let old_token = JwtConfig::with_keys(old, JwtAlgorithm::ES256)
+            .unwrap()
+            .generate_token(&claims())
+            .unwrap();
+
+        let rotated = Arc::new(JwtKeys::new(vec![ec_key("2026-10-19"), old_key], None));
+        let new_token = JwtConfig::with_keys(rotated.clone(), JwtAlgorithm::ES256)
+            .unwrap()
+            .generate_token(&claims())
+            .unwrap();
+
+        let header = decode_header(&new_token).unwrap();
+        assert_eq!(header.alg, Algorithm::ES256);
+        assert_eq!(header.kid.as_deref(), Some("2026-10-19"));
+        let config = JwtConfig::with_keys(rotated, JwtAlgorithm::ES256).unwrap();
+        assert!(config.validate_token(&new_token).is_ok());
+        assert!(config.validate_token(&old_token).is_ok());
+
+        // Once the old key is removed its tokens stop working
+        let retired = Arc::new(JwtKeys::new(vec![ec_key("2026-10-19")], None));
+        let config = JwtConfig::with_keys(retired, JwtAlgorithm::ES256).unwrap();
+        assert!(config.validate_token(&old_token).is_err());
+    }
+
+    #[test]
+    fn defaults_to_the_signing_keys_algorithm() {
+        let ec_keys = Arc::new(JwtKeys::new(vec![ec_key("2026-10-19"), JwtKey::from_secret(b"secret")], None));
+        assert_eq!(ec_keys.default_algorithm().unwrap(), Algorithm::ES256);
+        let config = JwtConfig::with_algorithm(ec_keys.clone(), ec_keys.default_algorithm().unwrap()).unwrap();
+        assert!(config.validate_token(&config.generate_token(&claims()).unwrap()).is_ok());
+
+        let secret_only = JwtKeys::new(vec![JwtKey::from_secret(b"secret")], None);
+        assert_eq!(secret_only.default_algorithm().unwrap(), Algorithm::HS512);
+
+        assert_eq!("es384".parse::<JwtAlgorithm>().unwrap(), JwtAlgorithm::ES384);
+        assert!("none".parse::<JwtAlgorithm>().is_err());
+    }
+
+    #[test]
+    fn rejects_hmac_tokens_unless_signing_with_the_secret() {
+        let keys = Arc::new(JwtKeys::new(vec![ec_key("2026-10-19"), JwtKey::from_secret(b"secret")], None));
+        let config = JwtConfig::with_keys(keys.clone(), JwtAlgorithm::ES256).unwrap();
+        let hmac_config = JwtConfig::with_keys(keys, JwtAlgorithm::HS256).unwrap();
+
+        let hmac_token = hmac_config.generate_token(&claims()).unwrap();
+        assert!(config.validate_token(&hmac_token).is_err());
+
+        // Opted into with JWT_ALGORITHM
+        assert!(hmac_config.validate_token(&hmac_token).is_ok());
+        assert!(hmac_config.validate_token(&config.generate_token(&claims()).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn rejects_algorithms_the_keys_cant_sign() {
+        let keys = Arc::new(JwtKeys::new(vec![ec_key("2026-10-19")], None));
+        assert!(JwtConfig::with_keys(keys.clone(), JwtAlgorithm::RS512).is_err());
+        assert!(JwtConfig::with_keys(keys.clone(), JwtAlgorithm::ES384).is_err());
+        assert!(JwtConfig::with_keys(keys, JwtAlgorithm::ES512).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+// The keys JWTs are signed and checked with. Key pairs are PEM files in
+// JWT_KEYS_DIR (default "keys"), each named after its key id, e.g.
+// "keys/2026-10-19.pem". Tokens carry the id in their `kid` header, so a new key
+// can be added and made the signing key while tokens signed with the old one
+// still validate. Remove the old file once its tokens have expired.
+//
+// RSA keys can be PKCS#1 ("RSA PRIVATE KEY") or PKCS#8 ("PRIVATE KEY"), EC keys
+// PKCS#8 on P-256 or P-384. JWT_SECRET, if set, adds a shared secret for the
+// HS algorithms.
+use super::jwt::JwtError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters, EllipticCurveKeyType, Jwk,
+    JwkSet, KeyAlgorithm, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use ring::rand::SystemRandom;
+use ring::signature::{
+    self, EcdsaKeyPair, KeyPair, RsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING, ECDSA_P384_SHA384_FIXED_SIGNING,
+};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+const DEFAULT_KEYS_DIR: &str = "keys";
+pub const SECRET_KEY_ID: &str = "secret";
+
+const RSA_ALGORITHMS: &[Algorithm] = &[Algorithm::RS256, Algorithm::RS384, Algorithm::RS512];
+const HMAC_ALGORITHMS: &[Algorithm] = &[Algorithm::HS256, Algorithm::HS384, Algorithm::HS512];
+
+#[derive(Clone)]
+pub struct JwtKey {
+    pub kid: String,
+    // What the key can sign. An EC key only signs for its own curve.
+    pub algorithms: &'static [Algorithm],
+    pub(super) encoding: EncodingKey,
+    pub(super) decoding: DecodingKey,
+    // The public half, for the JWKS. None for the shared secret.
+    pub jwk: Option<Jwk>,
+}
+
+impl std::fmt::Debug for JwtKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("JwtKey")
+            .field("kid", &self.kid)
+            .field("algorithms", &self.algorithms)
+            .finish()
+    }
+}
+
+impl JwtKey {
+    pub fn from_pem(kid: &str, data: &[u8]) -> Result<Self, JwtError> {
+        let invalid = |e: &dyn std::fmt::Display| JwtError::ConfigurationError(format!("Key {}: {}", kid, e));
+
+        let pem = pem::parse(data).map_err(|e| invalid(&e))?;
+        let der = pem.contents();
+
+        let rsa_key = match pem.tag() {
+            "RSA PRIVATE KEY" => Some(RsaKeyPair::from_der(der)),
+            "PRIVATE KEY" => RsaKeyPair::from_pkcs8(der).ok().map(Ok),
+            "EC PRIVATE KEY" => {
+                return Err(invalid(
+                    &"SEC1 EC keys aren't supported, convert it with `openssl pkcs8 -topk8 -nocrypt`",
+                ))
+            }
+            tag => return Err(invalid(&format!("\"{}\" isn't a private key", tag))),
+        };
+
+        if let Some(rsa_key) = rsa_key {
+            let rsa_key = rsa_key.map_err(|e| invalid(&e))?;
+            let public = signature::RsaPublicKeyComponents::<Vec<u8>>::from(rsa_key.public());
+
+            return Ok(JwtKey {
+                kid: kid.to_string(),
+                algorithms: RSA_ALGORITHMS,
+                encoding: EncodingKey::from_rsa_pem(data).map_err(|e| invalid(&e))?,
+                decoding: DecodingKey::from_rsa_raw_components(&public.n, &public.e),
+                jwk: Some(Jwk {
+                    common: common_parameters(kid, None),
+                    algorithm: AlgorithmParameters::RSA(RSAKeyParameters {
+                        key_type: RSAKeyType::RSA,
+                        n: URL_SAFE_NO_PAD.encode(&public.n),
+                        e: URL_SAFE_NO_PAD.encode(&public.e),
+                    }),
+                }),
+            });
+        }
+
+        let curves = [
+            (&ECDSA_P256_SHA256_FIXED_SIGNING, &[Algorithm::ES256], EllipticCurve::P256, KeyAlgorithm::ES256),
+            (&ECDSA_P384_SHA384_FIXED_SIGNING, &[Algorithm::ES384], EllipticCurve::P384, KeyAlgorithm::ES384),
+        ];
+
+        for (signing, algorithms, curve, key_algorithm) in curves {
+            if let Ok(ec_key) = EcdsaKeyPair::from_pkcs8(signing, der, &SystemRandom::new()) {
+                // 0x04 followed by the x and y coordinates
+                let point = ec_key.public_key().as_ref();
+                let (x, y) = point[1..].split_at((point.len() - 1) / 2);
+
+                return Ok(JwtKey {
+                    kid: kid.to_string(),
+                    algorithms,
+                    encoding: EncodingKey::from_ec_pem(data).map_err(|e| invalid(&e))?,
+                    // The uncompressed point, as jsonwebtoken expects
+                    decoding: DecodingKey::from_ec_der(point),
+                    jwk: Some(Jwk {
+                        common: common_parameters(kid, Some(key_algorithm)),
+                        algorithm: AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                            key_type: EllipticCurveKeyType::EC,
+                            curve,
+                            x: URL_SAFE_NO_PAD.encode(x),
+                            y: URL_SAFE_NO_PAD.encode(y),
+                        }),
+                    }),
+                });
+            }
+        }
+
+        Err(invalid(&"Not an RSA, P-256 or P-384 private key"))
+    }
+
+    pub fn from_secret(secret: &[u8]) -> Self {
+        JwtKey {
+            kid: SECRET_KEY_ID.to_string(),
+            algorithms: HMAC_ALGORITHMS,
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+}
+
+// An RSA key can sign with any of the RS algorithms, so its `alg` is left out
+fn common_parameters(kid: &str, key_algorithm: Option<KeyAlgorithm>) -> CommonParameters {
+    CommonParameters {
+        public_key_use: Some(PublicKeyUse::Signature),
+        key_algorithm,
+        key_id: Some(kid.to_string()),
+        ..CommonParameters::default()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct JwtKeys {
+    // Sorted by key id
+    keys: Vec<JwtKey>,
+    // Key id to sign with, JWT_SIGNING_KEY
+    signing_kid: Option<String>,
+}
+
+static SHARED_KEYS: OnceLock<Result<Arc<JwtKeys>, String>> = OnceLock::new();
+
+impl JwtKeys {
+    pub fn new(mut keys: Vec<JwtKey>, signing_kid: Option<String>) -> Self {
+        keys.sort_by(|a, b| a.kid.cmp(&b.kid));
+        JwtKeys { keys, signing_kid }
+    }
+
+    // Loaded from the environment once and kept for the life of the process
+    pub fn shared() -> Result<Arc<JwtKeys>, JwtError> {
+        SHARED_KEYS
+            .get_or_init(|| JwtKeys::from_env().map(Arc::new).map_err(|e| e.to_string()))
+            .clone()
+            .map_err(JwtError::ConfigurationError)
+    }
+
+    pub fn from_env() -> Result<Self, JwtError> {
+        let dir = std::env::var("JWT_KEYS_DIR").unwrap_or(DEFAULT_KEYS_DIR.to_string());
+
+        let mut keys = match Path::new(&dir).is_dir() {
+            true => JwtKeys::load_dir(Path::new(&dir))?,
+            false => Vec::new(),
+        };
+
+        if let Ok(secret) = std::env::var("JWT_SECRET") {
+            keys.push(JwtKey::from_secret(secret.as_bytes()));
+        }
+
+        if keys.is_empty() {
+            return Err(JwtError::ConfigurationError(format!(
+                "No JWT keys in {} and JWT_SECRET isn't set",
+                dir
+            )));
+        }
+
+        Ok(JwtKeys::new(keys, std::env::var("JWT_SIGNING_KEY").ok()))
+    }
+
+    fn load_dir(dir: &Path) -> Result<Vec<JwtKey>, JwtError> {
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| JwtError::ConfigurationError(format!("Reading {}: {}", dir.display(), e)))?;
+
+        let mut keys = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|extension| extension != "pem") {
+                continue;
+            }
+
+            let kid = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let data = std::fs::read(&path)
+                .map_err(|e| JwtError::ConfigurationError(format!("Reading {}: {}", path.display(), e)))?;
+            keys.push(JwtKey::from_pem(&kid, &data)?);
+        }
+
+        Ok(keys)
+    }
+
+    pub fn get(&self, kid: &str) -> Option<&JwtKey> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+
+    pub fn keys(&self) -> &[JwtKey] {
+        &self.keys
+    }
+
+    // The public keys, for GET /.well-known/jwks.json
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().filter_map(|key| key.jwk.clone()).collect(),
+        }
+    }
+
+    // Algorithms the public keys verify, for the discovery document
+    pub fn public_algorithms(&self) -> Vec<Algorithm> {
+        let mut algorithms: Vec<Algorithm> = Vec::new();
+        for key in self.keys.iter().filter(|key| key.jwk.is_some()) {
+            for algorithm in key.algorithms {
+                if !algorithms.contains(algorithm) {
+                    algorithms.push(*algorithm);
+                }
+            }
+        }
+        algorithms
+    }
+
+    // What to sign with when JWT_ALGORITHM isn't set: the strongest algorithm of
+    // JWT_SIGNING_KEY, or else of the last key pair. The shared secret is only
+    // picked when there are no key pairs.
+    pub fn default_algorithm(&self) -> Result<Algorithm, JwtError> {
+        let key = match &self.signing_kid {
+            Some(kid) => self
+                .get(kid)
+                .ok_or_else(|| JwtError::ConfigurationError(format!("JWT_SIGNING_KEY {} isn't loaded", kid)))?,
+            None => self
+                .keys
+                .iter()
+                .rev()
+                .find(|key| key.jwk.is_some())
+                .or_else(|| self.get(SECRET_KEY_ID))
+                .ok_or_else(|| JwtError::ConfigurationError("No JWT keys loaded".to_string()))?,
+        };
+
+        key.algorithms
+            .last()
+            .copied()
+            .ok_or_else(|| JwtError::ConfigurationError(format!("Key {} can't sign anything", key.kid)))
+    }
+
+    // JWT_SIGNING_KEY when it's set, otherwise the last key id that can sign
+    // with the algorithm. Naming keys by date makes that the newest.
+    pub fn signing_key(&self, algorithm: Algorithm) -> Result<&JwtKey, JwtError> {
+        let key = match &self.signing_kid {
+            Some(kid) if !HMAC_ALGORITHMS.contains(&algorithm) => self
+                .get(kid)
+                .ok_or_else(|| JwtError::ConfigurationError(format!("JWT_SIGNING_KEY {} isn't loaded", kid)))?,
+            _ => self
+                .keys
+                .iter()
+                .rev()
+                .find(|key| key.algorithms.contains(&algorithm))
+                .ok_or_else(|| JwtError::ConfigurationError(format!("No key can sign {:?}", algorithm)))?,
+        };
+
+        match key.algorithms.contains(&algorithm) {
+            true => Ok(key),
+            false => Err(JwtError::ConfigurationError(format!(
+                "Key {} can't sign {:?}",
+                key.kid, algorithm
+            ))),
+        }
+    }
+}
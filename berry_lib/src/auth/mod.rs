@@ -1 +1,7 @@
+pub mod envelope;
+pub mod jwks;
 pub mod jwt;
+pub mod keys;
+pub mod rate_limit;
+pub mod rbac;
+pub mod oauth;
@@ -0,0 +1,134 @@
+// The pieces of the Twitch login flow we generate ourselves. GET
+// /auth/twitch/authorize starts a login with a nonce and a PKCE verifier, which
+// are stored (see db::twitch_login_attempts), and hands the browser a signed
+// `state` naming the nonce. Twitch sends the state back with the code, so a
+// code only gets exchanged for a login we started, and only once.
+use super::jwt::{self, JwtConfig, JwtError};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+
+// Long enough to sign in, and to approve the scopes on first login
+pub const LOGIN_STATE_LIFETIME: chrono::Duration = chrono::Duration::minutes(10);
+
+// Keeps access tokens and login states from being mistaken for each other
+const STATE_AUDIENCE: &str = "berry-twitch-login";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginState {
+    // The login attempt's nonce
+    pub sub: String,
+    pub iss: String,
+    pub aud: String,
+    pub iat: usize,
+    pub nbf: usize,
+    pub exp: usize,
+}
+
+impl LoginState {
+    pub fn new(nonce: &str) -> Self {
+        let now = chrono::Utc::now().timestamp() as usize;
+
+        LoginState {
+            sub: nonce.to_string(),
+            iss: jwt::issuer(),
+            aud: STATE_AUDIENCE.to_string(),
+            iat: now,
+            nbf: now,
+            exp: now + LOGIN_STATE_LIFETIME.num_seconds() as usize,
+        }
+    }
+
+    pub fn sign(&self, config: &JwtConfig) -> Result<String, JwtError> {
+        config.generate_token(self)
+    }
+
+    // Checks the signature and expiry and returns the nonce
+    pub fn verify(config: &JwtConfig, state: &str) -> Result<String, JwtError> {
+        let validation = jwt::validation(&config.accepted_algorithms(), &jwt::issuer(), STATE_AUDIENCE);
+        jwt::decode_with::<LoginState>(config.keys(), state, &validation).map(|data| data.claims.sub)
+    }
+}
+
+pub fn new_nonce() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; 16]>())
+}
+
+// RFC 7636 with the S256 method: Twitch gets the challenge when the user is
+// sent to sign in, and the verifier with the code exchange
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl Pkce {
+    pub fn new() -> Self {
+        Pkce::from_verifier(&URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>()))
+    }
+
+    pub fn from_verifier(verifier: &str) -> Self {
+        Pkce {
+            verifier: verifier.to_string(),
+            challenge: URL_SAFE_NO_PAD.encode(digest(&SHA256, verifier.as_bytes())),
+        }
+    }
+}
+
+impl Default for Pkce {
+    fn default() -> Self {
+        Pkce::new()
+    }
+}
+
+// Scopes we asked for that the user didn't grant, in the order asked
+pub fn missing_scopes(requested: &[String], granted: &[String]) -> Vec<String> {
+    requested
+        .iter()
+        .filter(|scope| !granted.contains(scope))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::jwt::{Claims, JwtAlgorithm};
+    use crate::auth::keys::{JwtKey, JwtKeys};
+    use std::sync::Arc;
+
+    #[test]
+    fn states_verify_and_access_tokens_dont_pass_as_states() {
+        let keys = Arc::new(JwtKeys::new(vec![JwtKey::from_secret(b"secret")], None));
+        let config = JwtConfig::with_keys(keys, JwtAlgorithm::HS256).unwrap();
+
+        let state = LoginState::new("nonce").sign(&config).unwrap();
+        assert_eq!(LoginState::verify(&config, &state).unwrap(), "nonce");
+
+        let access_token = config
+            .generate_token(&Claims::new("unxid", "1", "session", chrono::Duration::minutes(15)))
+            .unwrap();
+        assert!(LoginState::verify(&config, &access_token).is_err());
+
+        let other_keys = Arc::new(JwtKeys::new(vec![JwtKey::from_secret(b"other")], None));
+        let other_config = JwtConfig::with_keys(other_keys, JwtAlgorithm::HS256).unwrap();
+        assert!(LoginState::verify(&other_config, &state).is_err());
+    }
+
+    #[test]
+    fn pkce_challenge_is_the_hashed_verifier() {
+        let pkce = Pkce::from_verifier("abcdefghijklmnopqrstuvwxyz0123456789-._~ABCDE");
+        assert_eq!(pkce.challenge, "e5A_4YqDBAOkeozejiAzw4Fzm_mqcrVsstgid63knfc");
+
+        let fresh = Pkce::new();
+        assert_eq!(fresh.verifier.len(), 43);
+        assert_ne!(fresh.verifier, Pkce::new().verifier);
+    }
+
+    #[test]
+    fn reports_scopes_that_werent_granted() {
+        let requested = vec!["chat:read".to_string(), "chat:edit".to_string(), "user:manage:whispers".to_string()];
+        let granted = vec!["chat:read".to_string()];
+        assert_eq!(missing_scopes(&requested, &granted), vec!["chat:edit", "user:manage:whispers"]);
+    }
+}
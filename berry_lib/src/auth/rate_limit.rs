@@ -0,0 +1,80 @@
+// Per-key request limits, counted in fixed one-minute windows. Counts live in
+// memory, so each server process enforces its own limit.
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+// Past this many tracked keys, finished windows are dropped on the next check
+const PRUNE_AFTER: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limited {
+    pub limit: u32,
+    pub retry_after: Duration,
+}
+
+#[derive(Default)]
+pub struct RateLimiter {
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    // Counts a request against `key`, or says how long until the next is allowed
+    pub fn check(&self, key: &str, per_minute: u32) -> Result<u32, Limited> {
+        self.check_at(key, per_minute, Instant::now())
+    }
+
+    // Ok holds how many requests are left in the current window
+    fn check_at(&self, key: &str, per_minute: u32, now: Instant) -> Result<u32, Limited> {
+        let Ok(mut windows) = self.windows.lock() else {
+            return Ok(per_minute);
+        };
+
+        if windows.len() > PRUNE_AFTER {
+            windows.retain(|_, (started, _)| now.duration_since(*started) < WINDOW);
+        }
+
+        let (started, count) = windows.entry(key.to_string()).or_insert((now, 0));
+        if now.duration_since(*started) >= WINDOW {
+            *started = now;
+            *count = 0;
+        }
+
+        if *count >= per_minute {
+            return Err(Limited {
+                limit: per_minute,
+                retry_after: WINDOW.saturating_sub(now.duration_since(*started)),
+            });
+        }
+
+        *count += 1;
+        Ok(per_minute - *count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_each_key_within_its_window() {
+        let limiter = RateLimiter::default();
+        let start = Instant::now();
+
+        assert_eq!(limiter.check_at("a", 2, start), Ok(1));
+        assert_eq!(limiter.check_at("a", 2, start), Ok(0));
+        assert_eq!(
+            limiter.check_at("a", 2, start + Duration::from_secs(45)),
+            Err(Limited {
+                limit: 2,
+                retry_after: Duration::from_secs(15),
+            })
+        );
+
+        // Other keys have their own count, and a new window starts afresh
+        assert_eq!(limiter.check_at("b", 2, start), Ok(1));
+        assert_eq!(limiter.check_at("a", 2, start + WINDOW), Ok(1));
+    }
+}
@@ -0,0 +1,164 @@
+// Who may manage a channel from the dashboard. The broadcaster holds every
+// permission on their own channel and can grant other Berry users a role on
+// it, so their mods get in without sharing the broadcaster's login. Admins hold
+// every permission everywhere.
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Broadcaster,
+    // Granted by the broadcaster
+    Editor,
+    Moderator,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    // Commands, counters and timers
+    ManageCommands,
+    // Polls, giveaways and points, the things mods run during a stream
+    ManageModeration,
+    ViewAnalytics,
+    // Granting and revoking roles on the channel
+    ManageAccess,
+    // Talking in chat as the channel's bot
+    SendChat,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Broadcaster => "broadcaster",
+            Role::Editor => "editor",
+            Role::Moderator => "moderator",
+        }
+    }
+
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::Admin | Role::Broadcaster => &[
+                Permission::ManageCommands,
+                Permission::ManageModeration,
+                Permission::ViewAnalytics,
+                Permission::ManageAccess,
+                Permission::SendChat,
+            ],
+            Role::Editor => &[
+                Permission::ManageCommands,
+                Permission::ManageModeration,
+                Permission::ViewAnalytics,
+                Permission::SendChat,
+            ],
+            Role::Moderator => &[
+                Permission::ManageModeration,
+                Permission::ViewAnalytics,
+                Permission::SendChat,
+            ],
+        }
+    }
+
+    pub fn allows(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+
+    // Whether a broadcaster can hand this role out
+    pub fn is_grantable(&self) -> bool {
+        matches!(self, Role::Editor | Role::Moderator)
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role.to_lowercase().as_str() {
+            "admin" => Ok(Role::Admin),
+            "broadcaster" => Ok(Role::Broadcaster),
+            "editor" => Ok(Role::Editor),
+            "moderator" => Ok(Role::Moderator),
+            _ => Err(format!("Unknown role \"{}\", use editor or moderator", role)),
+        }
+    }
+}
+
+impl Permission {
+    pub const ALL: &'static [Permission] = &[
+        Permission::ManageCommands,
+        Permission::ManageModeration,
+        Permission::ViewAnalytics,
+        Permission::ManageAccess,
+        Permission::SendChat,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ManageCommands => "manage_commands",
+            Permission::ManageModeration => "manage_moderation",
+            Permission::ViewAnalytics => "view_analytics",
+            Permission::ManageAccess => "manage_access",
+            Permission::SendChat => "send_chat",
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = String;
+
+    fn from_str(permission: &str) -> Result<Self, Self::Err> {
+        Permission::ALL
+            .iter()
+            .find(|known| known.as_str() == permission.to_lowercase())
+            .copied()
+            .ok_or_else(|| format!("Unknown permission \"{}\"", permission))
+    }
+}
+
+// The role a user holds on a channel: broadcaster on their own, then admin,
+// then whatever they were granted
+pub fn role_on_channel(login: &str, channel: &str, is_admin: bool, granted: Option<Role>) -> Option<Role> {
+    if login.eq_ignore_ascii_case(channel) {
+        Some(Role::Broadcaster)
+    } else if is_admin {
+        Some(Role::Admin)
+    } else {
+        granted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn granted_roles_only_reach_their_permissions() {
+        assert!(Role::Editor.allows(Permission::ManageCommands));
+        assert!(!Role::Editor.allows(Permission::ManageAccess));
+        assert!(Role::Moderator.allows(Permission::ManageModeration));
+        assert!(!Role::Moderator.allows(Permission::ManageCommands));
+        assert!(Role::Broadcaster.allows(Permission::ManageAccess));
+
+        assert!(Role::Moderator.is_grantable());
+        assert!(!Role::Admin.is_grantable());
+        assert!("Editor".parse::<Role>().unwrap().is_grantable());
+        assert!("owner".parse::<Role>().is_err());
+
+        for permission in Permission::ALL {
+            assert_eq!(permission.as_str().parse::<Permission>(), Ok(*permission));
+        }
+        assert!("send_chats".parse::<Permission>().is_err());
+    }
+
+    #[test]
+    fn broadcasters_own_their_channel_and_admins_every_channel() {
+        assert_eq!(role_on_channel("Berry", "berry", false, None), Some(Role::Broadcaster));
+        assert_eq!(role_on_channel("berry", "berry", true, None), Some(Role::Broadcaster));
+        assert_eq!(role_on_channel("staff", "berry", true, Some(Role::Moderator)), Some(Role::Admin));
+        assert_eq!(role_on_channel("mod", "berry", false, Some(Role::Moderator)), Some(Role::Moderator));
+        assert_eq!(role_on_channel("someone", "berry", false, None), None);
+    }
+}
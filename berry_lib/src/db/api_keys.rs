@@ -0,0 +1,180 @@
+// API keys for machine clients, an alternative to signing in. A key acts as the
+// user who made it, limited to its scopes. Keys are random and only stored as
+// SHA-256 hashes; the key itself is returned once, when it's created.
+use crate::auth::rbac::Permission;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use ring::digest::{digest, SHA256};
+use serde::Serialize;
+use sqlx::PgPool;
+
+// Every key starts with this, which tells them apart from JWTs
+pub const KEY_PREFIX: &str = "berry_";
+pub const MAX_KEYS_PER_USER: i64 = 25;
+pub const DEFAULT_RATE_LIMIT: i32 = 60;
+pub const MAX_RATE_LIMIT: i32 = 600;
+
+// How much of the key is kept in the clear to recognise it by
+const SHOWN_PREFIX_LEN: usize = KEY_PREFIX.len() + 6;
+
+const KEY_COLUMNS: &str =
+    "id, unxid, twitch_id, name, prefix, scopes, rate_limit, expires_at, last_used_at, created_at";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ApiKey {
+    pub id: String,
+    #[serde(skip)]
+    pub unxid: String,
+    #[serde(skip)]
+    pub twitch_id: String,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    // Requests per minute
+    pub rate_limit: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiKey {
+    pub fn allows(&self, permission: Permission) -> bool {
+        self.scopes.iter().any(|scope| scope == permission.as_str())
+    }
+}
+
+pub fn is_api_key(token: &str) -> bool {
+    token.starts_with(KEY_PREFIX)
+}
+
+fn new_key() -> (String, Vec<u8>) {
+    let key = format!("{}{}", KEY_PREFIX, URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>()));
+    let hash = hash_key(&key);
+    (key, hash)
+}
+
+// Keys carry 256 random bits, so a fast hash is enough
+fn hash_key(key: &str) -> Vec<u8> {
+    digest(&SHA256, key.as_bytes()).as_ref().to_vec()
+}
+
+fn scope_names(scopes: &[Permission]) -> Vec<&'static str> {
+    scopes.iter().map(Permission::as_str).collect()
+}
+
+pub async fn count_api_keys(pool: &PgPool, unxid: &str) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM api_keys WHERE unxid = $1")
+        .bind(unxid)
+        .fetch_one(pool)
+        .await
+}
+
+// Makes a key and returns it with the key itself, which can't be recovered later
+pub async fn create_api_key(
+    pool: &PgPool,
+    unxid: &str,
+    twitch_id: &str,
+    name: &str,
+    scopes: &[Permission],
+    rate_limit: i32,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<(ApiKey, String), sqlx::Error> {
+    let id = format!("{:032x}", rand::random::<u128>());
+    let (key, hash) = new_key();
+
+    let record = sqlx::query_as::<_, ApiKey>(&format!(
+        "INSERT INTO api_keys (id, unxid, twitch_id, name, prefix, key_hash, scopes, rate_limit, expires_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+         RETURNING {}",
+        KEY_COLUMNS
+    ))
+    .bind(&id)
+    .bind(unxid)
+    .bind(twitch_id)
+    .bind(name)
+    .bind(&key[..SHOWN_PREFIX_LEN])
+    .bind(&hash)
+    .bind(scope_names(scopes))
+    .bind(rate_limit)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await?;
+
+    Ok((record, key))
+}
+
+pub async fn list_api_keys(pool: &PgPool, unxid: &str) -> Result<Vec<ApiKey>, sqlx::Error> {
+    sqlx::query_as::<_, ApiKey>(&format!(
+        "SELECT {} FROM api_keys WHERE unxid = $1 ORDER BY created_at DESC",
+        KEY_COLUMNS
+    ))
+    .bind(unxid)
+    .fetch_all(pool)
+    .await
+}
+
+// Changes whatever is given and leaves the rest. None when the user has no such key.
+pub async fn update_api_key(
+    pool: &PgPool,
+    unxid: &str,
+    id: &str,
+    name: Option<&str>,
+    scopes: Option<&[Permission]>,
+    rate_limit: Option<i32>,
+) -> Result<Option<ApiKey>, sqlx::Error> {
+    sqlx::query_as::<_, ApiKey>(&format!(
+        "UPDATE api_keys
+         SET name = COALESCE($3, name), scopes = COALESCE($4, scopes), rate_limit = COALESCE($5, rate_limit)
+         WHERE id = $1 AND unxid = $2
+         RETURNING {}",
+        KEY_COLUMNS
+    ))
+    .bind(id)
+    .bind(unxid)
+    .bind(name)
+    .bind(scopes.map(scope_names))
+    .bind(rate_limit)
+    .fetch_optional(pool)
+    .await
+}
+
+// Returns false when the user has no such key
+pub async fn delete_api_key(pool: &PgPool, unxid: &str, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM api_keys WHERE id = $1 AND unxid = $2")
+        .bind(id)
+        .bind(unxid)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// The key presented with a request, marked as used. None when it's unknown or expired.
+pub async fn authenticate(pool: &PgPool, key: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+    sqlx::query_as::<_, ApiKey>(&format!(
+        "UPDATE api_keys SET last_used_at = NOW()
+         WHERE key_hash = $1 AND (expires_at IS NULL OR expires_at > NOW())
+         RETURNING {}",
+        KEY_COLUMNS
+    ))
+    .bind(hash_key(key))
+    .fetch_optional(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_recognisable_and_hashed() {
+        let (key, hash) = new_key();
+
+        assert!(is_api_key(&key));
+        assert!(!is_api_key("eyJhbGciOiJSUzUxMiJ9.e30.sig"));
+        assert!(key.len() > SHOWN_PREFIX_LEN + 32);
+        assert_eq!(hash, hash_key(&key));
+        assert_ne!(new_key().0, key);
+    }
+}
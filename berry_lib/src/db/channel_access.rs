@@ -0,0 +1,129 @@
+// Admins and the roles broadcasters grant on their channels, see auth::rbac
+use crate::auth::rbac::Role;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ChannelGrant {
+    pub channel: String,
+    // The grantee's Twitch login
+    pub login: String,
+    pub role: String,
+    pub granted_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+const GRANT_COLUMNS: &str = "g.channel, u.twitch_login AS login, g.role, g.granted_by, g.created_at";
+
+pub async fn is_admin(pool: &PgPool, unxid: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM user_roles WHERE unxid = $1 AND role = 'admin')")
+        .bind(unxid)
+        .fetch_one(pool)
+        .await
+}
+
+// Returns false when there's no Berry user with that login
+pub async fn set_admin(pool: &PgPool, login: &str, admin: bool) -> Result<bool, sqlx::Error> {
+    let Some(unxid) = unxid_for_login(pool, login).await? else {
+        return Ok(false);
+    };
+
+    let query = match admin {
+        true => "INSERT INTO user_roles (unxid, role) VALUES ($1, 'admin') ON CONFLICT DO NOTHING",
+        false => "DELETE FROM user_roles WHERE unxid = $1 AND role = 'admin'",
+    };
+    sqlx::query(query).bind(&unxid).execute(pool).await?;
+
+    Ok(true)
+}
+
+pub async fn granted_role(pool: &PgPool, channel: &str, unxid: &str) -> Result<Option<Role>, sqlx::Error> {
+    let role: Option<String> = sqlx::query_scalar("SELECT role FROM channel_grants WHERE channel = $1 AND unxid = $2")
+        .bind(channel)
+        .bind(unxid)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(role.and_then(|role| role.parse().ok()))
+}
+
+pub async fn list_grants(pool: &PgPool, channel: &str) -> Result<Vec<ChannelGrant>, sqlx::Error> {
+    sqlx::query_as::<_, ChannelGrant>(&format!(
+        "SELECT {} FROM channel_grants g JOIN user_data u ON u.unxid = g.unxid
+         WHERE g.channel = $1 ORDER BY u.twitch_login",
+        GRANT_COLUMNS
+    ))
+    .bind(channel)
+    .fetch_all(pool)
+    .await
+}
+
+// Channels the user was granted a role on
+pub async fn list_user_grants(pool: &PgPool, unxid: &str) -> Result<Vec<ChannelGrant>, sqlx::Error> {
+    sqlx::query_as::<_, ChannelGrant>(&format!(
+        "SELECT {} FROM channel_grants g JOIN user_data u ON u.unxid = g.unxid
+         WHERE g.unxid = $1 ORDER BY g.channel",
+        GRANT_COLUMNS
+    ))
+    .bind(unxid)
+    .fetch_all(pool)
+    .await
+}
+
+// Gives the user a role on the channel, replacing any they had. None when
+// there's no Berry user with that login; they have to sign in once first.
+pub async fn grant(
+    pool: &PgPool,
+    channel: &str,
+    login: &str,
+    role: Role,
+    granted_by: &str,
+) -> Result<Option<ChannelGrant>, sqlx::Error> {
+    let Some(unxid) = unxid_for_login(pool, login).await? else {
+        return Ok(None);
+    };
+
+    sqlx::query(
+        "INSERT INTO channel_grants (channel, unxid, role, granted_by) VALUES ($1, $2, $3, $4)
+         ON CONFLICT (channel, unxid) DO UPDATE
+         SET role = EXCLUDED.role, granted_by = EXCLUDED.granted_by, created_at = NOW()",
+    )
+    .bind(channel)
+    .bind(&unxid)
+    .bind(role.as_str())
+    .bind(granted_by)
+    .execute(pool)
+    .await?;
+
+    sqlx::query_as::<_, ChannelGrant>(&format!(
+        "SELECT {} FROM channel_grants g JOIN user_data u ON u.unxid = g.unxid
+         WHERE g.channel = $1 AND g.unxid = $2",
+        GRANT_COLUMNS
+    ))
+    .bind(channel)
+    .bind(&unxid)
+    .fetch_optional(pool)
+    .await
+}
+
+// Returns false when the user held no role on the channel
+pub async fn revoke(pool: &PgPool, channel: &str, login: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM channel_grants
+         WHERE channel = $1 AND unxid IN (SELECT unxid FROM user_data WHERE LOWER(twitch_login) = LOWER($2))",
+    )
+    .bind(channel)
+    .bind(login)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+async fn unxid_for_login(pool: &PgPool, login: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT unxid FROM user_data WHERE LOWER(twitch_login) = LOWER($1)")
+        .bind(login)
+        .fetch_optional(pool)
+        .await
+}
@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+const MAX_NAME_LENGTH: usize = 30;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct CounterRecord {
+    pub id: i64,
+    pub channel: String,
+    pub name: String,
+    pub value: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CounterInput {
+    pub name: String,
+    pub value: Option<i64>,
+}
+
+// Counter names are lowercase letters, digits and underscores, so they can double
+// as chat commands ("!deaths")
+pub fn normalize_counter_name(name: &str) -> Option<String> {
+    let name = name.trim().trim_start_matches('!').to_lowercase();
+
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    valid.then_some(name)
+}
+
+pub async fn list_counters(pool: &PgPool, channel: &str) -> Result<Vec<CounterRecord>, sqlx::Error> {
+    sqlx::query_as::<_, CounterRecord>("SELECT * FROM counters WHERE channel = $1 ORDER BY name")
+        .bind(channel)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_counter(
+    pool: &PgPool,
+    channel: &str,
+    name: &str,
+) -> Result<Option<CounterRecord>, sqlx::Error> {
+    sqlx::query_as::<_, CounterRecord>("SELECT * FROM counters WHERE channel = $1 AND name = $2")
+        .bind(channel)
+        .bind(name)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn create_counter(
+    pool: &PgPool,
+    channel: &str,
+    input: &CounterInput,
+) -> Result<CounterRecord, sqlx::Error> {
+    sqlx::query_as::<_, CounterRecord>(
+        "INSERT INTO counters (channel, name, value) VALUES ($1, $2, $3) RETURNING *",
+    )
+    .bind(channel)
+    .bind(&input.name)
+    .bind(input.value.unwrap_or(0))
+    .fetch_one(pool)
+    .await
+}
+
+// Adds to the counter in a single statement, creating it at zero first when it
+// doesn't exist yet
+pub async fn add_to_counter(
+    pool: &PgPool,
+    channel: &str,
+    name: &str,
+    delta: i64,
+) -> Result<CounterRecord, sqlx::Error> {
+    sqlx::query_as::<_, CounterRecord>(
+        "INSERT INTO counters (channel, name, value) VALUES ($1, $2, $3)
+         ON CONFLICT (channel, name)
+         DO UPDATE SET value = counters.value + EXCLUDED.value, updated_at = NOW()
+         RETURNING *",
+    )
+    .bind(channel)
+    .bind(name)
+    .bind(delta)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn set_counter(
+    pool: &PgPool,
+    channel: &str,
+    name: &str,
+    value: i64,
+) -> Result<CounterRecord, sqlx::Error> {
+    sqlx::query_as::<_, CounterRecord>(
+        "INSERT INTO counters (channel, name, value) VALUES ($1, $2, $3)
+         ON CONFLICT (channel, name)
+         DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()
+         RETURNING *",
+    )
+    .bind(channel)
+    .bind(name)
+    .bind(value)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn delete_counter(pool: &PgPool, channel: &str, name: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM counters WHERE channel = $1 AND name = $2")
+        .bind(channel)
+        .bind(name)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
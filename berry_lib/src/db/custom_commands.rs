@@ -0,0 +1,270 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::twitch::cooldown::MAX_COOLDOWN;
+use crate::twitch::permission::PermissionLevel;
+use crate::twitch::template::Template;
+use sqlx::PgPool;
+
+const MAX_NAME_LENGTH: usize = 50;
+const MAX_RESPONSE_LENGTH: usize = 500;
+const MAX_ALIASES: usize = 10;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct CustomCommandRecord {
+    pub id: i64,
+    pub channel: String,
+    pub name: String,
+    pub response: String,
+    pub enabled: bool,
+    pub created_by: String,
+    pub use_count: i64,
+    pub aliases: Vec<String>,
+    pub permission: String,
+    pub global_cooldown_secs: i32,
+    pub user_cooldown_secs: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomCommandInput {
+    pub name: String,
+    pub response: String,
+    pub enabled: Option<bool>,
+    pub aliases: Option<Vec<String>>,
+    pub permission: Option<PermissionLevel>,
+    pub global_cooldown_secs: Option<i32>,
+    pub user_cooldown_secs: Option<i32>,
+}
+
+// Normalizes the name and aliases and checks the response parses, for the API and imports
+pub fn validate_command_input(mut input: CustomCommandInput) -> Result<CustomCommandInput, String> {
+    input.name = input.name.trim().trim_start_matches('!').to_lowercase();
+    input.response = input.response.trim().to_string();
+
+    if input.name.is_empty() || input.name.chars().any(char::is_whitespace) {
+        return Err("Command name must be a single word".to_string());
+    }
+
+    if input.name.len() > MAX_NAME_LENGTH {
+        return Err(format!("Command name must be at most {} characters", MAX_NAME_LENGTH));
+    }
+
+    if input.response.is_empty() {
+        return Err("Command response is required".to_string());
+    }
+
+    if input.response.len() > MAX_RESPONSE_LENGTH {
+        return Err(format!("Command response must be at most {} characters", MAX_RESPONSE_LENGTH));
+    }
+
+    if let Err(e) = Template::parse(&input.response) {
+        return Err(format!("Invalid command response: {}", e));
+    }
+
+    if let Some(aliases) = input.aliases.take() {
+        input.aliases = Some(validate_aliases(&input.name, aliases)?);
+    }
+
+    for cooldown in [input.global_cooldown_secs, input.user_cooldown_secs].into_iter().flatten() {
+        if !(0..=MAX_COOLDOWN.as_secs() as i32).contains(&cooldown) {
+            return Err(format!(
+                "Cooldowns must be between 0 and {} seconds",
+                MAX_COOLDOWN.as_secs()
+            ));
+        }
+    }
+
+    Ok(input)
+}
+
+fn validate_aliases(name: &str, aliases: Vec<String>) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+
+    for alias in aliases {
+        let alias = alias.trim().trim_start_matches('!').to_lowercase();
+
+        if alias.is_empty() || alias.chars().any(char::is_whitespace) {
+            return Err("Aliases must be single words".to_string());
+        }
+
+        if alias.len() > MAX_NAME_LENGTH {
+            return Err(format!("Aliases must be at most {} characters", MAX_NAME_LENGTH));
+        }
+
+        if alias != name && !normalized.contains(&alias) {
+            normalized.push(alias);
+        }
+    }
+
+    if normalized.len() > MAX_ALIASES {
+        return Err(format!("A command can have at most {} aliases", MAX_ALIASES));
+    }
+
+    Ok(normalized)
+}
+
+pub async fn list_commands(
+    pool: &PgPool,
+    channel: &str,
+) -> Result<Vec<CustomCommandRecord>, sqlx::Error> {
+    sqlx::query_as::<_, CustomCommandRecord>(
+        "SELECT * FROM custom_commands WHERE channel = $1 ORDER BY name",
+    )
+    .bind(channel)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn list_enabled_commands(
+    pool: &PgPool,
+    channel: &str,
+) -> Result<Vec<CustomCommandRecord>, sqlx::Error> {
+    sqlx::query_as::<_, CustomCommandRecord>(
+        "SELECT * FROM custom_commands WHERE channel = $1 AND enabled ORDER BY name",
+    )
+    .bind(channel)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn create_command(
+    pool: &PgPool,
+    channel: &str,
+    created_by: &str,
+    input: &CustomCommandInput,
+) -> Result<CustomCommandRecord, sqlx::Error> {
+    sqlx::query_as::<_, CustomCommandRecord>(
+        "INSERT INTO custom_commands
+            (channel, name, response, enabled, created_by, aliases, permission,
+             global_cooldown_secs, user_cooldown_secs)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+         RETURNING *",
+    )
+    .bind(channel)
+    .bind(&input.name)
+    .bind(&input.response)
+    .bind(input.enabled.unwrap_or(true))
+    .bind(created_by)
+    .bind(input.aliases.clone().unwrap_or_default())
+    .bind(input.permission.unwrap_or_default().to_string())
+    .bind(input.global_cooldown_secs.unwrap_or(0))
+    .bind(input.user_cooldown_secs.unwrap_or(0))
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn update_command(
+    pool: &PgPool,
+    channel: &str,
+    id: i64,
+    input: &CustomCommandInput,
+) -> Result<Option<CustomCommandRecord>, sqlx::Error> {
+    sqlx::query_as::<_, CustomCommandRecord>(
+        "UPDATE custom_commands SET
+            name = $3,
+            response = $4,
+            enabled = COALESCE($5, enabled),
+            aliases = COALESCE($6, aliases),
+            permission = COALESCE($7, permission),
+            global_cooldown_secs = COALESCE($8, global_cooldown_secs),
+            user_cooldown_secs = COALESCE($9, user_cooldown_secs),
+            updated_at = NOW()
+         WHERE channel = $1 AND id = $2
+         RETURNING *",
+    )
+    .bind(channel)
+    .bind(id)
+    .bind(&input.name)
+    .bind(&input.response)
+    .bind(input.enabled)
+    .bind(&input.aliases)
+    .bind(input.permission.map(|permission| permission.to_string()))
+    .bind(input.global_cooldown_secs)
+    .bind(input.user_cooldown_secs)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn delete_command(pool: &PgPool, channel: &str, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM custom_commands WHERE channel = $1 AND id = $2")
+        .bind(channel)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Bumps the command's use count and returns the new value
+pub async fn increment_use_count(pool: &PgPool, id: i64) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "UPDATE custom_commands SET use_count = use_count + 1 WHERE id = $1 RETURNING use_count",
+    )
+    .bind(id)
+    .fetch_one(pool)
+    .await
+}
+
+// Names of imported commands by what happened to them
+#[derive(Debug, Default, Serialize)]
+pub struct ImportedCommands {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    // Already in the channel and left alone, without `overwrite`
+    pub existing: Vec<String>,
+}
+
+// Loads validated commands in one transaction. Commands that already exist are
+// replaced with `overwrite`, and skipped otherwise. `xmax = 0` is true for rows the
+// statement inserted and false for ones it updated.
+pub async fn import_commands(
+    pool: &PgPool,
+    channel: &str,
+    created_by: &str,
+    inputs: &[CustomCommandInput],
+    overwrite: bool,
+) -> Result<ImportedCommands, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut imported = ImportedCommands::default();
+
+    for input in inputs {
+        let inserted: Option<bool> = sqlx::query_scalar(
+            "INSERT INTO custom_commands
+                (channel, name, response, enabled, created_by, aliases, permission,
+                 global_cooldown_secs, user_cooldown_secs)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+             ON CONFLICT (channel, name) DO UPDATE SET
+                response = EXCLUDED.response,
+                enabled = EXCLUDED.enabled,
+                aliases = EXCLUDED.aliases,
+                permission = EXCLUDED.permission,
+                global_cooldown_secs = EXCLUDED.global_cooldown_secs,
+                user_cooldown_secs = EXCLUDED.user_cooldown_secs,
+                updated_at = NOW()
+             WHERE $10
+             RETURNING xmax = 0",
+        )
+        .bind(channel)
+        .bind(&input.name)
+        .bind(&input.response)
+        .bind(input.enabled.unwrap_or(true))
+        .bind(created_by)
+        .bind(input.aliases.clone().unwrap_or_default())
+        .bind(input.permission.unwrap_or_default().to_string())
+        .bind(input.global_cooldown_secs.unwrap_or(0))
+        .bind(input.user_cooldown_secs.unwrap_or(0))
+        .bind(overwrite)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        match inserted {
+            Some(true) => imported.created.push(input.name.clone()),
+            Some(false) => imported.updated.push(input.name.clone()),
+            None => imported.existing.push(input.name.clone()),
+        }
+    }
+
+    tx.commit().await?;
+    Ok(imported)
+}
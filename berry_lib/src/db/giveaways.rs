@@ -0,0 +1,205 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct GiveawayRecord {
+    pub id: i64,
+    pub channel: String,
+    pub keyword: String,
+    // open (taking entries), closed (entries stopped, winners can still be drawn) or ended
+    pub status: String,
+    pub subscriber_weight: i32,
+    pub started_by: String,
+    pub created_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+impl GiveawayRecord {
+    pub fn is_open(&self) -> bool {
+        self.status == "open"
+    }
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct GiveawayEntry {
+    pub login: String,
+    pub weight: i32,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct GiveawayDraw {
+    pub id: i64,
+    pub giveaway_id: i64,
+    pub seed: i64,
+    // None when nobody eligible was left to draw
+    pub winner: Option<String>,
+    pub skipped: Vec<String>,
+    pub drawn_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// A giveaway with how many people entered and everyone drawn so far
+#[derive(Debug, Clone, Serialize)]
+pub struct GiveawaySummary {
+    #[serde(flatten)]
+    pub giveaway: GiveawayRecord,
+    pub entries: i64,
+    pub tickets: i64,
+    pub draws: Vec<GiveawayDraw>,
+}
+
+// Fails with a unique violation when the channel already has an active giveaway
+pub async fn create_giveaway(
+    pool: &PgPool,
+    channel: &str,
+    keyword: &str,
+    subscriber_weight: i32,
+    started_by: &str,
+) -> Result<GiveawayRecord, sqlx::Error> {
+    sqlx::query_as::<_, GiveawayRecord>(
+        "INSERT INTO giveaways (channel, keyword, subscriber_weight, started_by)
+         VALUES ($1, $2, $3, $4) RETURNING *",
+    )
+    .bind(channel)
+    .bind(keyword)
+    .bind(subscriber_weight)
+    .bind(started_by)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn get_giveaway(pool: &PgPool, channel: &str, id: i64) -> Result<Option<GiveawayRecord>, sqlx::Error> {
+    sqlx::query_as::<_, GiveawayRecord>("SELECT * FROM giveaways WHERE channel = $1 AND id = $2")
+        .bind(channel)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn get_active_giveaway(pool: &PgPool, channel: &str) -> Result<Option<GiveawayRecord>, sqlx::Error> {
+    sqlx::query_as::<_, GiveawayRecord>("SELECT * FROM giveaways WHERE channel = $1 AND status <> 'ended'")
+        .bind(channel)
+        .fetch_optional(pool)
+        .await
+}
+
+// The active giveaway, or the last one to end when none is active
+pub async fn latest_giveaway(pool: &PgPool, channel: &str) -> Result<Option<GiveawayRecord>, sqlx::Error> {
+    sqlx::query_as::<_, GiveawayRecord>(
+        "SELECT * FROM giveaways WHERE channel = $1 ORDER BY status <> 'ended' DESC, id DESC LIMIT 1",
+    )
+    .bind(channel)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn list_giveaways(pool: &PgPool, channel: &str, limit: i64) -> Result<Vec<GiveawayRecord>, sqlx::Error> {
+    sqlx::query_as::<_, GiveawayRecord>("SELECT * FROM giveaways WHERE channel = $1 ORDER BY id DESC LIMIT $2")
+        .bind(channel)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
+
+// Returns false when the giveaway isn't taking entries or the viewer already entered
+pub async fn add_entry(pool: &PgPool, giveaway_id: i64, login: &str, weight: i32) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO giveaway_entries (giveaway_id, login, weight)
+         SELECT id, $2, $3 FROM giveaways WHERE id = $1 AND status = 'open'
+         ON CONFLICT (giveaway_id, login) DO NOTHING",
+    )
+    .bind(giveaway_id)
+    .bind(login)
+    .bind(weight.max(1))
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Entries that haven't already won a draw
+pub async fn eligible_entries(pool: &PgPool, giveaway_id: i64) -> Result<Vec<GiveawayEntry>, sqlx::Error> {
+    sqlx::query_as::<_, GiveawayEntry>(
+        "SELECT login, weight FROM giveaway_entries e
+         WHERE giveaway_id = $1
+           AND NOT EXISTS (SELECT 1 FROM giveaway_draws d WHERE d.giveaway_id = $1 AND d.winner = e.login)
+         ORDER BY login",
+    )
+    .bind(giveaway_id)
+    .fetch_all(pool)
+    .await
+}
+
+// Moves an active giveaway to `status`. Returns None when it was already ended, or
+// (when closing) no longer open.
+pub async fn set_status(
+    pool: &PgPool,
+    channel: &str,
+    id: i64,
+    status: &str,
+) -> Result<Option<GiveawayRecord>, sqlx::Error> {
+    sqlx::query_as::<_, GiveawayRecord>(
+        "UPDATE giveaways SET status = $3, closed_at = COALESCE(closed_at, NOW())
+         WHERE channel = $1 AND id = $2 AND status <> 'ended' AND ($3 <> 'closed' OR status = 'open')
+         RETURNING *",
+    )
+    .bind(channel)
+    .bind(id)
+    .bind(status)
+    .fetch_optional(pool)
+    .await
+}
+
+// Records a draw, and stops entries if the giveaway was still taking them
+pub async fn record_draw(
+    pool: &PgPool,
+    giveaway_id: i64,
+    seed: i64,
+    winner: Option<&str>,
+    skipped: &[String],
+    drawn_by: &str,
+) -> Result<GiveawayDraw, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("UPDATE giveaways SET status = 'closed', closed_at = NOW() WHERE id = $1 AND status = 'open'")
+        .bind(giveaway_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let draw = sqlx::query_as::<_, GiveawayDraw>(
+        "INSERT INTO giveaway_draws (giveaway_id, seed, winner, skipped, drawn_by)
+         VALUES ($1, $2, $3, $4, $5) RETURNING *",
+    )
+    .bind(giveaway_id)
+    .bind(seed)
+    .bind(winner)
+    .bind(skipped)
+    .bind(drawn_by)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(draw)
+}
+
+pub async fn giveaway_summary(pool: &PgPool, giveaway: GiveawayRecord) -> Result<GiveawaySummary, sqlx::Error> {
+    let (entries, tickets): (i64, i64) = sqlx::query_as(
+        "SELECT COUNT(*), COALESCE(SUM(weight), 0)::BIGINT FROM giveaway_entries WHERE giveaway_id = $1",
+    )
+    .bind(giveaway.id)
+    .fetch_one(pool)
+    .await?;
+
+    let draws = sqlx::query_as::<_, GiveawayDraw>("SELECT * FROM giveaway_draws WHERE giveaway_id = $1 ORDER BY id")
+        .bind(giveaway.id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(GiveawaySummary {
+        giveaway,
+        entries,
+        tickets,
+        draws,
+    })
+}
@@ -0,0 +1,13 @@
+pub mod api_keys;
+pub mod channel_access;
+pub mod counters;
+pub mod custom_commands;
+pub mod giveaways;
+pub mod moderation_audit;
+pub mod points;
+pub mod polls;
+pub mod quotes;
+pub mod sessions;
+pub mod timers;
+pub mod twitch_login_attempts;
+pub mod twitch_credentials;
@@ -0,0 +1,93 @@
+// The moderation audit log. Every action the enforcer takes, or would have taken in
+// shadow mode, is kept here with its outcome; a user's strikes are counted from it.
+use crate::twitch::enforcement::{AuditEntry, AuditOutcome, AuditSource};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+
+const AUDIT_COLUMNS: &str =
+    "id, target, action, reason, source, moderator, category, score, outcome, error, strike, created_at";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct AuditRecord {
+    pub id: i64,
+    pub target: String,
+    pub action: String,
+    pub reason: String,
+    pub source: String,
+    pub moderator: Option<String>,
+    pub category: Option<String>,
+    pub score: Option<f64>,
+    pub outcome: String,
+    pub error: Option<String>,
+    pub strike: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn record_entry(pool: &PgPool, channel: &str, entry: &AuditEntry) -> Result<(), sqlx::Error> {
+    let (source, moderator, category, score) = match &entry.source {
+        AuditSource::Automatic { category, score } => {
+            ("automatic", None, Some(category.as_str()), Some(*score))
+        }
+        AuditSource::Moderator(login) => ("moderator", Some(login.as_str()), None, None),
+    };
+
+    let (outcome, error) = match &entry.outcome {
+        AuditOutcome::Applied => ("applied", None),
+        AuditOutcome::Shadowed => ("shadowed", None),
+        AuditOutcome::Failed(e) => ("failed", Some(e.as_str())),
+    };
+
+    sqlx::query(
+        "INSERT INTO moderation_audit_log
+            (channel, target, action, reason, source, moderator, category, score, outcome, error, strike, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+    )
+    .bind(channel)
+    .bind(&entry.target)
+    .bind(entry.action.to_string())
+    .bind(&entry.reason)
+    .bind(source)
+    .bind(moderator)
+    .bind(category)
+    .bind(score)
+    .bind(outcome)
+    .bind(error)
+    .bind(entry.is_strike())
+    .bind(entry.at)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn count_strikes(pool: &PgPool, channel: &str, login: &str) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM moderation_audit_log WHERE channel = $1 AND target = $2 AND strike",
+    )
+    .bind(channel)
+    .bind(login.to_lowercase())
+    .fetch_one(pool)
+    .await
+}
+
+// Newest first, optionally only the entries against one user
+pub async fn list_entries(
+    pool: &PgPool,
+    channel: &str,
+    target: Option<&str>,
+    limit: i64,
+) -> Result<Vec<AuditRecord>, sqlx::Error> {
+    sqlx::query_as::<_, AuditRecord>(&format!(
+        "SELECT {} FROM moderation_audit_log
+         WHERE channel = $1 AND ($2::TEXT IS NULL OR target = $2)
+         ORDER BY created_at DESC, id DESC
+         LIMIT $3",
+        AUDIT_COLUMNS
+    ))
+    .bind(channel)
+    .bind(target.map(str::to_lowercase))
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
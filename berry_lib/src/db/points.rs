@@ -0,0 +1,246 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgPool, Postgres, Transaction};
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PointsBalance {
+    pub channel: String,
+    pub login: String,
+    pub balance: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct LedgerEntry {
+    pub id: i64,
+    pub channel: String,
+    pub login: String,
+    pub amount: i64,
+    pub reason: String,
+    pub idempotency_key: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub enum PointsError {
+    InsufficientPoints,
+    Database(sqlx::Error),
+}
+
+impl std::fmt::Display for PointsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PointsError::InsufficientPoints => write!(f, "Not enough points"),
+            PointsError::Database(e) => write!(f, "Database Error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PointsError {}
+
+impl From<sqlx::Error> for PointsError {
+    fn from(err: sqlx::Error) -> Self {
+        PointsError::Database(err)
+    }
+}
+
+// One change to award or take away, written to the ledger and applied to the balance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointsChange {
+    pub login: String,
+    pub amount: i64,
+    pub reason: String,
+    // Changes with a key are applied at most once per channel
+    pub idempotency_key: Option<String>,
+}
+
+impl PointsChange {
+    pub fn new(login: &str, amount: i64, reason: &str) -> Self {
+        PointsChange {
+            login: login.to_string(),
+            amount,
+            reason: reason.to_string(),
+            idempotency_key: None,
+        }
+    }
+}
+
+// Writes the ledger row and applies it to the balance inside the caller's transaction.
+// Returns the new balance, or None when the idempotency key was already used.
+async fn apply(
+    tx: &mut Transaction<'_, Postgres>,
+    channel: &str,
+    change: &PointsChange,
+) -> Result<Option<i64>, PointsError> {
+    let inserted: Option<i64> = sqlx::query_scalar(
+        "INSERT INTO points_ledger (channel, login, amount, reason, idempotency_key)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (channel, idempotency_key) DO NOTHING
+         RETURNING id",
+    )
+    .bind(channel)
+    .bind(&change.login)
+    .bind(change.amount)
+    .bind(&change.reason)
+    .bind(&change.idempotency_key)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    if inserted.is_none() {
+        return Ok(None);
+    }
+
+    let balance: Option<i64> = if change.amount >= 0 {
+        sqlx::query_scalar(
+            "INSERT INTO points_balances (channel, login, balance) VALUES ($1, $2, $3)
+             ON CONFLICT (channel, login)
+             DO UPDATE SET balance = points_balances.balance + EXCLUDED.balance, updated_at = NOW()
+             RETURNING balance",
+        )
+        .bind(channel)
+        .bind(&change.login)
+        .bind(change.amount)
+        .fetch_optional(&mut **tx)
+        .await?
+    } else {
+        sqlx::query_scalar(
+            "UPDATE points_balances SET balance = balance + $3, updated_at = NOW()
+             WHERE channel = $1 AND login = $2 AND balance + $3 >= 0
+             RETURNING balance",
+        )
+        .bind(channel)
+        .bind(&change.login)
+        .bind(change.amount)
+        .fetch_optional(&mut **tx)
+        .await?
+    };
+
+    balance.map(Some).ok_or(PointsError::InsufficientPoints)
+}
+
+// Applies every change in one transaction. Changes whose key was already used are
+// skipped; returns how many were applied.
+pub async fn apply_changes(
+    pool: &PgPool,
+    channel: &str,
+    changes: &[PointsChange],
+) -> Result<usize, PointsError> {
+    let mut tx = pool.begin().await?;
+    let mut applied = 0;
+
+    for change in changes {
+        if apply(&mut tx, channel, change).await?.is_some() {
+            applied += 1;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(applied)
+}
+
+// Adds (or with a negative amount takes away) points, failing rather than going below zero
+pub async fn adjust(
+    pool: &PgPool,
+    channel: &str,
+    login: &str,
+    amount: i64,
+    reason: &str,
+) -> Result<i64, PointsError> {
+    let mut tx = pool.begin().await?;
+
+    let change = PointsChange::new(login, amount, reason);
+    let balance = apply(&mut tx, channel, &change).await?.unwrap_or(0);
+
+    tx.commit().await?;
+    Ok(balance)
+}
+
+// Moves points between two users. Returns both new balances (sender, receiver).
+pub async fn transfer(
+    pool: &PgPool,
+    channel: &str,
+    from: &str,
+    to: &str,
+    amount: i64,
+) -> Result<(i64, i64), PointsError> {
+    let mut tx = pool.begin().await?;
+
+    let debit = PointsChange::new(from, -amount, &format!("give to {}", to));
+    let credit = PointsChange::new(to, amount, &format!("gift from {}", from));
+
+    let from_balance = apply(&mut tx, channel, &debit).await?.unwrap_or(0);
+    let to_balance = apply(&mut tx, channel, &credit).await?.unwrap_or(0);
+
+    tx.commit().await?;
+    Ok((from_balance, to_balance))
+}
+
+// Stakes the amount and on a win refunds it and pays the same again. The bet, the
+// refund and the winnings are separate ledger rows.
+pub async fn gamble(
+    pool: &PgPool,
+    channel: &str,
+    login: &str,
+    amount: i64,
+    won: bool,
+) -> Result<i64, PointsError> {
+    let mut tx = pool.begin().await?;
+
+    let bet = PointsChange::new(login, -amount, "gamble bet");
+    let mut balance = apply(&mut tx, channel, &bet).await?.unwrap_or(0);
+
+    if won {
+        let refund = PointsChange::new(login, amount, "gamble refund");
+        let winnings = PointsChange::new(login, amount, "gamble win");
+        apply(&mut tx, channel, &refund).await?;
+        balance = apply(&mut tx, channel, &winnings).await?.unwrap_or(balance);
+    }
+
+    tx.commit().await?;
+    Ok(balance)
+}
+
+pub async fn get_balance(pool: &PgPool, channel: &str, login: &str) -> Result<i64, sqlx::Error> {
+    let balance: Option<i64> = sqlx::query_scalar(
+        "SELECT balance FROM points_balances WHERE channel = $1 AND login = $2",
+    )
+    .bind(channel)
+    .bind(login)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(balance.unwrap_or(0))
+}
+
+pub async fn leaderboard(
+    pool: &PgPool,
+    channel: &str,
+    limit: i64,
+) -> Result<Vec<PointsBalance>, sqlx::Error> {
+    sqlx::query_as::<_, PointsBalance>(
+        "SELECT * FROM points_balances WHERE channel = $1 AND balance > 0
+         ORDER BY balance DESC, login LIMIT $2",
+    )
+    .bind(channel)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+// Newest first, optionally for a single user
+pub async fn ledger(
+    pool: &PgPool,
+    channel: &str,
+    login: Option<&str>,
+    limit: i64,
+) -> Result<Vec<LedgerEntry>, sqlx::Error> {
+    sqlx::query_as::<_, LedgerEntry>(
+        "SELECT * FROM points_ledger WHERE channel = $1 AND ($2::TEXT IS NULL OR login = $2)
+         ORDER BY id DESC LIMIT $3",
+    )
+    .bind(channel)
+    .bind(login)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,140 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PollRecord {
+    pub id: i64,
+    pub channel: String,
+    pub question: String,
+    pub options: Vec<String>,
+    pub status: String,
+    pub started_by: String,
+    pub created_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+impl PollRecord {
+    pub fn is_open(&self) -> bool {
+        self.status == "open"
+    }
+}
+
+// A poll with its vote counts, one per option in order
+#[derive(Debug, Clone, Serialize)]
+pub struct PollResults {
+    #[serde(flatten)]
+    pub poll: PollRecord,
+    pub votes: Vec<i64>,
+    pub total_votes: i64,
+}
+
+// Fails with a unique violation when the channel already has an open poll
+pub async fn create_poll(
+    pool: &PgPool,
+    channel: &str,
+    question: &str,
+    options: &[String],
+    started_by: &str,
+) -> Result<PollRecord, sqlx::Error> {
+    sqlx::query_as::<_, PollRecord>(
+        "INSERT INTO polls (channel, question, options, started_by) VALUES ($1, $2, $3, $4) RETURNING *",
+    )
+    .bind(channel)
+    .bind(question)
+    .bind(options)
+    .bind(started_by)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn get_poll(pool: &PgPool, channel: &str, id: i64) -> Result<Option<PollRecord>, sqlx::Error> {
+    sqlx::query_as::<_, PollRecord>("SELECT * FROM polls WHERE channel = $1 AND id = $2")
+        .bind(channel)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn get_open_poll(pool: &PgPool, channel: &str) -> Result<Option<PollRecord>, sqlx::Error> {
+    sqlx::query_as::<_, PollRecord>("SELECT * FROM polls WHERE channel = $1 AND status = 'open'")
+        .bind(channel)
+        .fetch_optional(pool)
+        .await
+}
+
+// The open poll, or the last one to close when none is open
+pub async fn latest_poll(pool: &PgPool, channel: &str) -> Result<Option<PollRecord>, sqlx::Error> {
+    sqlx::query_as::<_, PollRecord>(
+        "SELECT * FROM polls WHERE channel = $1 ORDER BY status = 'open' DESC, id DESC LIMIT 1",
+    )
+    .bind(channel)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn list_polls(pool: &PgPool, channel: &str, limit: i64) -> Result<Vec<PollRecord>, sqlx::Error> {
+    sqlx::query_as::<_, PollRecord>("SELECT * FROM polls WHERE channel = $1 ORDER BY id DESC LIMIT $2")
+        .bind(channel)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
+
+// Records or changes the viewer's vote. Returns false when the poll isn't open or
+// the option doesn't exist.
+pub async fn cast_vote(
+    pool: &PgPool,
+    poll_id: i64,
+    login: &str,
+    option_index: i32,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO poll_votes (poll_id, login, option_index)
+         SELECT id, $2, $3 FROM polls
+         WHERE id = $1 AND status = 'open' AND $3 >= 0 AND $3 < cardinality(options)
+         ON CONFLICT (poll_id, login) DO UPDATE SET option_index = EXCLUDED.option_index, created_at = NOW()",
+    )
+    .bind(poll_id)
+    .bind(login)
+    .bind(option_index)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Returns None when the poll wasn't open
+pub async fn close_poll(pool: &PgPool, channel: &str, id: i64) -> Result<Option<PollRecord>, sqlx::Error> {
+    sqlx::query_as::<_, PollRecord>(
+        "UPDATE polls SET status = 'closed', closed_at = NOW()
+         WHERE channel = $1 AND id = $2 AND status = 'open'
+         RETURNING *",
+    )
+    .bind(channel)
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn poll_results(pool: &PgPool, poll: PollRecord) -> Result<PollResults, sqlx::Error> {
+    let counts: Vec<(i32, i64)> = sqlx::query_as(
+        "SELECT option_index, COUNT(*) FROM poll_votes WHERE poll_id = $1 GROUP BY option_index",
+    )
+    .bind(poll.id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut votes = vec![0; poll.options.len()];
+    for (index, count) in counts {
+        if let Some(votes) = votes.get_mut(index as usize) {
+            *votes = count;
+        }
+    }
+
+    Ok(PollResults {
+        total_votes: votes.iter().sum(),
+        votes,
+        poll,
+    })
+}
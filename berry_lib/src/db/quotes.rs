@@ -0,0 +1,176 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+
+// The search column is left out, it only exists for the full-text index
+const QUOTE_COLUMNS: &str = "id, channel, number, text, added_by, game, created_at";
+const NUMBERING_RETRIES: usize = 3;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct QuoteRecord {
+    pub id: i64,
+    pub channel: String,
+    // Per-channel number people use in chat ("!quote 42")
+    pub number: i32,
+    pub text: String,
+    pub added_by: String,
+    pub game: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuotePage {
+    pub quotes: Vec<QuoteRecord>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
+
+// Numbers the quote one past the channel's highest, retrying when another quote
+// takes the number first
+pub async fn add_quote(
+    pool: &PgPool,
+    channel: &str,
+    text: &str,
+    added_by: &str,
+    game: Option<&str>,
+) -> Result<QuoteRecord, sqlx::Error> {
+    let mut attempt = 0;
+
+    loop {
+        let result = sqlx::query_as::<_, QuoteRecord>(&format!(
+            "INSERT INTO quotes (channel, number, text, added_by, game)
+             SELECT $1, COALESCE(MAX(number), 0) + 1, $2, $3, $4 FROM quotes WHERE channel = $1
+             RETURNING {}",
+            QUOTE_COLUMNS
+        ))
+        .bind(channel)
+        .bind(text)
+        .bind(added_by)
+        .bind(game)
+        .fetch_one(pool)
+        .await;
+
+        match result {
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() && attempt < NUMBERING_RETRIES => {
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+pub async fn get_quote(
+    pool: &PgPool,
+    channel: &str,
+    number: i32,
+) -> Result<Option<QuoteRecord>, sqlx::Error> {
+    sqlx::query_as::<_, QuoteRecord>(&format!(
+        "SELECT {} FROM quotes WHERE channel = $1 AND number = $2",
+        QUOTE_COLUMNS
+    ))
+    .bind(channel)
+    .bind(number)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn random_quote(pool: &PgPool, channel: &str) -> Result<Option<QuoteRecord>, sqlx::Error> {
+    sqlx::query_as::<_, QuoteRecord>(&format!(
+        "SELECT {} FROM quotes WHERE channel = $1 ORDER BY RANDOM() LIMIT 1",
+        QUOTE_COLUMNS
+    ))
+    .bind(channel)
+    .fetch_optional(pool)
+    .await
+}
+
+// Best matches first. Accepts web-search style queries ("quoted phrase", -word, or).
+pub async fn search_quotes(
+    pool: &PgPool,
+    channel: &str,
+    query: &str,
+    limit: i64,
+) -> Result<Vec<QuoteRecord>, sqlx::Error> {
+    sqlx::query_as::<_, QuoteRecord>(&format!(
+        "SELECT {} FROM quotes
+         WHERE channel = $1 AND search @@ websearch_to_tsquery('english', $2)
+         ORDER BY ts_rank(search, websearch_to_tsquery('english', $2)) DESC, number
+         LIMIT $3",
+        QUOTE_COLUMNS
+    ))
+    .bind(channel)
+    .bind(query)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+// Pages start at 1. With a search the page is ordered by relevance, otherwise by number.
+pub async fn list_quotes(
+    pool: &PgPool,
+    channel: &str,
+    search: Option<&str>,
+    page: i64,
+    per_page: i64,
+) -> Result<QuotePage, sqlx::Error> {
+    let filter = "channel = $1 AND ($2::TEXT IS NULL OR search @@ websearch_to_tsquery('english', $2))";
+
+    let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM quotes WHERE {}", filter))
+        .bind(channel)
+        .bind(search)
+        .fetch_one(pool)
+        .await?;
+
+    let quotes = sqlx::query_as::<_, QuoteRecord>(&format!(
+        "SELECT {} FROM quotes WHERE {}
+         ORDER BY CASE WHEN $2::TEXT IS NULL THEN 0
+                       ELSE ts_rank(search, websearch_to_tsquery('english', $2)) END DESC,
+                  number
+         LIMIT $3 OFFSET $4",
+        QUOTE_COLUMNS, filter
+    ))
+    .bind(channel)
+    .bind(search)
+    .bind(per_page)
+    .bind(page_offset(page, per_page))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(QuotePage {
+        quotes,
+        page,
+        per_page,
+        total,
+    })
+}
+
+// Rows before the page. Pages far past the end saturate rather than overflow, and
+// come back empty like any other page past the last quote.
+fn page_offset(page: i64, per_page: i64) -> i64 {
+    page.max(1).saturating_sub(1).saturating_mul(per_page.max(0))
+}
+
+pub async fn delete_quote(pool: &PgPool, channel: &str, number: i32) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM quotes WHERE channel = $1 AND number = $2")
+        .bind(channel)
+        .bind(number)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_offsets_never_overflow() {
+        assert_eq!(page_offset(1, 20), 0);
+        assert_eq!(page_offset(3, 20), 40);
+        assert_eq!(page_offset(0, 20), 0);
+        assert_eq!(page_offset(i64::MAX, 100), i64::MAX);
+        assert_eq!(page_offset(i64::MIN, 100), 0);
+    }
+}
@@ -0,0 +1,204 @@
+// Signed-in sessions and their rotating refresh tokens. Refresh tokens are random
+// and only stored as SHA-256 hashes; each is good for one refresh, which hands
+// out the next. A used token coming back means someone kept a copy, so the whole
+// session is revoked.
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use ring::digest::{digest, SHA256};
+use serde::Serialize;
+use sqlx::PgPool;
+
+pub const SESSION_LIFETIME_DAYS: i64 = 30;
+
+const SESSION_COLUMNS: &str = "id, unxid, twitch_id, user_agent, ip_address, created_at, last_used_at, expires_at";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SessionRecord {
+    pub id: String,
+    pub unxid: String,
+    pub twitch_id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub enum Refreshed {
+    Rotated {
+        session: SessionRecord,
+        refresh_token: String,
+    },
+    // The token was already used, the session has been revoked
+    Reused,
+    // Unknown token, or the session expired or was revoked
+    Invalid,
+}
+
+fn new_refresh_token() -> (String, Vec<u8>) {
+    let token = URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>());
+    let hash = hash_token(&token);
+    (token, hash)
+}
+
+// Refresh tokens are 256 random bits, so a fast hash is enough
+fn hash_token(token: &str) -> Vec<u8> {
+    digest(&SHA256, token.as_bytes()).as_ref().to_vec()
+}
+
+// Starts a session and returns it with its first refresh token
+pub async fn create_session(
+    pool: &PgPool,
+    unxid: &str,
+    twitch_id: &str,
+    user_agent: Option<&str>,
+    ip_address: Option<&str>,
+) -> Result<(SessionRecord, String), sqlx::Error> {
+    let id = format!("{:032x}", rand::random::<u128>());
+    let (refresh_token, hash) = new_refresh_token();
+
+    let mut tx = pool.begin().await?;
+
+    let session = sqlx::query_as::<_, SessionRecord>(&format!(
+        "INSERT INTO sessions (id, unxid, twitch_id, user_agent, ip_address, expires_at)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING {}",
+        SESSION_COLUMNS
+    ))
+    .bind(&id)
+    .bind(unxid)
+    .bind(twitch_id)
+    .bind(user_agent)
+    .bind(ip_address)
+    .bind(Utc::now() + Duration::days(SESSION_LIFETIME_DAYS))
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query("INSERT INTO session_refresh_tokens (token_hash, session_id) VALUES ($1, $2)")
+        .bind(&hash)
+        .bind(&id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok((session, refresh_token))
+}
+
+pub async fn refresh_session(pool: &PgPool, refresh_token: &str) -> Result<Refreshed, sqlx::Error> {
+    let presented = hash_token(refresh_token);
+    let mut tx = pool.begin().await?;
+
+    // Locked, so of two refreshes racing with the same token one sees it used
+    let token: Option<(String, Option<DateTime<Utc>>)> = sqlx::query_as(
+        "SELECT session_id, used_at FROM session_refresh_tokens WHERE token_hash = $1 FOR UPDATE",
+    )
+    .bind(&presented)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let session_id = match token {
+        None => return Ok(Refreshed::Invalid),
+        Some((session_id, Some(_))) => {
+            sqlx::query(
+                "UPDATE sessions SET revoked_at = NOW(), revoked_reason = 'refresh token reused'
+                 WHERE id = $1 AND revoked_at IS NULL",
+            )
+            .bind(&session_id)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            return Ok(Refreshed::Reused);
+        }
+        Some((session_id, None)) => session_id,
+    };
+
+    let session = sqlx::query_as::<_, SessionRecord>(&format!(
+        "UPDATE sessions SET last_used_at = NOW()
+         WHERE id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+         RETURNING {}",
+        SESSION_COLUMNS
+    ))
+    .bind(&session_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(session) = session else {
+        return Ok(Refreshed::Invalid);
+    };
+
+    let (refresh_token, hash) = new_refresh_token();
+
+    sqlx::query("UPDATE session_refresh_tokens SET used_at = NOW() WHERE token_hash = $1")
+        .bind(&presented)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("INSERT INTO session_refresh_tokens (token_hash, session_id) VALUES ($1, $2)")
+        .bind(&hash)
+        .bind(&session.id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(Refreshed::Rotated { session, refresh_token })
+}
+
+// Whether an access token's session is still going
+pub async fn is_active(pool: &PgPool, id: &str, unxid: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (
+            SELECT 1 FROM sessions
+            WHERE id = $1 AND unxid = $2 AND revoked_at IS NULL AND expires_at > NOW()
+         )",
+    )
+    .bind(id)
+    .bind(unxid)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn list_sessions(pool: &PgPool, unxid: &str) -> Result<Vec<SessionRecord>, sqlx::Error> {
+    sqlx::query_as::<_, SessionRecord>(&format!(
+        "SELECT {} FROM sessions
+         WHERE unxid = $1 AND revoked_at IS NULL AND expires_at > NOW()
+         ORDER BY last_used_at DESC",
+        SESSION_COLUMNS
+    ))
+    .bind(unxid)
+    .fetch_all(pool)
+    .await
+}
+
+// Returns false when the user has no such active session
+pub async fn revoke_session(pool: &PgPool, unxid: &str, id: &str, reason: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE sessions SET revoked_at = NOW(), revoked_reason = $3
+         WHERE id = $1 AND unxid = $2 AND revoked_at IS NULL",
+    )
+    .bind(id)
+    .bind(unxid)
+    .bind(reason)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Signs the user out everywhere but `keep_id`, returning how many sessions ended
+pub async fn revoke_other_sessions(pool: &PgPool, unxid: &str, keep_id: &str, reason: &str) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE sessions SET revoked_at = NOW(), revoked_reason = $3
+         WHERE unxid = $1 AND id <> $2 AND revoked_at IS NULL",
+    )
+    .bind(unxid)
+    .bind(keep_id)
+    .bind(reason)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct TimerRecord {
+    pub id: i64,
+    pub channel: String,
+    pub name: String,
+    pub messages: Vec<String>,
+    pub interval_minutes: i32,
+    pub min_lines: i32,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimerInput {
+    pub name: String,
+    pub messages: Vec<String>,
+    pub interval_minutes: i32,
+    pub min_lines: Option<i32>,
+    pub enabled: Option<bool>,
+}
+
+pub async fn list_timers(pool: &PgPool, channel: &str) -> Result<Vec<TimerRecord>, sqlx::Error> {
+    sqlx::query_as::<_, TimerRecord>("SELECT * FROM timers WHERE channel = $1 ORDER BY name")
+        .bind(channel)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn list_enabled_timers(pool: &PgPool, channel: &str) -> Result<Vec<TimerRecord>, sqlx::Error> {
+    sqlx::query_as::<_, TimerRecord>(
+        "SELECT * FROM timers WHERE channel = $1 AND enabled ORDER BY name",
+    )
+    .bind(channel)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn create_timer(
+    pool: &PgPool,
+    channel: &str,
+    input: &TimerInput,
+) -> Result<TimerRecord, sqlx::Error> {
+    sqlx::query_as::<_, TimerRecord>(
+        "INSERT INTO timers (channel, name, messages, interval_minutes, min_lines, enabled)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING *",
+    )
+    .bind(channel)
+    .bind(&input.name)
+    .bind(&input.messages)
+    .bind(input.interval_minutes)
+    .bind(input.min_lines.unwrap_or(0))
+    .bind(input.enabled.unwrap_or(true))
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn update_timer(
+    pool: &PgPool,
+    channel: &str,
+    id: i64,
+    input: &TimerInput,
+) -> Result<Option<TimerRecord>, sqlx::Error> {
+    sqlx::query_as::<_, TimerRecord>(
+        "UPDATE timers SET
+            name = $3,
+            messages = $4,
+            interval_minutes = $5,
+            min_lines = COALESCE($6, min_lines),
+            enabled = COALESCE($7, enabled),
+            updated_at = NOW()
+         WHERE channel = $1 AND id = $2
+         RETURNING *",
+    )
+    .bind(channel)
+    .bind(id)
+    .bind(&input.name)
+    .bind(&input.messages)
+    .bind(input.interval_minutes)
+    .bind(input.min_lines)
+    .bind(input.enabled)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn delete_timer(pool: &PgPool, channel: &str, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM timers WHERE channel = $1 AND id = $2")
+        .bind(channel)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
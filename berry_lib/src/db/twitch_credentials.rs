@@ -0,0 +1,265 @@
+// Users' Twitch tokens, envelope encrypted (see auth::envelope). Only this
+// module sees them decrypted.
+use crate::auth::envelope::{Envelope, EnvelopeError, Sealed};
+use crate::twitch::twitch_access_token::TwitchAccessToken;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+
+const STORED_COLUMNS: &str = "unxid, twitch_id, key_id, wrapped_key, access_token_sealed, refresh_token_sealed,
+     scopes, expires_at, validated_at, revoked_at, updated_at";
+
+#[derive(Debug)]
+pub enum CredentialsError {
+    Database(sqlx::Error),
+    Envelope(EnvelopeError),
+}
+
+impl std::fmt::Display for CredentialsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CredentialsError::Database(e) => write!(f, "Database Error: {}", e),
+            CredentialsError::Envelope(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CredentialsError {}
+
+impl From<sqlx::Error> for CredentialsError {
+    fn from(err: sqlx::Error) -> Self {
+        CredentialsError::Database(err)
+    }
+}
+
+impl From<EnvelopeError> for CredentialsError {
+    fn from(err: EnvelopeError) -> Self {
+        CredentialsError::Envelope(err)
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct StoredCredentials {
+    unxid: String,
+    twitch_id: String,
+    key_id: String,
+    wrapped_key: Vec<u8>,
+    access_token_sealed: Vec<u8>,
+    refresh_token_sealed: Vec<u8>,
+    scopes: Vec<String>,
+    expires_at: DateTime<Utc>,
+    validated_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TwitchCredentials {
+    pub unxid: String,
+    pub twitch_id: String,
+    pub access_token: String,
+    pub refresh_token: String,
+    pub scopes: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+    pub validated_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    // Guards against overwriting a newer login, see save_refreshed
+    pub updated_at: DateTime<Utc>,
+}
+
+// What the user sees about their Twitch connection, without the tokens
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct CredentialsStatus {
+    pub twitch_id: String,
+    pub scopes: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+    pub validated_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+fn open(envelope: &Envelope, stored: StoredCredentials) -> Result<TwitchCredentials, EnvelopeError> {
+    let sealed = Sealed {
+        key_id: stored.key_id,
+        wrapped_key: stored.wrapped_key,
+        values: vec![stored.access_token_sealed, stored.refresh_token_sealed],
+    };
+
+    let mut values = envelope.open(&stored.unxid, &sealed)?.into_iter();
+    let mut next = || String::from_utf8(values.next().unwrap_or_default()).map_err(|_| EnvelopeError::DecryptionFailed);
+
+    Ok(TwitchCredentials {
+        access_token: next()?,
+        refresh_token: next()?,
+        unxid: stored.unxid,
+        twitch_id: stored.twitch_id,
+        scopes: stored.scopes,
+        expires_at: stored.expires_at,
+        validated_at: stored.validated_at,
+        revoked_at: stored.revoked_at,
+        updated_at: stored.updated_at,
+    })
+}
+
+fn seal(envelope: &Envelope, unxid: &str, token: &TwitchAccessToken) -> Result<Sealed, EnvelopeError> {
+    envelope.seal(unxid, &[token.access_token.as_bytes(), token.refresh_token.as_bytes()])
+}
+
+// Keeps the user's latest tokens from a login, replacing any earlier ones and
+// clearing a revocation
+pub async fn save_credentials(
+    pool: &PgPool,
+    envelope: &Envelope,
+    unxid: &str,
+    twitch_id: &str,
+    token: &TwitchAccessToken,
+) -> Result<(), CredentialsError> {
+    let sealed = seal(envelope, unxid, token)?;
+    let expires_at = Utc::now() + Duration::seconds(token.expires_in as i64);
+
+    sqlx::query(
+        "INSERT INTO user_twitch_credentials
+            (unxid, twitch_id, key_id, wrapped_key, access_token_sealed, refresh_token_sealed, scopes, expires_at, validated_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
+         ON CONFLICT (unxid) DO UPDATE
+         SET twitch_id = EXCLUDED.twitch_id, key_id = EXCLUDED.key_id, wrapped_key = EXCLUDED.wrapped_key,
+             access_token_sealed = EXCLUDED.access_token_sealed,
+             refresh_token_sealed = EXCLUDED.refresh_token_sealed, scopes = EXCLUDED.scopes,
+             expires_at = EXCLUDED.expires_at, validated_at = NOW(), revoked_at = NULL, updated_at = NOW()",
+    )
+    .bind(unxid)
+    .bind(twitch_id)
+    .bind(&sealed.key_id)
+    .bind(&sealed.wrapped_key)
+    .bind(&sealed.values[0])
+    .bind(&sealed.values[1])
+    .bind(&token.scope)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Stores refreshed tokens, unless the user signed in again since `current` was
+// read. Returns false in that case, the newer tokens are kept.
+pub async fn save_refreshed(
+    pool: &PgPool,
+    envelope: &Envelope,
+    current: &TwitchCredentials,
+    token: &TwitchAccessToken,
+) -> Result<bool, CredentialsError> {
+    let sealed = seal(envelope, &current.unxid, token)?;
+    let expires_at = Utc::now() + Duration::seconds(token.expires_in as i64);
+
+    let result = sqlx::query(
+        "UPDATE user_twitch_credentials
+         SET key_id = $3, wrapped_key = $4, access_token_sealed = $5, refresh_token_sealed = $6, scopes = $7,
+             expires_at = $8, validated_at = NOW(), updated_at = NOW()
+         WHERE unxid = $1 AND updated_at = $2",
+    )
+    .bind(&current.unxid)
+    .bind(current.updated_at)
+    .bind(&sealed.key_id)
+    .bind(&sealed.wrapped_key)
+    .bind(&sealed.values[0])
+    .bind(&sealed.values[1])
+    .bind(&token.scope)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_credentials(
+    pool: &PgPool,
+    envelope: &Envelope,
+    unxid: &str,
+) -> Result<Option<TwitchCredentials>, CredentialsError> {
+    let stored = sqlx::query_as::<_, StoredCredentials>(&format!(
+        "SELECT {} FROM user_twitch_credentials WHERE unxid = $1",
+        STORED_COLUMNS
+    ))
+    .bind(unxid)
+    .fetch_optional(pool)
+    .await?;
+
+    match stored {
+        Some(stored) => Ok(Some(open(envelope, stored)?)),
+        None => Ok(None),
+    }
+}
+
+pub async fn get_status(pool: &PgPool, unxid: &str) -> Result<Option<CredentialsStatus>, sqlx::Error> {
+    sqlx::query_as::<_, CredentialsStatus>(
+        "SELECT twitch_id, scopes, expires_at, validated_at, revoked_at
+         FROM user_twitch_credentials WHERE unxid = $1",
+    )
+    .bind(unxid)
+    .fetch_optional(pool)
+    .await
+}
+
+// Unrevoked credentials that expire before `expiring_before` or haven't been
+// validated since `validated_before`. Records that can't be decrypted are
+// logged and left out.
+pub async fn list_due(
+    pool: &PgPool,
+    envelope: &Envelope,
+    expiring_before: DateTime<Utc>,
+    validated_before: DateTime<Utc>,
+) -> Result<Vec<TwitchCredentials>, sqlx::Error> {
+    let stored = sqlx::query_as::<_, StoredCredentials>(&format!(
+        "SELECT {} FROM user_twitch_credentials
+         WHERE revoked_at IS NULL
+           AND (expires_at < $1 OR validated_at IS NULL OR validated_at < $2)
+         ORDER BY expires_at",
+        STORED_COLUMNS
+    ))
+    .bind(expiring_before)
+    .bind(validated_before)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(stored
+        .into_iter()
+        .filter_map(|stored| {
+            let unxid = stored.unxid.clone();
+            open(envelope, stored)
+                .inspect_err(|e| eprintln!("Error Opening Twitch Credentials for {}: {}", unxid, e))
+                .ok()
+        })
+        .collect())
+}
+
+pub async fn mark_validated(pool: &PgPool, unxid: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE user_twitch_credentials SET validated_at = NOW() WHERE unxid = $1")
+        .bind(unxid)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Same guard as save_refreshed, a login since `current` was read wins
+pub async fn mark_revoked(pool: &PgPool, current: &TwitchCredentials) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE user_twitch_credentials SET revoked_at = NOW()
+         WHERE unxid = $1 AND updated_at = $2 AND revoked_at IS NULL",
+    )
+    .bind(&current.unxid)
+    .bind(current.updated_at)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn delete_credentials(pool: &PgPool, unxid: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM user_twitch_credentials WHERE unxid = $1")
+        .bind(unxid)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+// Logins started with GET /auth/twitch/authorize, see auth::oauth
+use chrono::Utc;
+use sqlx::PgPool;
+
+// What the code exchange needs from the attempt
+#[derive(Debug, sqlx::FromRow)]
+pub struct LoginAttempt {
+    pub code_verifier: String,
+    pub scopes: Vec<String>,
+}
+
+pub async fn create_attempt(
+    pool: &PgPool,
+    nonce: &str,
+    code_verifier: &str,
+    scopes: &[String],
+    lifetime: chrono::Duration,
+) -> Result<(), sqlx::Error> {
+    // Attempts nobody finished pile up otherwise
+    sqlx::query("DELETE FROM twitch_login_attempts WHERE expires_at < NOW() - INTERVAL '1 day'")
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO twitch_login_attempts (nonce, code_verifier, scopes, expires_at)
+         VALUES ($1, $2, $3, $4)",
+    )
+    .bind(nonce)
+    .bind(code_verifier)
+    .bind(scopes)
+    .bind(Utc::now() + lifetime)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Marks the attempt used and returns it. None when it's unknown, expired or was
+// already used, so a state can't be replayed.
+pub async fn take_attempt(pool: &PgPool, nonce: &str) -> Result<Option<LoginAttempt>, sqlx::Error> {
+    sqlx::query_as::<_, LoginAttempt>(
+        "UPDATE twitch_login_attempts SET used_at = NOW()
+         WHERE nonce = $1 AND used_at IS NULL AND expires_at > NOW()
+         RETURNING code_verifier, scopes",
+    )
+    .bind(nonce)
+    .fetch_optional(pool)
+    .await
+}
@@ -0,0 +1,75 @@
+// Our own command export. It's what GET /commands/export returns and what
+// importing with the "berry" format reads back.
+use super::{Record, SourceCommand};
+use crate::db::custom_commands::CustomCommandRecord;
+use crate::twitch::permission::PermissionLevel;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+const EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct CommandExport {
+    pub format: String,
+    pub version: u32,
+    pub channel: String,
+    pub exported_at: DateTime<Utc>,
+    pub commands: Vec<ExportedCommand>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportedCommand {
+    pub name: String,
+    pub response: String,
+    pub aliases: Vec<String>,
+    pub permission: String,
+    pub global_cooldown_secs: i32,
+    pub user_cooldown_secs: i32,
+    pub enabled: bool,
+    pub use_count: i64,
+}
+
+pub fn export_commands(channel: &str, records: Vec<CustomCommandRecord>) -> CommandExport {
+    CommandExport {
+        format: "berry".to_string(),
+        version: EXPORT_VERSION,
+        channel: channel.to_string(),
+        exported_at: Utc::now(),
+        commands: records
+            .into_iter()
+            .map(|record| ExportedCommand {
+                name: record.name,
+                response: record.response,
+                aliases: record.aliases,
+                permission: record.permission,
+                global_cooldown_secs: record.global_cooldown_secs,
+                user_cooldown_secs: record.user_cooldown_secs,
+                enabled: record.enabled,
+                use_count: record.use_count,
+            })
+            .collect(),
+    }
+}
+
+pub(super) fn command(record: &Record) -> SourceCommand {
+    let mut command = SourceCommand {
+        name: record.text(&["name"]).unwrap_or_default(),
+        response: record.text(&["response"]).unwrap_or_default(),
+        aliases: record.list(&["aliases"]),
+        global_cooldown_secs: record.int(&["globalcooldownsecs"]).unwrap_or(0),
+        user_cooldown_secs: record.int(&["usercooldownsecs"]).unwrap_or(0),
+        enabled: record.flag(&["enabled"]).unwrap_or(true),
+        ..SourceCommand::default()
+    };
+
+    let level = record.text(&["permission"]).unwrap_or_else(|| "everyone".to_string());
+    command.permission = match level.parse::<PermissionLevel>() {
+        Ok(permission) => permission,
+        Err(e) => {
+            command.problem = Some(e);
+            PermissionLevel::Broadcaster
+        }
+    };
+
+    command
+}
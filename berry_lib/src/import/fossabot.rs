@@ -0,0 +1,56 @@
+// Fossabot's command export (JSON, or CSV with the same column names). Variables
+// look like "$(user)", as Nightbot's do.
+use super::{argument_index, no_arguments, pick, random_range, regular_level, translate_calls, Record, SourceCommand};
+use crate::twitch::permission::PermissionLevel;
+
+pub(super) fn command(record: &Record) -> SourceCommand {
+    let mut command = SourceCommand {
+        name: record.text(&["name", "command"]).unwrap_or_default(),
+        response: record.text(&["response", "message", "reply"]).unwrap_or_default(),
+        aliases: record.list(&["aliases"]),
+        global_cooldown_secs: record
+            .int(&["globalcooldown", "cooldownglobal", "cooldown.global", "cooldown"])
+            .unwrap_or(0),
+        user_cooldown_secs: record
+            .int(&["usercooldown", "cooldownuser", "cooldown.user"])
+            .unwrap_or(0),
+        enabled: record.flag(&["enabled"]).unwrap_or(true),
+        ..SourceCommand::default()
+    };
+
+    let level = record
+        .text(&["permission", "userlevel", "role"])
+        .unwrap_or_else(|| "everyone".to_string());
+    command.permission = match level.to_lowercase().as_str() {
+        "everyone" | "everybody" => PermissionLevel::Everyone,
+        "subscriber" | "subscribers" => PermissionLevel::Subscriber,
+        "vip" | "vips" => PermissionLevel::Vip,
+        "regular" | "regulars" => regular_level(&mut command, &level),
+        "moderator" | "moderators" => PermissionLevel::Moderator,
+        "broadcaster" | "owner" | "streamer" => PermissionLevel::Broadcaster,
+        _ => {
+            command.problem = Some(format!("Unknown permission \"{}\"", level));
+            PermissionLevel::Broadcaster
+        }
+    };
+
+    command
+}
+
+pub(super) fn translate(response: &str) -> Result<String, String> {
+    translate_calls(response, '(', ')', |name, args| match name {
+        "user" | "username" | "sender" => no_arguments(name, args, "${user}"),
+        "touser" | "target" => no_arguments(name, args, "${touser}"),
+        "query" | "args" => no_arguments(name, args, "${args}"),
+        "channel" => no_arguments(name, args, "${channel}"),
+        "count" | "usagecount" => no_arguments(name, args, "${count}"),
+        "uptime" => no_arguments(name, args, "${uptime}"),
+        "random" | "random.range" | "rand" => random_range(args),
+        "random.pick" | "random.choice" => pick(args),
+        arg if arg.strip_prefix("arg").and_then(argument_index).is_some() => {
+            no_arguments(name, args, &format!("${{{}}}", &arg["arg".len()..]))
+        }
+        index if argument_index(index).is_some() => no_arguments(name, args, &format!("${{{}}}", index)),
+        _ => Err(format!("$({}) has no equivalent", name)),
+    })
+}
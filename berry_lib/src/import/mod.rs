@@ -0,0 +1,566 @@
+pub mod export;
+mod fossabot;
+mod nightbot;
+mod streamelements;
+mod streamlabs;
+
+use crate::db::custom_commands::{self, validate_command_input, CustomCommandInput, ImportedCommands};
+use crate::twitch::cooldown::MAX_COOLDOWN;
+use crate::twitch::permission::PermissionLevel;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+// Bots whose command exports we can read. Each has its own variable syntax, which
+// is translated into our template language (see twitch/template.rs).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    // Our own export, from GET /commands/export
+    Berry,
+    Nightbot,
+    StreamElements,
+    Fossabot,
+    Streamlabs,
+}
+
+impl std::fmt::Display for ImportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ImportFormat::Berry => write!(f, "berry"),
+            ImportFormat::Nightbot => write!(f, "nightbot"),
+            ImportFormat::StreamElements => write!(f, "streamelements"),
+            ImportFormat::Fossabot => write!(f, "fossabot"),
+            ImportFormat::Streamlabs => write!(f, "streamlabs"),
+        }
+    }
+}
+
+impl std::str::FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "berry" => Ok(ImportFormat::Berry),
+            "nightbot" => Ok(ImportFormat::Nightbot),
+            "streamelements" | "se" => Ok(ImportFormat::StreamElements),
+            "fossabot" => Ok(ImportFormat::Fossabot),
+            "streamlabs" | "slcb" => Ok(ImportFormat::Streamlabs),
+            _ => Err(format!(
+                "Unknown format \"{}\", expected berry, nightbot, streamelements, fossabot or streamlabs",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    // The file isn't JSON or CSV we can make sense of
+    Unreadable(String),
+    Database(sqlx::Error),
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ImportError::Unreadable(e) => write!(f, "Unreadable Export: {}", e),
+            ImportError::Database(e) => write!(f, "Database Error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<sqlx::Error> for ImportError {
+    fn from(err: sqlx::Error) -> Self {
+        ImportError::Database(err)
+    }
+}
+
+// Something about one command in the export: why it couldn't be converted, or
+// what changed in converting it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportNote {
+    pub name: String,
+    pub message: String,
+}
+
+impl ImportNote {
+    fn new(name: &str, message: impl Into<String>) -> Self {
+        ImportNote {
+            name: name.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedExport {
+    // Converted and validated, ready to load
+    pub commands: Vec<CustomCommandInput>,
+    pub unconverted: Vec<ImportNote>,
+    pub warnings: Vec<ImportNote>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub format: String,
+    pub dry_run: bool,
+    #[serde(flatten)]
+    pub commands: ImportedCommands,
+    pub unconverted: Vec<ImportNote>,
+    pub warnings: Vec<ImportNote>,
+}
+
+// A command as the other bot describes it, before its response is translated
+#[derive(Debug, Default)]
+struct SourceCommand {
+    name: String,
+    response: String,
+    aliases: Vec<String>,
+    permission: PermissionLevel,
+    global_cooldown_secs: i64,
+    user_cooldown_secs: i64,
+    enabled: bool,
+    // Set when this entry only makes another command answer to a second name
+    alias_of: Option<String>,
+    // Why the command can't be converted at all
+    problem: Option<String>,
+    warnings: Vec<String>,
+}
+
+// One command from an export, its fields keyed by name in lowercase without spaces
+// or underscores ("User Cooldown" and "user_cooldown" are both "usercooldown").
+// Nested JSON objects are flattened with dots, so cooldown.user is one field.
+struct Record {
+    fields: HashMap<String, Value>,
+}
+
+impl Record {
+    fn from_json(value: Value) -> Option<Record> {
+        let mut fields = HashMap::new();
+        match value {
+            Value::Object(object) => Self::flatten("", object, &mut fields),
+            _ => return None,
+        }
+        Some(Record { fields })
+    }
+
+    fn flatten(prefix: &str, object: serde_json::Map<String, Value>, fields: &mut HashMap<String, Value>) {
+        for (key, value) in object {
+            let key = format!("{}{}", prefix, field_name(&key));
+            match value {
+                Value::Object(nested) => Self::flatten(&format!("{}.", key), nested, fields),
+                value => {
+                    fields.insert(key, value);
+                }
+            }
+        }
+    }
+
+    // The first of `keys` that is present and not null
+    fn get(&self, keys: &[&str]) -> Option<&Value> {
+        keys.iter()
+            .filter_map(|key| self.fields.get(*key))
+            .find(|value| !value.is_null())
+    }
+
+    fn text(&self, keys: &[&str]) -> Option<String> {
+        match self.get(keys)? {
+            Value::String(text) => Some(text.trim().to_string()).filter(|text| !text.is_empty()),
+            Value::Number(number) => Some(number.to_string()),
+            Value::Bool(flag) => Some(flag.to_string()),
+            _ => None,
+        }
+    }
+
+    fn int(&self, keys: &[&str]) -> Option<i64> {
+        match self.get(keys)? {
+            Value::Number(number) => number.as_i64().or_else(|| number.as_f64().map(|n| n.round() as i64)),
+            Value::String(text) => {
+                let text = text.trim();
+                text.parse::<i64>().ok().or_else(|| text.parse::<f64>().ok().map(|n| n.round() as i64))
+            }
+            _ => None,
+        }
+    }
+
+    fn flag(&self, keys: &[&str]) -> Option<bool> {
+        match self.get(keys)? {
+            Value::Bool(flag) => Some(*flag),
+            Value::Number(number) => number.as_i64().map(|n| n != 0),
+            Value::String(text) => match text.trim().to_lowercase().as_str() {
+                "true" | "yes" | "1" | "on" | "enabled" => Some(true),
+                "false" | "no" | "0" | "off" | "disabled" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // A JSON array, or a comma separated CSV cell
+    fn list(&self, keys: &[&str]) -> Vec<String> {
+        let items: Vec<String> = match self.get(keys) {
+            Some(Value::Array(items)) => items
+                .iter()
+                .filter_map(|item| item.as_str().map(String::from))
+                .collect(),
+            Some(Value::String(text)) => text.split(',').map(String::from).collect(),
+            _ => Vec::new(),
+        };
+
+        items
+            .into_iter()
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+}
+
+fn field_name(name: &str) -> String {
+    name.to_lowercase().replace([' ', '_'], "")
+}
+
+// Exports are JSON (a list of commands, or an object holding one) or CSV with a
+// header row
+fn records(data: &str) -> Result<Vec<Record>, ImportError> {
+    let data = data.trim_start_matches('\u{feff}').trim();
+
+    if data.starts_with('{') || data.starts_with('[') {
+        let value: Value = serde_json::from_str(data).map_err(|e| ImportError::Unreadable(e.to_string()))?;
+        return Ok(json_commands(value)?.into_iter().filter_map(Record::from_json).collect());
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(data.as_bytes());
+
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| ImportError::Unreadable(e.to_string()))?
+        .iter()
+        .map(field_name)
+        .collect();
+
+    let mut records = Vec::new();
+    for row in reader.records() {
+        let row = row.map_err(|e| ImportError::Unreadable(e.to_string()))?;
+        let fields = headers
+            .iter()
+            .cloned()
+            .zip(row.iter().map(|cell| Value::String(cell.to_string())))
+            .collect();
+        records.push(Record { fields });
+    }
+
+    Ok(records)
+}
+
+fn json_commands(value: Value) -> Result<Vec<Value>, ImportError> {
+    match value {
+        Value::Array(items) => Ok(items),
+        Value::Object(mut object) => {
+            for key in ["commands", "data", "items"] {
+                match object.remove(key) {
+                    Some(Value::Array(items)) => return Ok(items),
+                    // An API response wrapping the export, like our own {"data": {...}}
+                    Some(nested @ Value::Object(_)) => return json_commands(nested),
+                    _ => {}
+                }
+            }
+            Err(ImportError::Unreadable("Expected a list of commands".to_string()))
+        }
+        _ => Err(ImportError::Unreadable("Expected a list of commands".to_string())),
+    }
+}
+
+// Replaces every `open ... close` call in the response (Nightbot's "$(user)",
+// StreamElements' "${user}") with what `variable` makes of its name and arguments.
+// Calls can nest, as in "$(urlfetch https://example.com/$(user))".
+fn translate_calls<F>(response: &str, open: char, close: char, variable: F) -> Result<String, String>
+where
+    F: Fn(&str, &str) -> Result<String, String>,
+{
+    let chars: Vec<char> = response.chars().collect();
+    let mut output = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i] != '$' || chars.get(i + 1) != Some(&open) {
+            output.push(chars[i]);
+            i += 1;
+            continue;
+        }
+
+        let mut depth = 0;
+        let mut end = None;
+        for (j, c) in chars.iter().enumerate().skip(i + 1) {
+            if *c == open {
+                depth += 1;
+            } else if *c == close {
+                depth -= 1;
+                if depth == 0 {
+                    end = Some(j);
+                    break;
+                }
+            }
+        }
+
+        let end = end.ok_or_else(|| format!("Unclosed \"${}\"", open))?;
+        let body: String = chars[i + 2..end].iter().collect();
+        let body = body.trim();
+        let (name, args) = match body.split_once(char::is_whitespace) {
+            Some((name, args)) => (name, args.trim()),
+            None => (body, ""),
+        };
+
+        output.push_str(&variable(&name.to_lowercase(), args)?);
+        i = end + 1;
+    }
+
+    Ok(output)
+}
+
+fn no_arguments(name: &str, args: &str, translated: &str) -> Result<String, String> {
+    match args.is_empty() {
+        true => Ok(translated.to_string()),
+        false => Err(format!("\"{}\" with arguments has no equivalent", name)),
+    }
+}
+
+// "1-100", "1 100" or "1,100"
+fn random_range(args: &str) -> Result<String, String> {
+    let bounds: Vec<i64> = args
+        .split(['-', ' ', ','])
+        .filter(|bound| !bound.trim().is_empty())
+        .map(|bound| bound.trim().parse::<i64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("Random range \"{}\" isn't two whole numbers", args))?;
+
+    match bounds.as_slice() {
+        [low, high] => Ok(format!("${{random {} {}}}", low, high)),
+        _ => Err(format!("Random range \"{}\" isn't two whole numbers", args)),
+    }
+}
+
+// Options quoted with ' or ", or separated by spaces when unquoted
+fn pick(args: &str) -> Result<String, String> {
+    let mut options = Vec::new();
+    let mut chars = args.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => options.push(chars.by_ref().take_while(|next| *next != c).collect::<String>()),
+            c if c.is_whitespace() => {}
+            c => {
+                let mut option = c.to_string();
+                while let Some(next) = chars.next_if(|next| !next.is_whitespace()) {
+                    option.push(next);
+                }
+                options.push(option);
+            }
+        }
+    }
+
+    if options.is_empty() || options.iter().any(|option| option.contains(['|', '}'])) {
+        return Err(format!("Random pick \"{}\" can't be converted", args));
+    }
+
+    Ok(format!("${{pick {}}}", options.join("|")))
+}
+
+fn argument_index(name: &str) -> Option<usize> {
+    name.parse::<usize>().ok().filter(|index| (1..=9).contains(index))
+}
+
+// Levels that don't exist here map to the next stricter one we have
+fn regular_level(command: &mut SourceCommand, level: &str) -> PermissionLevel {
+    command
+        .warnings
+        .push(format!("\"{}\" permission has no equivalent, limited to VIPs and up", level));
+    PermissionLevel::Vip
+}
+
+pub fn parse_export(format: ImportFormat, data: &str) -> Result<ParsedExport, ImportError> {
+    let mut parsed = ParsedExport::default();
+    let mut aliases: Vec<(String, String)> = Vec::new();
+
+    for record in records(data)? {
+        let source = match format {
+            ImportFormat::Berry => export::command(&record),
+            ImportFormat::Nightbot => nightbot::command(&record),
+            ImportFormat::StreamElements => streamelements::command(&record),
+            ImportFormat::Fossabot => fossabot::command(&record),
+            ImportFormat::Streamlabs => streamlabs::command(&record),
+        };
+
+        let name = source.name.trim().trim_start_matches('!').to_lowercase();
+        if name.is_empty() {
+            parsed.unconverted.push(ImportNote::new("", "Missing a command name"));
+            continue;
+        }
+
+        if let Some(target) = source.alias_of {
+            aliases.push((name, target.trim().trim_start_matches('!').to_lowercase()));
+            continue;
+        }
+
+        if let Some(problem) = source.problem {
+            parsed.unconverted.push(ImportNote::new(&name, problem));
+            continue;
+        }
+
+        let translated = match format {
+            ImportFormat::Berry => Ok(source.response.clone()),
+            ImportFormat::Nightbot => nightbot::translate(&source.response),
+            ImportFormat::StreamElements => streamelements::translate(&source.response),
+            ImportFormat::Fossabot => fossabot::translate(&source.response),
+            ImportFormat::Streamlabs => streamlabs::translate(&source.response),
+        };
+
+        let response = match translated {
+            Ok(response) => response,
+            Err(reason) => {
+                parsed.unconverted.push(ImportNote::new(&name, reason));
+                continue;
+            }
+        };
+
+        let mut warnings = source.warnings;
+        let max_cooldown = MAX_COOLDOWN.as_secs() as i64;
+        let mut cooldown = |secs: i64| {
+            if secs > max_cooldown {
+                warnings.push(format!("Cooldown of {}s shortened to {}s", secs, max_cooldown));
+            }
+            secs.clamp(0, max_cooldown) as i32
+        };
+
+        let input = CustomCommandInput {
+            name: name.clone(),
+            response,
+            enabled: Some(source.enabled),
+            aliases: Some(source.aliases),
+            permission: Some(source.permission),
+            global_cooldown_secs: Some(cooldown(source.global_cooldown_secs)),
+            user_cooldown_secs: Some(cooldown(source.user_cooldown_secs)),
+        };
+
+        match validate_command_input(input) {
+            Ok(_) if parsed.commands.iter().any(|command| command.name == name) => {
+                parsed.unconverted.push(ImportNote::new(&name, "Duplicate of an earlier command"));
+            }
+            Ok(input) => {
+                parsed.commands.push(input);
+                parsed
+                    .warnings
+                    .extend(warnings.into_iter().map(|warning| ImportNote::new(&name, warning)));
+            }
+            Err(e) => parsed.unconverted.push(ImportNote::new(&name, e)),
+        }
+    }
+
+    for (alias, target) in aliases {
+        let command = match parsed.commands.iter_mut().find(|command| command.name == target) {
+            Some(command) => command,
+            None => {
+                parsed.unconverted.push(ImportNote::new(
+                    &alias,
+                    format!("Alias of !{}, which wasn't converted", target),
+                ));
+                continue;
+            }
+        };
+
+        let mut with_alias = command.clone();
+        with_alias.aliases.get_or_insert_with(Vec::new).push(alias.clone());
+
+        match validate_command_input(with_alias) {
+            Ok(updated) => *command = updated,
+            Err(e) => parsed.unconverted.push(ImportNote::new(&alias, e)),
+        }
+    }
+
+    Ok(parsed)
+}
+
+// Converts the export and loads it into the channel's commands. A dry run reports
+// what would happen without writing anything.
+pub async fn import_commands(
+    pool: &PgPool,
+    channel: &str,
+    created_by: &str,
+    format: ImportFormat,
+    data: &str,
+    overwrite: bool,
+    dry_run: bool,
+) -> Result<ImportReport, ImportError> {
+    let parsed = parse_export(format, data)?;
+
+    let commands = match dry_run {
+        false => custom_commands::import_commands(pool, channel, created_by, &parsed.commands, overwrite).await?,
+        true => {
+            let existing: Vec<String> = custom_commands::list_commands(pool, channel)
+                .await?
+                .into_iter()
+                .map(|command| command.name)
+                .collect();
+
+            let mut commands = ImportedCommands::default();
+            for command in &parsed.commands {
+                let name = command.name.clone();
+                match (existing.contains(&name), overwrite) {
+                    (false, _) => commands.created.push(name),
+                    (true, true) => commands.updated.push(name),
+                    (true, false) => commands.existing.push(name),
+                }
+            }
+            commands
+        }
+    };
+
+    Ok(ImportReport {
+        format: format.to_string(),
+        dry_run,
+        commands,
+        unconverted: parsed.unconverted,
+        warnings: parsed.warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_csv_and_json() {
+        let csv = "Command,Response\n!hi,\"Hello, $user\"\n";
+        let json = r#"{"commands": [{"Command": "!hi", "cooldown": {"User": 5}}]}"#;
+
+        let csv = records(csv).unwrap();
+        assert_eq!(csv[0].text(&["response"]).as_deref(), Some("Hello, $user"));
+
+        let json = records(json).unwrap();
+        assert_eq!(json[0].text(&["command"]).as_deref(), Some("!hi"));
+        assert_eq!(json[0].int(&["cooldown.user"]), Some(5));
+    }
+
+    #[test]
+    fn nightbot_aliases_attach_to_their_command() {
+        let export = r#"[
+            {"name": "!discord", "message": "Join us $(user)", "coolDown": 30, "userLevel": "everyone"},
+            {"name": "!dc", "message": "", "alias": "!discord", "userLevel": "everyone"},
+            {"name": "!weather", "message": "$(weather $(query))", "userLevel": "everyone"}
+        ]"#;
+
+        let parsed = parse_export(ImportFormat::Nightbot, export).unwrap();
+
+        assert_eq!(parsed.commands.len(), 1);
+        assert_eq!(parsed.commands[0].response, "Join us ${user}");
+        assert_eq!(parsed.commands[0].aliases, Some(vec!["dc".to_string()]));
+        assert_eq!(parsed.commands[0].global_cooldown_secs, Some(30));
+        assert_eq!(parsed.unconverted.len(), 1);
+        assert_eq!(parsed.unconverted[0].name, "weather");
+    }
+}
@@ -0,0 +1,69 @@
+// Nightbot's command list, as returned by its API (GET /1/commands) or saved as CSV
+// with the same column names. Variables look like "$(user)".
+use super::{argument_index, no_arguments, regular_level, translate_calls, Record, SourceCommand};
+use crate::twitch::permission::PermissionLevel;
+
+pub(super) fn command(record: &Record) -> SourceCommand {
+    let mut command = SourceCommand {
+        name: record.text(&["name"]).unwrap_or_default(),
+        response: record.text(&["message"]).unwrap_or_default(),
+        global_cooldown_secs: record.int(&["cooldown"]).unwrap_or(0),
+        enabled: true,
+        ..SourceCommand::default()
+    };
+
+    // Nightbot aliases are commands that run another one, with their message put in
+    // front of the arguments. Only plain aliases carry over.
+    if let Some(target) = record.text(&["alias"]) {
+        match command.response.is_empty() {
+            true => command.alias_of = Some(target),
+            false => command.problem = Some(format!("Alias of {} with a message has no equivalent", target)),
+        }
+        return command;
+    }
+
+    let level = record.text(&["userlevel"]).unwrap_or_else(|| "everyone".to_string());
+    command.permission = match level.to_lowercase().as_str() {
+        "everyone" => PermissionLevel::Everyone,
+        "subscriber" => PermissionLevel::Subscriber,
+        "twitch_vip" | "vip" => PermissionLevel::Vip,
+        "regular" => regular_level(&mut command, &level),
+        "moderator" => PermissionLevel::Moderator,
+        "admin" | "owner" => PermissionLevel::Broadcaster,
+        _ => {
+            command.problem = Some(format!("Unknown user level \"{}\"", level));
+            PermissionLevel::Broadcaster
+        }
+    };
+
+    command
+}
+
+pub(super) fn translate(response: &str) -> Result<String, String> {
+    translate_calls(response, '(', ')', |name, args| match name {
+        "user" => no_arguments(name, args, "${user}"),
+        "touser" => no_arguments(name, args, "${touser}"),
+        "query" => no_arguments(name, args, "${args}"),
+        "channel" => no_arguments(name, args, "${channel}"),
+        "count" => no_arguments(name, args, "${count}"),
+        // The usual way of showing uptime: $(twitch $(channel) "{{uptimeLength}}")
+        "twitch" if args.contains("{{uptimeLength}}") => Ok("${uptime}".to_string()),
+        index if argument_index(index).is_some() => no_arguments(name, args, &format!("${{{}}}", index)),
+        _ => Err(format!("$({}) has no equivalent", name)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_variables() {
+        assert_eq!(
+            translate("$(touser) hugs $(1), $(query)! Up $(twitch $(channel) \"{{uptimeLength}}\")").unwrap(),
+            "${touser} hugs ${1}, ${args}! Up ${uptime}"
+        );
+        assert!(translate("$(urlfetch https://example.com/$(user))").is_err());
+        assert!(translate("Unclosed $(user").is_err());
+    }
+}
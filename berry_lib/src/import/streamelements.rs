@@ -0,0 +1,78 @@
+// StreamElements' custom commands, as returned by its API
+// (GET /kappa/v2/bot/commands/{channel}) or saved as CSV with columns command,
+// reply, aliases, accessLevel, userCooldown and globalCooldown. Variables look
+// like "${user}", as ours do, but the names differ.
+use super::{argument_index, no_arguments, pick, random_range, translate_calls, Record, SourceCommand};
+use crate::twitch::permission::PermissionLevel;
+
+pub(super) fn command(record: &Record) -> SourceCommand {
+    let mut command = SourceCommand {
+        name: record.text(&["command", "name"]).unwrap_or_default(),
+        response: record.text(&["reply", "response"]).unwrap_or_default(),
+        aliases: record.list(&["aliases"]),
+        global_cooldown_secs: record.int(&["cooldown.global", "globalcooldown"]).unwrap_or(0),
+        user_cooldown_secs: record.int(&["cooldown.user", "usercooldown"]).unwrap_or(0),
+        enabled: record.flag(&["enabled"]).unwrap_or(true),
+        ..SourceCommand::default()
+    };
+
+    // 100 everyone, 250 subscriber, 300 regular, 400 VIP, 500 moderator,
+    // 1000 super moderator, 1500 broadcaster
+    let level = record.int(&["accesslevel"]).unwrap_or(100);
+    command.permission = match level {
+        1500.. => PermissionLevel::Broadcaster,
+        1000.. => {
+            command
+                .warnings
+                .push("Super moderator permission became moderator".to_string());
+            PermissionLevel::Moderator
+        }
+        500.. => PermissionLevel::Moderator,
+        400.. => PermissionLevel::Vip,
+        300.. => super::regular_level(&mut command, "regular"),
+        250.. => PermissionLevel::Subscriber,
+        _ => PermissionLevel::Everyone,
+    };
+
+    if record.int(&["cost"]).unwrap_or(0) > 0 {
+        command
+            .warnings
+            .push("The command's points cost was dropped".to_string());
+    }
+
+    command
+}
+
+pub(super) fn translate(response: &str) -> Result<String, String> {
+    translate_calls(response, '{', '}', |name, args| match name {
+        "user" | "user.name" | "sender" | "sender.name" | "source" | "source.name" => {
+            no_arguments(name, args, "${user}")
+        }
+        "touser" => no_arguments(name, args, "${touser}"),
+        "1:" => no_arguments(name, args, "${args}"),
+        "channel" | "channel.name" | "channel.display_name" => no_arguments(name, args, "${channel}"),
+        "uptime" => no_arguments(name, args, "${uptime}"),
+        // ${count} alone is the command's own count, ${count deaths} bumps a named counter
+        "count" if args.is_empty() => Ok("${count}".to_string()),
+        "count" => Ok(format!("${{counter {} +}}", args)),
+        "getcount" => Ok(format!("${{counter {}}}", args)),
+        "random.pick" => pick(args),
+        range if range.starts_with("random.") && args.is_empty() => random_range(&range["random.".len()..]),
+        index if argument_index(index).is_some() => no_arguments(name, args, &format!("${{{}}}", index)),
+        _ => Err(format!("${{{}}} has no equivalent", name)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_variables() {
+        assert_eq!(
+            translate("${sender} rolled ${random.1-6} on ${random.pick 'red' \"big blue\"}, deaths ${count deaths}").unwrap(),
+            "${user} rolled ${random 1 6} on ${pick red|big blue}, deaths ${counter deaths +}"
+        );
+        assert!(translate("${customapi.https://example.com}").is_err());
+    }
+}
@@ -0,0 +1,127 @@
+// Streamlabs Chatbot's commands, exported as CSV (Command, Permission, Response,
+// Cooldown, User Cooldown, Cost, Usage, Enabled) or JSON with the same names. Its
+// global cooldown is in minutes. Variables are "$user" or "$randnum(1,10)".
+use super::{argument_index, random_range, regular_level, Record, SourceCommand};
+use crate::twitch::permission::PermissionLevel;
+
+// Variables we know and can't convert. Any other "$word" is left as text, since
+// unlike the other formats there's nothing marking it as a variable.
+const UNSUPPORTED: &[&str] = &[
+    "readapi", "randuser", "game", "title", "time", "points", "currency", "rank", "hours", "followage",
+    "followdate", "checkpoints", "savetofile", "readline", "readrandline", "mychannel", "url", "touserpoints",
+    "touserrank", "touserhours", "cost", "dummy", "dummyormsg", "setgame", "settitle", "addpoints",
+    "removepoints", "queue", "default", "nl",
+];
+
+pub(super) fn command(record: &Record) -> SourceCommand {
+    let mut command = SourceCommand {
+        name: record.text(&["command", "name"]).unwrap_or_default(),
+        response: record.text(&["response"]).unwrap_or_default(),
+        global_cooldown_secs: record.int(&["cooldown"]).unwrap_or(0) * 60,
+        user_cooldown_secs: record.int(&["usercooldown"]).unwrap_or(0),
+        enabled: record.flag(&["enabled"]).unwrap_or(true),
+        ..SourceCommand::default()
+    };
+
+    let level = record.text(&["permission"]).unwrap_or_else(|| "Everyone".to_string());
+    command.permission = match level.to_lowercase().as_str() {
+        "everyone" => PermissionLevel::Everyone,
+        "subscriber" => PermissionLevel::Subscriber,
+        "vip" | "vip exclusive" => PermissionLevel::Vip,
+        "regular" => regular_level(&mut command, &level),
+        "moderator" => PermissionLevel::Moderator,
+        "editor" => {
+            command.warnings.push("Editor permission became moderator".to_string());
+            PermissionLevel::Moderator
+        }
+        "caster" => PermissionLevel::Broadcaster,
+        _ => {
+            command.problem = Some(format!("\"{}\" permission has no equivalent", level));
+            PermissionLevel::Broadcaster
+        }
+    };
+
+    if record
+        .text(&["usage"])
+        .is_some_and(|usage| usage.eq_ignore_ascii_case("discord"))
+    {
+        command.problem = Some("Discord-only command".to_string());
+    }
+
+    if record.int(&["cost"]).unwrap_or(0) > 0 {
+        command
+            .warnings
+            .push("The command's points cost was dropped".to_string());
+    }
+
+    command
+}
+
+pub(super) fn translate(response: &str) -> Result<String, String> {
+    let chars: Vec<char> = response.chars().collect();
+    let mut output = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let is_variable = chars[i] == '$' && chars.get(i + 1).is_some_and(|c| c.is_ascii_alphabetic());
+        if !is_variable {
+            output.push(chars[i]);
+            i += 1;
+            continue;
+        }
+
+        let start = i + 1;
+        let mut end = start;
+        while end < chars.len() && chars[end].is_ascii_alphanumeric() {
+            end += 1;
+        }
+        let name: String = chars[start..end].iter().collect::<String>().to_lowercase();
+
+        // Arguments in parentheses straight after the name
+        let mut args = None;
+        if chars.get(end) == Some(&'(') {
+            let close = chars[end..]
+                .iter()
+                .position(|c| *c == ')')
+                .ok_or_else(|| format!("Unclosed \"${}(\"", name))?;
+            args = Some(chars[end + 1..end + close].iter().collect::<String>());
+            end += close + 1;
+        }
+
+        let translated = match (name.as_str(), &args) {
+            ("user" | "username", None) => "${user}".to_string(),
+            ("touser" | "target", None) => "${touser}".to_string(),
+            ("msg", None) => "${args}".to_string(),
+            ("channel", None) => "${channel}".to_string(),
+            ("count", None) => "${count}".to_string(),
+            ("uptime", None) => "${uptime}".to_string(),
+            ("randnum", Some(range)) => random_range(range)?,
+            (arg, None) if arg.strip_prefix("arg").and_then(argument_index).is_some() => {
+                format!("${{{}}}", &arg["arg".len()..])
+            }
+            (known, _) if UNSUPPORTED.contains(&known) || args.is_some() => {
+                return Err(format!("${} has no equivalent", known))
+            }
+            _ => chars[i..end].iter().collect(),
+        };
+
+        output.push_str(&translated);
+        i = end;
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_variables() {
+        assert_eq!(
+            translate("$user gave $touser $randnum(1,10) berries for $arg1 ($5 value, $USD)").unwrap(),
+            "${user} gave ${touser} ${random 1 10} berries for ${1} ($5 value, $USD)"
+        );
+        assert!(translate("$user has $points points").is_err());
+    }
+}
@@ -1,5 +1,8 @@
+pub mod analytics;
 pub mod api;
 pub mod auth;
+pub mod db;
+pub mod import;
 pub mod openai;
 pub mod twitch;
 pub mod user;
@@ -1,14 +1,14 @@
 use colored::*;
 use reqwest;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use std::{env, fs};
 
 
 
 
 
-enum PunishmentAction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PunishmentAction {
     Timeout(u64),
     Ban,
     Delete,
@@ -16,6 +16,129 @@ enum PunishmentAction {
     None,
 }
 
+// Single source of truth for OpenAI moderation categories. The serde names match the
+// slash/dash form the API uses, so every lookup goes through this enum instead of strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ModerationCategory {
+    #[serde(rename = "harassment")]
+    Harassment,
+    #[serde(rename = "harassment/threatening")]
+    HarassmentThreatening,
+    #[serde(rename = "hate")]
+    Hate,
+    #[serde(rename = "hate/threatening")]
+    HateThreatening,
+    #[serde(rename = "self-harm")]
+    SelfHarm,
+    #[serde(rename = "self-harm/instructions")]
+    SelfHarmInstructions,
+    #[serde(rename = "self-harm/intent")]
+    SelfHarmIntent,
+    #[serde(rename = "sexual")]
+    Sexual,
+    #[serde(rename = "sexual/minors")]
+    SexualMinors,
+    #[serde(rename = "violence")]
+    Violence,
+    #[serde(rename = "violence/graphic")]
+    ViolenceGraphic,
+}
+
+impl ModerationCategory {
+    pub const ALL: [ModerationCategory; 11] = [
+        ModerationCategory::Harassment,
+        ModerationCategory::HarassmentThreatening,
+        ModerationCategory::Hate,
+        ModerationCategory::HateThreatening,
+        ModerationCategory::SelfHarm,
+        ModerationCategory::SelfHarmInstructions,
+        ModerationCategory::SelfHarmIntent,
+        ModerationCategory::Sexual,
+        ModerationCategory::SexualMinors,
+        ModerationCategory::Violence,
+        ModerationCategory::ViolenceGraphic,
+    ];
+
+    // The name OpenAI uses for the category (e.g. "hate/threatening")
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModerationCategory::Harassment => "harassment",
+            ModerationCategory::HarassmentThreatening => "harassment/threatening",
+            ModerationCategory::Hate => "hate",
+            ModerationCategory::HateThreatening => "hate/threatening",
+            ModerationCategory::SelfHarm => "self-harm",
+            ModerationCategory::SelfHarmInstructions => "self-harm/instructions",
+            ModerationCategory::SelfHarmIntent => "self-harm/intent",
+            ModerationCategory::Sexual => "sexual",
+            ModerationCategory::SexualMinors => "sexual/minors",
+            ModerationCategory::Violence => "violence",
+            ModerationCategory::ViolenceGraphic => "violence/graphic",
+        }
+    }
+
+    // Severity rank, 1 is the most severe. When several categories are flagged the
+    // lowest rank wins.
+    pub fn severity(&self) -> u8 {
+        match self {
+            ModerationCategory::SexualMinors => 1,
+            ModerationCategory::Hate => 2,
+            ModerationCategory::SelfHarm => 3,
+            ModerationCategory::SelfHarmIntent => 4,
+            ModerationCategory::HateThreatening => 5,
+            ModerationCategory::SelfHarmInstructions => 6,
+            ModerationCategory::HarassmentThreatening => 7,
+            ModerationCategory::Sexual => 8,
+            ModerationCategory::ViolenceGraphic => 9,
+            ModerationCategory::Violence => 10,
+            ModerationCategory::Harassment => 11,
+        }
+    }
+
+    pub fn punishment(&self) -> PunishmentAction {
+        match self {
+            ModerationCategory::Harassment => PunishmentAction::Warn,
+            ModerationCategory::HarassmentThreatening => PunishmentAction::Ban,
+            ModerationCategory::Hate => PunishmentAction::Timeout(60),
+            ModerationCategory::HateThreatening => PunishmentAction::Ban,
+            ModerationCategory::SelfHarm => PunishmentAction::Delete,
+            ModerationCategory::SelfHarmInstructions => PunishmentAction::Delete,
+            ModerationCategory::SelfHarmIntent => PunishmentAction::Timeout(120),
+            ModerationCategory::Sexual => PunishmentAction::Delete,
+            ModerationCategory::SexualMinors => PunishmentAction::Ban,
+            ModerationCategory::Violence => PunishmentAction::Timeout(30),
+            ModerationCategory::ViolenceGraphic => PunishmentAction::Delete,
+        }
+    }
+
+    // Picks the most severe category out of everything that was flagged
+    pub fn most_severe<I>(categories: I) -> Option<ModerationCategory>
+    where
+        I: IntoIterator<Item = ModerationCategory>,
+    {
+        categories.into_iter().min_by_key(|category| category.severity())
+    }
+}
+
+impl std::fmt::Display for ModerationCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for ModerationCategory {
+    type Err = String;
+
+    // Accepts both the API form ("self-harm/intent") and the config form ("self_harm_intent")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.trim().to_lowercase().replace(['/', '-'], "_");
+
+        ModerationCategory::ALL
+            .into_iter()
+            .find(|category| category.as_str().replace(['/', '-'], "_") == normalized)
+            .ok_or_else(|| format!("Unknown moderation category: {}", s))
+    }
+}
+
 #[derive(Deserialize)]
 struct DefaultThresholds {
     harassment: f64,
@@ -31,6 +154,24 @@ struct DefaultThresholds {
     violence_graphic: f64,
 }
 
+impl DefaultThresholds {
+    fn for_category(&self, category: ModerationCategory) -> f64 {
+        match category {
+            ModerationCategory::Harassment => self.harassment,
+            ModerationCategory::HarassmentThreatening => self.harassment_threatening,
+            ModerationCategory::Hate => self.hate,
+            ModerationCategory::HateThreatening => self.hate_threatening,
+            ModerationCategory::SelfHarm => self.self_harm,
+            ModerationCategory::SelfHarmInstructions => self.self_harm_instructions,
+            ModerationCategory::SelfHarmIntent => self.self_harm_intent,
+            ModerationCategory::Sexual => self.sexual,
+            ModerationCategory::SexualMinors => self.sexual_minors,
+            ModerationCategory::Violence => self.violence,
+            ModerationCategory::ViolenceGraphic => self.violence_graphic,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ModerationResponse {
     pub id: String,
@@ -38,25 +179,90 @@ pub struct ModerationResponse {
     pub results: Vec<OpenAiModRes>,
 }
 
+// How much a category's score has to rise when the message is added to its context
+// before we blame the message (and not the context) for it.
+const CONTEXT_SCORE_MARGIN: f64 = 0.2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextMessage {
+    pub id: Option<String>,
+    pub sender: String,
+    pub text: String,
+}
+
+// The conversation a message was sent into: the message it replies to (if any) and
+// the chat lines around it, oldest first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModerationContext {
+    pub reply_parent: Option<ContextMessage>,
+    pub messages: Vec<ContextMessage>,
+}
+
+impl ModerationContext {
+    pub fn is_empty(&self) -> bool {
+        self.reply_parent.is_none() && self.messages.is_empty()
+    }
+
+    fn transcript(&self) -> String {
+        let mut lines: Vec<String> = self
+            .messages
+            .iter()
+            .map(|message| format!("{}: {}", message.sender, message.text))
+            .collect();
+
+        if let Some(parent) = &self.reply_parent {
+            let already_included = parent.id.is_some()
+                && self.messages.iter().any(|message| message.id == parent.id);
+
+            if !already_included {
+                lines.push(format!("{}: {}", parent.sender, parent.text));
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+// The outcome of checking a message, with the context that was needed to flag it
+#[derive(Debug)]
+pub struct ModerationVerdict {
+    pub category: ModerationCategory,
+    pub score: f64,
+    pub context: Option<ModerationContext>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct FlaggedMessage {
     pub username: String,
     pub user_id: String,
     pub text: String,
-    pub category: String,
+    pub category: ModerationCategory,
     pub score: f64,
+    pub context: Option<ModerationContext>,
 }
 
 impl FlaggedMessage {
-    pub fn new(username: &str, user_id: &str, text: &str, category: &str, score: f64) -> Self {
+    pub fn new(
+        username: &str,
+        user_id: &str,
+        text: &str,
+        category: ModerationCategory,
+        score: f64,
+    ) -> Self {
         FlaggedMessage {
             username: String::from(username),
             user_id: String::from(user_id),
             text: String::from(text),
-            category: String::from(category),
+            category,
             score,
+            context: None,
         }
     }
+
+    pub fn with_context(mut self, context: Option<ModerationContext>) -> Self {
+        self.context = context;
+        self
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -99,19 +305,27 @@ pub struct ModerationCategories {
 }
 
 impl ModerationCategories {
-    pub fn iterate_and_filter_true(&self) -> Vec<String> {
-        let serialized = serde_json::to_value(self).unwrap();
-        let mut true_fields = Vec::new();
-
-        if let Value::Object(map) = serialized {
-            for (key, value) in map {
-                if let Value::Bool(true) = value {
-                    true_fields.push(key);
-                }
-            }
+    pub fn is_flagged(&self, category: ModerationCategory) -> bool {
+        match category {
+            ModerationCategory::Harassment => self.harassment,
+            ModerationCategory::HarassmentThreatening => self.harassment_threatening,
+            ModerationCategory::Hate => self.hate,
+            ModerationCategory::HateThreatening => self.hate_threatening,
+            ModerationCategory::SelfHarm => self.self_harm,
+            ModerationCategory::SelfHarmInstructions => self.self_harm_instructions,
+            ModerationCategory::SelfHarmIntent => self.self_harm_intent,
+            ModerationCategory::Sexual => self.sexual,
+            ModerationCategory::SexualMinors => self.sexual_minors,
+            ModerationCategory::Violence => self.violence,
+            ModerationCategory::ViolenceGraphic => self.violence_graphic,
         }
+    }
 
-        true_fields
+    pub fn iterate_and_filter_true(&self) -> Vec<ModerationCategory> {
+        ModerationCategory::ALL
+            .into_iter()
+            .filter(|category| self.is_flagged(*category))
+            .collect()
     }
 }
 
@@ -147,27 +361,33 @@ pub struct ModerationScores {
 }
 
 impl ModerationScores {
-    pub fn get_score(&self, field: &str) -> f64 {
-        match field {
-            "harassment" => self.harassment,
-            "harassment/threatening" => self.harassment_threatening,
-            "hate" => self.hate,
-            "hate/threatening" => self.hate_threatening,
-            "self-harm" => self.self_harm,
-            "self-harm/instructions" => self.self_harm_instructions,
-            "self-harm/intent" => self.self_harm_intent,
-            "sexual" => self.sexual,
-            "sexual/minors" => self.sexual_minors,
-            "violence" => self.violence,
-            "violence/graphic" => self.violence_graphic,
-            _ => 0.0,
+    pub fn get_score(&self, category: ModerationCategory) -> f64 {
+        match category {
+            ModerationCategory::Harassment => self.harassment,
+            ModerationCategory::HarassmentThreatening => self.harassment_threatening,
+            ModerationCategory::Hate => self.hate,
+            ModerationCategory::HateThreatening => self.hate_threatening,
+            ModerationCategory::SelfHarm => self.self_harm,
+            ModerationCategory::SelfHarmInstructions => self.self_harm_instructions,
+            ModerationCategory::SelfHarmIntent => self.self_harm_intent,
+            ModerationCategory::Sexual => self.sexual,
+            ModerationCategory::SexualMinors => self.sexual_minors,
+            ModerationCategory::Violence => self.violence,
+            ModerationCategory::ViolenceGraphic => self.violence_graphic,
         }
     }
 }
 
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ModerationInput {
+    Single(String),
+    Batch(Vec<String>),
+}
+
 #[derive(Serialize)]
 struct ModerationRequest {
-    input: String,
+    input: ModerationInput,
 }
 
 pub enum ModerationError {
@@ -195,6 +415,8 @@ impl From<std::io::Error> for ModerationError {
 pub struct OpenAiApiModeration {
     api_key: String,
     input: String,
+    sender: String,
+    context: Option<ModerationContext>,
 }
 
 impl OpenAiApiModeration {
@@ -202,7 +424,72 @@ impl OpenAiApiModeration {
         OpenAiApiModeration {
             api_key: env::var("OPEN_AI_KEY").expect("Failed to get Open AI Key"),
             input: input.to_string(),
+            sender: String::new(),
+            context: None,
+        }
+    }
+
+    // Moderates the input as part of a conversation. The message is checked on its own,
+    // the context on its own, and both together, so a flag can be traced to the message.
+    pub fn with_context(mut self, sender: &str, context: ModerationContext) -> Self {
+        self.sender = sender.to_string();
+        self.context = Some(context).filter(|context| !context.is_empty());
+        self
+    }
+
+    fn build_input(&self) -> ModerationInput {
+        match &self.context {
+            Some(context) => {
+                let transcript = context.transcript();
+                let in_context = format!("{}\n{}: {}", transcript, self.sender, self.input);
+
+                ModerationInput::Batch(vec![self.input.clone(), transcript, in_context])
+            }
+            None => ModerationInput::Single(self.input.clone()),
+        }
+    }
+
+    pub fn evaluate(&self, response: &ModerationResponse) -> Option<ModerationVerdict> {
+        let standalone = response.results.first()?;
+
+        if standalone.flagged {
+            let category =
+                ModerationCategory::most_severe(standalone.categories.iterate_and_filter_true())?;
+
+            return Some(ModerationVerdict {
+                category,
+                score: standalone.category_scores.get_score(category),
+                context: None,
+            });
+        }
+
+        let context = self.context.as_ref()?;
+        let (context_only, in_context) = match &response.results[..] {
+            [_, context_only, in_context] => (context_only, in_context),
+            _ => return None,
+        };
+
+        if !in_context.flagged {
+            return None;
         }
+
+        let raised_by_message = in_context
+            .categories
+            .iterate_and_filter_true()
+            .into_iter()
+            .filter(|category| {
+                in_context.category_scores.get_score(*category)
+                    - context_only.category_scores.get_score(*category)
+                    >= CONTEXT_SCORE_MARGIN
+            });
+
+        let category = ModerationCategory::most_severe(raised_by_message)?;
+
+        Some(ModerationVerdict {
+            category,
+            score: in_context.category_scores.get_score(category),
+            context: Some(context.clone()),
+        })
     }
 
     pub async fn handle_input_check(&self) -> Result<ModerationResponse, ModerationError> {
@@ -210,7 +497,7 @@ impl OpenAiApiModeration {
         let endpoint = "https://api.openai.com/v1/moderations";
 
         let request_body = ModerationRequest {
-            input: self.input.clone(),
+            input: self.build_input(),
         };
 
         let response = client
@@ -230,7 +517,12 @@ impl OpenAiApiModeration {
         Ok(moderation_response)
     }
 
-    pub fn moderate_input(&self, mod_results: FlaggedMessage) -> Result<(), ModerationError> {
+    // Decides the punishment for a flagged message. Returns None when the score doesn't
+    // reach the category's threshold.
+    pub fn moderate_input(
+        &self,
+        mod_results: FlaggedMessage,
+    ) -> Result<Option<PunishmentAction>, ModerationError> {
 
         let default_thresholds = load_default_thresholds()?;
         
@@ -240,45 +532,29 @@ impl OpenAiApiModeration {
             "Moderation Results: ".bright_purple().bold().underline()
         ); // !REMOVE
 
-        let (threshold, punishment) = match &mod_results.category[..] {
-            "harassment" => (default_thresholds.harassment, PunishmentAction::Warn),
-            "harassment/threatening" => (
-                default_thresholds.harassment_threatening,
-                PunishmentAction::Ban,
-            ),
-            "hate" => (default_thresholds.hate, PunishmentAction::Timeout(60)),
-            "hate/threatening" => (default_thresholds.hate_threatening, PunishmentAction::Ban),
-            "self-harm" => (default_thresholds.self_harm, PunishmentAction::Delete),
-            "self-harm/instructions" => (
-                default_thresholds.self_harm_instructions,
-                PunishmentAction::Delete,
-            ),
-            "self-harm/intent" => (
-                default_thresholds.self_harm_intent,
-                PunishmentAction::Timeout(120),
-            ),
-            "sexual" => (default_thresholds.sexual, PunishmentAction::Delete),
-            "sexual/minors" => (default_thresholds.sexual_minors, PunishmentAction::Ban),
-            "violence" => (default_thresholds.violence, PunishmentAction::Timeout(30)),
-            "violence/graphic" => (
-                default_thresholds.violence_graphic,
-                PunishmentAction::Delete,
-            ),
-            _ => (0.0, PunishmentAction::None),
-        };
+        let threshold = default_thresholds.for_category(mod_results.category);
+        let punishment = mod_results.category.punishment();
 
         let rounded_score = round_to_decimal_places(mod_results.score);
 
         if rounded_score >= threshold {
             println!(
                 "{}: {} {} {}",
-                mod_results.category.bright_yellow().bold(),
+                mod_results.category.as_str().bright_yellow().bold(),
                 "Flagged".bright_red().bold(),
                 mod_results.username,
                 mod_results.user_id
             );
             println!("{}: {}", "Score".bright_yellow().bold(), rounded_score);
 
+            if let Some(context) = &mod_results.context {
+                println!(
+                    "{}: {:?}",
+                    "Flagged In Context".bright_yellow().bold(),
+                    context
+                );
+            }
+
             match punishment {
                 PunishmentAction::Timeout(duration) => {
                     println!(
@@ -286,12 +562,10 @@ impl OpenAiApiModeration {
                         "Punishment Issues".bright_cyan().bold().underline(),
                         mod_results.username,
                         mod_results.text.on_bright_green(),
-                        mod_results.category.red()
+                        mod_results.category.as_str().red()
                     );
 
                     println!("{}: {} seconds", "Timeout".bright_cyan().bold(), duration);
-
-                    ()
                 }
                 PunishmentAction::Ban => {
                     println!(
@@ -307,9 +581,6 @@ impl OpenAiApiModeration {
                         "Punishment".bright_cyan().bold(),
                         "Ban".bright_red().bold()
                     );
-
-                    ()
-
                 }
                 PunishmentAction::Delete => {
                     println!(
@@ -325,9 +596,6 @@ impl OpenAiApiModeration {
                         "Punishment".bright_cyan().bold(),
                         "Delete".bright_red().bold()
                     );
-
-                    ()
-
                 }
                 PunishmentAction::Warn => {
                     println!(
@@ -343,9 +611,6 @@ impl OpenAiApiModeration {
                         "Punishment".bright_cyan().bold(),
                         "Warn".bright_yellow().bold()
                     );
-
-                    ()
-
                 }
                 PunishmentAction::None => {
                     println!(
@@ -361,10 +626,10 @@ impl OpenAiApiModeration {
                         "Punishment".bright_cyan().bold(),
                         "None".bright_green().bold()
                     );
-
-                    ()
                 }
             }
+
+            Ok(Some(punishment))
         } else {
             println!(
                 "{}: {}",
@@ -376,9 +641,9 @@ impl OpenAiApiModeration {
 
             println!("{}", "=====================================================".bright_yellow().bold());
             println!("{}", "=====================================================".bright_yellow().bold());
-        }
 
-        Ok(())
+            Ok(None)
+        }
     }
 }
 
@@ -402,3 +667,190 @@ fn load_default_thresholds() -> Result<DefaultThresholds, ModerationError> {
     
     Ok(thresholds)
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn thresholds() -> DefaultThresholds {
+        serde_json::from_str(include_str!("../config/moderation_defaults.json")).unwrap()
+    }
+
+    // A single moderation result where only `flagged` is set (if any), scored at `score`
+    fn result_with(flagged: Option<ModerationCategory>, score: f64) -> serde_json::Value {
+        let mut categories = serde_json::Map::new();
+        let mut scores = serde_json::Map::new();
+
+        for category in ModerationCategory::ALL {
+            let is_flagged = Some(category) == flagged;
+            categories.insert(category.to_string(), serde_json::json!(is_flagged));
+            let category_score = if is_flagged { score } else { 0.0001 };
+            scores.insert(category.to_string(), serde_json::json!(category_score));
+        }
+
+        serde_json::json!({
+            "flagged": flagged.is_some(),
+            "categories": categories,
+            "category_scores": scores,
+        })
+    }
+
+    fn response_from(results: Vec<serde_json::Value>) -> ModerationResponse {
+        serde_json::from_value(serde_json::json!({
+            "id": "modr-test",
+            "model": "text-moderation-007",
+            "results": results,
+        }))
+        .unwrap()
+    }
+
+    fn response_with(flagged: ModerationCategory, score: f64) -> ModerationResponse {
+        response_from(vec![result_with(Some(flagged), score)])
+    }
+
+    fn moderation_with_context() -> OpenAiApiModeration {
+        OpenAiApiModeration {
+            api_key: String::new(),
+            input: "you should do it".to_string(),
+            sender: "viewer".to_string(),
+            context: Some(ModerationContext {
+                reply_parent: None,
+                messages: vec![ContextMessage {
+                    id: Some("1".to_string()),
+                    sender: "other".to_string(),
+                    text: "i want to hurt myself".to_string(),
+                }],
+            }),
+        }
+    }
+
+    #[test]
+    fn serde_names_match_api() {
+        for category in ModerationCategory::ALL {
+            let serialized = serde_json::to_string(&category).unwrap();
+            assert_eq!(serialized, format!("\"{}\"", category.as_str()));
+
+            let deserialized: ModerationCategory = serde_json::from_str(&serialized).unwrap();
+            assert_eq!(deserialized, category);
+        }
+    }
+
+    #[test]
+    fn from_str_accepts_slash_and_underscore_forms() {
+        for category in ModerationCategory::ALL {
+            let underscored = category.as_str().replace(['/', '-'], "_");
+
+            assert_eq!(category.as_str().parse::<ModerationCategory>(), Ok(category));
+            assert_eq!(underscored.parse::<ModerationCategory>(), Ok(category));
+        }
+
+        assert!("not_a_category".parse::<ModerationCategory>().is_err());
+    }
+
+    #[test]
+    fn severities_are_unique() {
+        let severities: HashSet<u8> = ModerationCategory::ALL
+            .iter()
+            .map(|category| category.severity())
+            .collect();
+
+        assert_eq!(severities.len(), ModerationCategory::ALL.len());
+    }
+
+    #[test]
+    fn most_severe_picks_lowest_rank() {
+        let flagged = vec![
+            ModerationCategory::Harassment,
+            ModerationCategory::HateThreatening,
+            ModerationCategory::Violence,
+        ];
+
+        assert_eq!(
+            ModerationCategory::most_severe(flagged),
+            Some(ModerationCategory::HateThreatening)
+        );
+        assert_eq!(ModerationCategory::most_severe(vec![]), None);
+    }
+
+    #[test]
+    fn every_category_flows_from_response_to_punishment() {
+        let thresholds = thresholds();
+
+        for category in ModerationCategory::ALL {
+            let response = response_with(category, 0.999);
+            let result = &response.results[0];
+
+            let true_fields = result.categories.iterate_and_filter_true();
+            assert_eq!(true_fields, vec![category]);
+
+            let offence = ModerationCategory::most_severe(true_fields).unwrap();
+            assert_eq!(offence, category);
+
+            let score = result.category_scores.get_score(offence);
+            assert_eq!(score, 0.999);
+
+            let flagged = FlaggedMessage::new("user", "123", "text", offence, score);
+            let threshold = thresholds.for_category(flagged.category);
+            assert!(round_to_decimal_places(flagged.score) >= threshold);
+            assert_ne!(flagged.category.punishment(), PunishmentAction::None);
+        }
+    }
+
+    #[test]
+    fn scores_below_threshold_are_not_punished() {
+        let thresholds = thresholds();
+
+        for category in ModerationCategory::ALL {
+            let threshold = thresholds.for_category(category);
+            let response = response_with(category, threshold - 0.01);
+            let score = response.results[0].category_scores.get_score(category);
+
+            assert!(round_to_decimal_places(score) < threshold);
+        }
+    }
+
+    #[test]
+    fn evaluate_records_context_when_message_is_only_flagged_in_context() {
+        let moderation = moderation_with_context();
+        let response = response_from(vec![
+            result_with(None, 0.0),
+            result_with(Some(ModerationCategory::SelfHarm), 0.4),
+            result_with(Some(ModerationCategory::SelfHarm), 0.9),
+        ]);
+
+        let verdict = moderation.evaluate(&response).unwrap();
+
+        assert_eq!(verdict.category, ModerationCategory::SelfHarm);
+        assert_eq!(verdict.score, 0.9);
+        assert_eq!(verdict.context.unwrap().messages[0].text, "i want to hurt myself");
+    }
+
+    #[test]
+    fn evaluate_ignores_flags_that_come_from_the_context_alone() {
+        let moderation = moderation_with_context();
+        let response = response_from(vec![
+            result_with(None, 0.0),
+            result_with(Some(ModerationCategory::SelfHarm), 0.85),
+            result_with(Some(ModerationCategory::SelfHarm), 0.9),
+        ]);
+
+        assert!(moderation.evaluate(&response).is_none());
+    }
+
+    #[test]
+    fn evaluate_prefers_standalone_flags_without_context() {
+        let moderation = moderation_with_context();
+        let response = response_from(vec![
+            result_with(Some(ModerationCategory::Harassment), 0.97),
+            result_with(None, 0.0),
+            result_with(Some(ModerationCategory::Harassment), 0.97),
+        ]);
+
+        let verdict = moderation.evaluate(&response).unwrap();
+
+        assert_eq!(verdict.category, ModerationCategory::Harassment);
+        assert!(verdict.context.is_none());
+    }
+}
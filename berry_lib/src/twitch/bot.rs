@@ -1,44 +1,114 @@
-use crate::openai::moderation::FlaggedMessage;
+use crate::analytics::sentiment::{LexiconSentimentScorer, SentimentScorer};
+use crate::analytics::trends::TrendRegistry;
+use crate::db::{counters, custom_commands, giveaways, points, polls, timers};
+use crate::openai::moderation::{FlaggedMessage, ModerationContext};
+use chrono::Utc;
 use colored::Colorize;
 
 // bot.rs
+use super::bot_registry::{BotControl, BotRegistry};
+use super::chat_context::ChatContextBuffer;
 use super::commands::CommandHandler;
-use super::commands::CustomCommand;
-use super::twitch_api::{TwitchChatAPI, TwitchError, TwitchMessage};
+use super::commands::{CommandContext, CommandError, CommandOutput, CustomCommand};
+use super::enforcement::{AuditOutcome, AuditSource, EnforcementAction, EnforcementTarget, Enforcer, ModMode};
+use super::exemptions::ModerationExemptions;
+use super::giveaway_commands::{self, ActiveGiveaway};
+use super::helix::HelixClient;
+use super::mod_commands::{parse_mod_command, ModCommand};
+use super::points::{PointsSettings, PointsTracker};
+use super::poll_commands::{self, parse_vote, ActivePoll};
+use super::timers::TimerScheduler;
+use super::twitch_api::{ChatEvent, TwitchChatAPI, TwitchError, TwitchMessage};
 use super::twitch_endpoint;
 use crate::openai;
+use sqlx::PgPool;
 use std::io::ErrorKind;
-use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+const LIVE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
 
 pub struct Bot<'a> {
+    channel: &'a str,
+    broadcaster_id: Option<String>,
     api: TwitchChatAPI<'a>,
+    helix: HelixClient,
+    enforcer: Enforcer,
     command_handler: CommandHandler,
+    exemptions: ModerationExemptions,
+    chat_context: ChatContextBuffer,
+    sentiment_scorer: Box<dyn SentimentScorer>,
+    trends: TrendRegistry,
+    pool: PgPool,
+    registry: BotRegistry,
+    control: UnboundedReceiver<BotControl>,
+    timers: TimerScheduler,
+    points: PointsTracker,
+    stream_live: bool,
+    last_live_check: Option<Instant>,
 }
 
 impl<'a> Bot<'a> {
-    pub fn new(access_token: &'a str, channel: &'a str) -> Result<Self, TwitchError> {
+    pub fn new(
+        access_token: &'a str,
+        channel: &'a str,
+        trends: TrendRegistry,
+        pool: PgPool,
+        registry: BotRegistry,
+    ) -> Result<Self, TwitchError> {
         let api = TwitchChatAPI::new(access_token, channel)?;
 
-        let command_handler = CommandHandler::new(|| match get_custom_commands() {
-            Ok(command) => command,
-            Err(e) => {
-                println!("Error Getting Commands {e}");
-                vec![]
-            }
-        });
+        // Custom commands are loaded from the database once the bot starts running
+        let command_handler = CommandHandler::new(Vec::new);
+        let exemptions = ModerationExemptions::for_channel(channel);
+
         Ok(Bot {
+            channel,
+            broadcaster_id: None,
             api,
+            helix: HelixClient::new(access_token),
+            enforcer: Enforcer::new(ModMode::from_env(), channel, pool.clone()),
             command_handler,
+            exemptions,
+            chat_context: ChatContextBuffer::default(),
+            sentiment_scorer: Box::new(LexiconSentimentScorer::default()),
+            trends,
+            pool,
+            control: registry.register(channel),
+            registry,
+            timers: TimerScheduler::default(),
+            points: PointsTracker::new(PointsSettings::for_channel(channel)),
+            stream_live: false,
+            last_live_check: None,
         })
     }
 
+    pub fn with_sentiment_scorer(mut self, scorer: Box<dyn SentimentScorer>) -> Self {
+        self.sentiment_scorer = scorer;
+        self
+    }
+
     // ...
 
     pub async fn run(&mut self) -> Result<(), TwitchError> {
         self.api.connect()?;
+        self.reload_custom_commands().await;
+        self.reload_timers().await;
+        self.reload_poll().await;
+        self.reload_giveaway().await;
+
         loop {
+            while let Ok(control) = self.control.try_recv() {
+                self.handle_control(control).await;
+            }
+
+            self.refresh_live_status().await;
+            self.run_timers();
+            self.run_points().await;
+
             match self.api.read_message() {
-                Ok(Some(message)) => self.handle_message(&message).await,
+                Ok(Some(ChatEvent::Message(message))) => self.handle_message(&message).await,
+                Ok(Some(ChatEvent::UserNotice(notice))) => self.handle_user_notice(&notice).await,
                 Ok(None) => {}
                 Err(TwitchError::IOError(ref e)) if e.kind() == ErrorKind::WouldBlock => {
                     std::thread::sleep(std::time::Duration::from_millis(500));
@@ -48,99 +118,510 @@ impl<'a> Bot<'a> {
         }
     }
 
-    async fn handle_message(&mut self, message: &TwitchMessage) {
+    async fn handle_control(&mut self, control: BotControl) {
+        match control {
+            BotControl::ReloadCommands => self.reload_custom_commands().await,
+            BotControl::ReloadTimers => self.reload_timers().await,
+            BotControl::PollStarted(id) => {
+                self.reload_poll().await;
+                if let Ok(Some(poll)) = polls::get_poll(&self.pool, self.channel, id).await {
+                    self.send_reply(&poll_commands::format_poll(&poll));
+                }
+            }
+            BotControl::PollEnded(id) => {
+                self.reload_poll().await;
+                let results = match polls::get_poll(&self.pool, self.channel, id).await {
+                    Ok(Some(poll)) => polls::poll_results(&self.pool, poll).await,
+                    Ok(None) => return,
+                    Err(e) => Err(e),
+                };
+                match results {
+                    Ok(results) => self.send_reply(&poll_commands::format_results(&results)),
+                    Err(e) => eprintln!("{} {}", "Error Getting Poll Results:".bright_red(), e),
+                }
+            }
+            BotControl::GiveawayChanged => self.reload_giveaway().await,
+            BotControl::DrawGiveaway { id, drawn_by } => self.draw_giveaway(id, &drawn_by).await,
+            BotControl::Say(message) => self.send_reply(&message),
+            BotControl::TokenRefreshed(access_token) => self.helix.set_access_token(&access_token),
+        }
+    }
 
-        let moderation = openai::moderation::OpenAiApiModeration::new(&message.text);
+    async fn reload_poll(&mut self) {
+        match polls::get_open_poll(&self.pool, self.channel).await {
+            Ok(poll) => self.command_handler.current_poll().set(poll.as_ref().map(ActivePoll::from)),
+            Err(e) => eprintln!("{} {}", "Error Getting Poll".bright_red(), e),
+        }
+    }
 
-        match moderation.handle_input_check().await {
-            Ok(res) => {
+    async fn reload_giveaway(&mut self) {
+        match giveaways::get_active_giveaway(&self.pool, self.channel).await {
+            Ok(giveaway) => self
+                .command_handler
+                .current_giveaway()
+                .set(giveaway.as_ref().map(ActiveGiveaway::from)),
+            Err(e) => eprintln!("{} {}", "Error Getting Giveaway".bright_red(), e),
+        }
+    }
 
-                let is_flagged = res.results[0].flagged;
-                let categories = &res.results[0].categories;
-                let scores = &res.results[0].category_scores;
+    async fn draw_giveaway(&mut self, id: i64, drawn_by: &str) {
+        let giveaway = match giveaways::get_giveaway(&self.pool, self.channel, id).await {
+            Ok(Some(giveaway)) => giveaway,
+            Ok(None) => return,
+            Err(e) => {
+                eprintln!("{} {}", "Error Getting Giveaway".bright_red(), e);
+                return;
+            }
+        };
 
-                if is_flagged {
+        let broadcaster_id = self.broadcaster_id().await;
+        match giveaway_commands::draw(&self.pool, &self.helix, broadcaster_id.as_deref(), &giveaway, drawn_by).await {
+            Ok(draw) => self.send_reply(&giveaway_commands::format_draw(&draw)),
+            Err(e) => eprintln!("{} {}", "Error Drawing Giveaway:".bright_red(), e),
+        }
 
-                    println!("{}", "=====================================================".bright_yellow().bold());
-                    println!("{}", "=====================================================".bright_yellow().bold());
+        self.reload_giveaway().await;
+    }
 
-                    println!("{}", "Message is FLAGGED".bright_red().bold());
-                    let true_fields = categories.iterate_and_filter_true();
+    // Plain numbers vote in the running poll and the giveaway keyword enters the
+    // giveaway. Returns true when the message was one of those.
+    async fn handle_poll_or_giveaway(&mut self, message: &TwitchMessage) -> bool {
+        let login = message.sender.to_lowercase();
 
-                    println!("{}: {:?}", "Moderation Scores".bright_yellow().bold(), res.results[0].category_scores);
+        if let Some(poll) = self.command_handler.current_poll().get() {
+            if let Some(option) = parse_vote(&message.text, poll.options) {
+                if let Err(e) = polls::cast_vote(&self.pool, poll.id, &login, option).await {
+                    eprintln!("{} {}", "Error Recording Vote:".bright_red(), e);
+                }
+                return true;
+            }
+        }
 
-                    println!(
-                        "{}: {:?}",
-                        "True Fields".bright_yellow().bold(),
-                        true_fields
-                    );
+        if let Some(giveaway) = self.command_handler.current_giveaway().get() {
+            if giveaway.accepting_entries && message.text.trim().eq_ignore_ascii_case(&giveaway.keyword) {
+                let weight = match message.is_subscriber() {
+                    true => giveaway.subscriber_weight,
+                    false => 1,
+                };
+                if let Err(e) = giveaways::add_entry(&self.pool, giveaway.id, &login, weight).await {
+                    eprintln!("{} {}", "Error Entering Giveaway:".bright_red(), e);
+                }
+                return true;
+            }
+        }
 
+        false
+    }
 
-                    let offence = match determine_offence(true_fields) {
-                        Some(offence) => offence,
-                        None => {
-                            "No Offence Found".to_string();
-                            return
-                        },
-                    };
+    async fn reload_custom_commands(&mut self) {
+        match custom_commands::list_enabled_commands(&self.pool, self.channel).await {
+            Ok(records) => {
+                println!(
+                    "{} {} ({})",
+                    "Loaded Custom Commands:".bright_blue().bold(),
+                    records.len(),
+                    self.channel
+                );
 
+                // Commands are validated when saved, this only catches rows written some other way
+                let commands = records
+                    .into_iter()
+                    .filter_map(|record| {
+                        let name = record.name.clone();
+                        CustomCommand::from_record(record)
+                            .inspect_err(|e| {
+                                eprintln!("{} !{}: {}", "Invalid Custom Command".bright_red(), name, e)
+                            })
+                            .ok()
+                    })
+                    .collect();
 
-                    let offender_name = &message.sender;
-                    let score = scores.get_score(&offence);
-                    let user_text = &message.text;
+                self.command_handler.set_custom_commands(commands);
+            }
+            Err(e) => eprintln!("{} {}", "Error Getting Commands".bright_red(), e),
+        }
 
-                    println!(
-                        "{} {}: {} {} {} {}",
-                        "OFFENCE".red().bold().underline(),
-                        offender_name,
-                        offence,
-                        score,
-                        "USER TEXT".bright_purple().bold().underline(),
-                        user_text
-                    );
+        match counters::list_counters(&self.pool, self.channel).await {
+            Ok(counters) => self
+                .command_handler
+                .set_counter_names(counters.into_iter().map(|counter| counter.name).collect()),
+            Err(e) => eprintln!("{} {}", "Error Getting Counters".bright_red(), e),
+        }
+    }
 
-                    let offender_twitch_id = match twitch_endpoint::get_user_twitch_id(
-                        &offender_name,
-                        &self.api,
-                    )
-                    .await
-                    {
-                        Ok(id) => id,
-                        Err(e) => {
-                            println!(
-                                "{} {e}",
-                                "ERROR GETTING TWITCH TOKEN: ".bright_red().bold().underline()
-                            );
-                            return;
-                        }
-                    };
-                    
-                    let flagged_message = FlaggedMessage::new(
-                        &offender_name,
-                        &offender_twitch_id,
-                        &user_text,
-                        &offence,
-                        score,
-                    );
+    async fn reload_timers(&mut self) {
+        match timers::list_enabled_timers(&self.pool, self.channel).await {
+            Ok(records) => self.timers.set_timers(records, Instant::now()),
+            Err(e) => eprintln!("{} {}", "Error Getting Timers".bright_red(), e),
+        }
+    }
+
+    // Timers and points both only run while the stream is live
+    async fn refresh_live_status(&mut self) {
+        if self.timers.is_empty() && !self.points.settings().enabled {
+            return;
+        }
+
+        let check_due = self
+            .last_live_check
+            .is_none_or(|checked| checked.elapsed() >= LIVE_CHECK_INTERVAL);
+
+        if check_due {
+            self.last_live_check = Some(Instant::now());
+            self.stream_live = self.is_live().await;
+        }
+    }
+
+    fn run_timers(&mut self) {
+        for message in self.timers.due(Instant::now(), self.stream_live) {
+            self.send_reply(&message);
+        }
+    }
+
+    // Pays out the earning window that just closed, if one has
+    async fn run_points(&mut self) {
+        let window = match self.points.closed_window(Utc::now().timestamp(), self.stream_live) {
+            Some(window) => window,
+            None => return,
+        };
+
+        // Without the viewer list only the people who chatted are paid
+        let present = match self.broadcaster_id().await {
+            Some(broadcaster_id) => self.helix.get_chatters(&broadcaster_id).await.unwrap_or_else(|e| {
+                eprintln!("{} {}", "Error Getting Chatters:".bright_red(), e);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+
+        let earnings = self.points.earnings(window, &present);
+        if let Err(e) = points::apply_changes(&self.pool, self.channel, &earnings).await {
+            eprintln!("{} {}", "Error Paying Points:".bright_red(), e);
+        }
+    }
+
+    async fn handle_user_notice(&mut self, notice: &TwitchMessage) {
+        let bonus = match self.points.notice_bonus(notice) {
+            Some(bonus) => bonus,
+            None => return,
+        };
+
+        println!(
+            "{} {} {} ({})",
+            "Points Bonus:".bright_blue().bold(),
+            bonus.login,
+            bonus.amount,
+            bonus.reason
+        ); // !REMOVE
+
+        if let Err(e) = points::apply_changes(&self.pool, self.channel, &[bonus]).await {
+            eprintln!("{} {}", "Error Paying Points:".bright_red(), e);
+        }
+    }
+
+    async fn is_live(&mut self) -> bool {
+        let broadcaster_id = match self.broadcaster_id().await {
+            Some(id) => id,
+            None => return false,
+        };
+
+        match self.helix.get_stream_started_at(&broadcaster_id).await {
+            Ok(started_at) => started_at.is_some(),
+            Err(e) => {
+                eprintln!("{} {}", "Error Getting Stream:".bright_red(), e);
+                self.stream_live
+            }
+        }
+    }
+
+    async fn handle_message(&mut self, message: &TwitchMessage) {
+        if let Some(room_id) = message.tag("room-id") {
+            self.broadcaster_id = Some(room_id.to_string());
+        }
+
+        self.timers.record_line();
+
+        let sentiment = self.sentiment_scorer.score(&message.text);
+        self.trends.record_message(self.channel, &sentiment);
+
+        if let Some(reply) = self.exemptions.handle_permit_command(message) {
+            self.send_reply(&reply);
+            return;
+        }
+
+        if let Some(parsed) = parse_mod_command(&message.text) {
+            if message.is_moderator() {
+                let reply = match parsed {
+                    Ok(command) => self.run_mod_command(message, command).await,
+                    Err(usage) => usage,
+                };
+                self.send_reply(&reply);
+            }
+            return;
+        }
 
-                    moderation.moderate_input(flagged_message);
+        let context = self.chat_context.context_for(message);
+        self.chat_context.push(message);
 
+        match self.exemptions.exemption_for(message) {
+            Some(reason) => {
+                println!(
+                    "{} {} ({})",
+                    "Skipping Moderation:".bright_green().bold(),
+                    message.sender,
+                    reason
+                ); // !REMOVE
+            }
+            None => {
+                if !self.moderate_message(message, context).await {
                     return;
                 }
-                if let Some(command) = self.command_handler.get_command(&message.text) {
-             
-                    let response = command.execute(&message);
-                    if let Err(e) = self.api.send_message(&response) {
-                        eprintln!("Error sending message: {:?}", e);
-                    }
-                } else {
-                    ()
+            }
+        }
+
+        self.points.record_chatter(message);
+
+        if self.handle_poll_or_giveaway(message).await {
+            return;
+        }
+
+        // Commands the sender can't run, or that are cooling down, are ignored silently
+        if !self.command_handler.check_access(message) {
+            return;
+        }
+
+        let broadcaster_id = self.broadcaster_id().await;
+
+        let outputs = {
+            let command = match self.command_handler.get_command(&message.text) {
+                Some(command) => command,
+                None => return,
+            };
+
+            let context = CommandContext::new(
+                message,
+                self.channel,
+                broadcaster_id.as_deref(),
+                &self.pool,
+                &self.helix,
+            );
+
+            match command.execute(&context).await {
+                Ok(()) => context.take_outputs(),
+                Err(CommandError::Usage(usage)) => vec![CommandOutput::Reply(usage)],
+                Err(e) => {
+                    eprintln!("{} !{}: {}", "Error Running Command".bright_red(), command.get_name(), e);
+                    context.take_outputs()
+                }
+            }
+        };
+
+        for output in outputs {
+            self.send_output(output, broadcaster_id.as_deref()).await;
+        }
+    }
+
+    async fn send_output(&mut self, output: CommandOutput, broadcaster_id: Option<&str>) {
+        let result = match (output, broadcaster_id) {
+            (CommandOutput::Reply(text), _) => {
+                self.send_reply(&text);
+                return;
+            }
+            (CommandOutput::Whisper { to, text }, Some(broadcaster_id)) => {
+                match self.helix.get_user_id(&to).await {
+                    Ok(to_id) => self.helix.send_whisper(broadcaster_id, &to_id, &text).await,
+                    Err(e) => Err(e),
                 }
             }
+            (CommandOutput::Announce(text), Some(broadcaster_id)) => {
+                self.helix.send_announcement(broadcaster_id, &text).await
+            }
+            (_, None) => {
+                eprintln!("{}", "Unknown Broadcaster, Dropping Command Output".bright_red());
+                return;
+            }
+        };
+
+        if let Err(e) = result {
+            eprintln!("{} {}", "Error Sending Command Output:".bright_red(), e);
+        }
+    }
+
+    // Runs the message through moderation. Returns false when the message was flagged
+    // (or could not be checked) and should not go on to command handling.
+    async fn moderate_message(&mut self, message: &TwitchMessage, context: ModerationContext) -> bool {
+        let moderation = openai::moderation::OpenAiApiModeration::new(&message.text)
+            .with_context(&message.sender, context);
+
+        let res = match moderation.handle_input_check().await {
+            Ok(res) => res,
             Err(e) => {
                 eprintln!("Error Handling Moderation: {e}");
+                return false;
+            }
+        };
+
+        let verdict = match moderation.evaluate(&res) {
+            Some(verdict) => verdict,
+            None => return true,
+        };
+
+        println!("{}", "=====================================================".bright_yellow().bold());
+        println!("{}", "=====================================================".bright_yellow().bold());
+
+        println!("{}", "Message is FLAGGED".bright_red().bold());
+        self.trends.record_flag(self.channel);
+
+        println!("{}: {:?}", "Moderation Scores".bright_yellow().bold(), res.results);
+
+        let offender_name = &message.sender;
+        let user_text = &message.text;
+
+        println!(
+            "{} {}: {} {} {} {}",
+            "OFFENCE".red().bold().underline(),
+            offender_name,
+            verdict.category,
+            verdict.score,
+            "USER TEXT".bright_purple().bold().underline(),
+            user_text
+        );
+
+        let offender_twitch_id = match message.tag("user-id") {
+            Some(id) => id.to_string(),
+            None => match twitch_endpoint::get_user_twitch_id(offender_name, &self.api).await {
+                Ok(id) => id,
+                Err(e) => {
+                    println!(
+                        "{} {e}",
+                        "ERROR GETTING TWITCH TOKEN: ".bright_red().bold().underline()
+                    );
+                    return false;
+                }
+            },
+        };
+
+        let flagged_message = FlaggedMessage::new(
+            offender_name,
+            &offender_twitch_id,
+            user_text,
+            verdict.category,
+            verdict.score,
+        )
+        .with_context(verdict.context);
+
+        let punishment = match moderation.moderate_input(flagged_message) {
+            Ok(Some(punishment)) => punishment,
+            Ok(None) => return false,
+            Err(e) => {
+                eprintln!("Error Moderating Input: {e}");
+                return false;
+            }
+        };
+
+        if let Some(action) = EnforcementAction::from_punishment(punishment, message.tag("id")) {
+            let target = EnforcementTarget {
+                login: offender_name.to_string(),
+                user_id: Some(offender_twitch_id),
+            };
+            let reason = format!("Automatic moderation: {}", verdict.category);
+            let source = AuditSource::Automatic {
+                category: verdict.category,
+                score: verdict.score,
+            };
+
+            let outcome = self.enforce(target, action.clone(), &reason, source).await;
+
+            if action == EnforcementAction::Warn && outcome == AuditOutcome::Applied {
+                self.send_reply(&format!(
+                    "@{} please keep it friendly ({})",
+                    offender_name, verdict.category
+                ));
+            }
+        }
+
+        false
+    }
+
+    async fn run_mod_command(&mut self, message: &TwitchMessage, command: ModCommand) -> String {
+        let (user, action, reason) = match command {
+            ModCommand::Strikes { user } => {
+                return match self.enforcer.strikes(&user).await {
+                    Ok(strikes) => format!("{} has {} strike(s)", user, strikes),
+                    Err(e) => {
+                        eprintln!("{} {}", "Error Counting Strikes:".bright_red(), e);
+                        format!("Could not look up strikes for {}", user)
+                    }
+                };
+            }
+            ModCommand::ModMode(None) => {
+                return format!("Moderation is in {} mode", self.enforcer.mode());
+            }
+            ModCommand::ModMode(Some(mode)) => {
+                self.enforcer.set_mode(mode);
+                return format!("Moderation switched to {} mode", mode);
+            }
+            ModCommand::Timeout {
+                user,
+                duration,
+                reason,
+            } => (user, EnforcementAction::Timeout(duration.as_secs()), reason),
+            ModCommand::Ban { user, reason } => (user, EnforcementAction::Ban, reason),
+            ModCommand::Unban { user } => (user, EnforcementAction::Unban, String::new()),
+            ModCommand::Purge { user } => {
+                let message_ids = self.chat_context.recent_message_ids_from(&user);
+                (user, EnforcementAction::Purge(message_ids), String::new())
+            }
+        };
+
+        let target = EnforcementTarget {
+            login: user.clone(),
+            user_id: None,
+        };
+        let source = AuditSource::Moderator(message.sender.clone());
+
+        match self.enforce(target, action.clone(), &reason, source).await {
+            AuditOutcome::Failed(e) => format!("Could not {} {}: {}", action, user, e),
+            _ => format!("{}: {}", user, action),
+        }
+    }
+
+    async fn enforce(
+        &mut self,
+        target: EnforcementTarget,
+        action: EnforcementAction,
+        reason: &str,
+        source: AuditSource,
+    ) -> AuditOutcome {
+        let broadcaster_id = match self.broadcaster_id().await {
+            Some(id) => id,
+            None => return AuditOutcome::Failed("Unknown broadcaster".to_string()),
+        };
+
+        self.enforcer
+            .enforce(&self.helix, &broadcaster_id, target, action, reason, source)
+            .await
+    }
+
+    async fn broadcaster_id(&mut self) -> Option<String> {
+        if self.broadcaster_id.is_none() {
+            match self.helix.get_user_id(self.channel).await {
+                Ok(id) => self.broadcaster_id = Some(id),
+                Err(e) => eprintln!("{} {}", "Error Getting Broadcaster Id:".bright_red(), e),
             }
         }
+
+        self.broadcaster_id.clone()
+    }
+
+    fn send_reply(&mut self, reply: &str) {
+        if reply.is_empty() {
+            return;
+        }
+
+        if let Err(e) = self.api.send_message(reply) {
+            eprintln!("Error sending message: {:?}", e);
+        }
     }
 
     pub fn disconnect(&mut self) -> Result<(), TwitchError> {
@@ -148,42 +629,9 @@ impl<'a> Bot<'a> {
     }
 }
 
-fn get_custom_commands() -> Result<Vec<CustomCommand>, Box<dyn std::error::Error>> {
-    Ok(vec![CustomCommand {
-        name: "hello".to_string(),
-        action: "!hello".to_string(),
-        callback: Box::new(|message| format!("Hello from Rust! {}", message.text)),
-    }])
+impl Drop for Bot<'_> {
+    fn drop(&mut self) {
+        self.control.close();
+        self.registry.unregister(self.channel);
+    }
 }
-
-
-fn determine_offence(categories: Vec<String>) -> Option<String> {
-    let severities: HashMap<&str, i32> = HashMap::from([
-        ("sexual_minors", 1),
-        ("hate", 2),
-        ("self_harm", 3),
-        ("self_harm_intent", 4),
-        ("hate_threatening", 5),
-        ("self_harm_instructions", 6),
-        ("harassment_threatening", 7),
-        ("sexual", 8),
-        ("violence_graphic", 9),
-        ("violence", 10),
-        ("harassment", 11),
-    ]);
-
-    let cat = categories
-        .into_iter()
-        .filter_map(|category| severities.get(&category.as_str()).copied())
-        .min()
-        .map(|severity| {
-            severities
-                .iter()
-                .find(|&(_, s)| *s == severity)
-                .map(|(category, _)| category.to_string())
-                .unwrap_or_else(|| "No Offence Found".to_string())
-        })
-        .unwrap_or_else(|| "No Offence Found".to_string());
-
-    Some(cat)
-}
\ No newline at end of file
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+// Messages the API sends to a running bot
+#[derive(Debug, Clone)]
+pub enum BotControl {
+    ReloadCommands,
+    ReloadTimers,
+    // A poll was started or ended from the API, the bot posts it to chat
+    PollStarted(i64),
+    PollEnded(i64),
+    GiveawayChanged,
+    DrawGiveaway { id: i64, drawn_by: String },
+    // A message to post in chat, from the bot say endpoint
+    Say(String),
+    // The broadcaster's token was refreshed, Helix calls switch to the new one
+    TokenRefreshed(String),
+}
+
+// Tracks the bot running in each channel so the API can reach it without a restart
+#[derive(Clone, Default)]
+pub struct BotRegistry {
+    bots: Arc<Mutex<HashMap<String, UnboundedSender<BotControl>>>>,
+}
+
+impl BotRegistry {
+    pub fn register(&self, channel: &str) -> UnboundedReceiver<BotControl> {
+        let (sender, receiver) = unbounded_channel();
+
+        if let Ok(mut bots) = self.bots.lock() {
+            bots.insert(channel.to_lowercase(), sender);
+        }
+
+        receiver
+    }
+
+    // Only drops the channel's entry once its bot has closed its receiver, so a bot
+    // shutting down can't unregister a newer one in the same channel.
+    pub fn unregister(&self, channel: &str) {
+        if let Ok(mut bots) = self.bots.lock() {
+            let channel = channel.to_lowercase();
+            if bots.get(&channel).is_some_and(|sender| sender.is_closed()) {
+                bots.remove(&channel);
+            }
+        }
+    }
+
+    pub fn is_running(&self, channel: &str) -> bool {
+        self.bots
+            .lock()
+            .map(|bots| {
+                bots.get(&channel.to_lowercase())
+                    .is_some_and(|sender| !sender.is_closed())
+            })
+            .unwrap_or(false)
+    }
+
+    // Returns false when no bot is running in the channel
+    pub fn send(&self, channel: &str, control: BotControl) -> bool {
+        self.bots
+            .lock()
+            .ok()
+            .and_then(|bots| {
+                bots.get(&channel.to_lowercase())
+                    .map(|sender| sender.send(control).is_ok())
+            })
+            .unwrap_or(false)
+    }
+}
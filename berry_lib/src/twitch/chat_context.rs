@@ -0,0 +1,109 @@
+use super::twitch_api::TwitchMessage;
+use crate::openai::moderation::{ContextMessage, ModerationContext};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const DEFAULT_CAPACITY: usize = 50;
+const DEFAULT_WINDOW: usize = 5;
+const MAX_CONTEXT_AGE: Duration = Duration::from_secs(2 * 60);
+
+struct BufferedMessage {
+    message: ContextMessage,
+    thread_id: Option<String>,
+    received: Instant,
+}
+
+// Rolling buffer of the channel's recent chat, used to give moderation the
+// conversation a message was sent into.
+pub struct ChatContextBuffer {
+    capacity: usize,
+    window: usize,
+    messages: VecDeque<BufferedMessage>,
+}
+
+impl Default for ChatContextBuffer {
+    fn default() -> Self {
+        ChatContextBuffer::new(DEFAULT_CAPACITY, DEFAULT_WINDOW)
+    }
+}
+
+impl ChatContextBuffer {
+    pub fn new(capacity: usize, window: usize) -> Self {
+        ChatContextBuffer {
+            capacity,
+            window,
+            messages: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, message: &TwitchMessage) {
+        if self.messages.len() == self.capacity {
+            self.messages.pop_front();
+        }
+
+        self.messages.push_back(BufferedMessage {
+            message: to_context_message(message),
+            thread_id: message.tag("reply-thread-parent-msg-id").map(String::from),
+            received: Instant::now(),
+        });
+    }
+
+    // Ids of the messages the user still has in the buffer, for purging them
+    pub fn recent_message_ids_from(&self, login: &str) -> Vec<String> {
+        self.messages
+            .iter()
+            .filter(|buffered| buffered.message.sender.eq_ignore_ascii_case(login))
+            .filter_map(|buffered| buffered.message.id.clone())
+            .collect()
+    }
+
+    // Replies get the thread they belong to, everything else gets the last few
+    // lines of chat.
+    pub fn context_for(&self, message: &TwitchMessage) -> ModerationContext {
+        let reply_parent = reply_parent(message);
+
+        let thread_id = message
+            .tag("reply-thread-parent-msg-id")
+            .or_else(|| message.tag("reply-parent-msg-id"));
+
+        let recent = self
+            .messages
+            .iter()
+            .filter(|buffered| buffered.received.elapsed() <= MAX_CONTEXT_AGE)
+            .filter(|buffered| match thread_id {
+                Some(thread_id) => {
+                    buffered.message.id.as_deref() == Some(thread_id)
+                        || buffered.thread_id.as_deref() == Some(thread_id)
+                }
+                None => true,
+            });
+
+        let mut messages: Vec<ContextMessage> = recent
+            .rev()
+            .take(self.window)
+            .map(|buffered| buffered.message.clone())
+            .collect();
+        messages.reverse();
+
+        ModerationContext {
+            reply_parent,
+            messages,
+        }
+    }
+}
+
+fn to_context_message(message: &TwitchMessage) -> ContextMessage {
+    ContextMessage {
+        id: message.tag("id").map(String::from),
+        sender: message.sender.clone(),
+        text: message.text.clone(),
+    }
+}
+
+fn reply_parent(message: &TwitchMessage) -> Option<ContextMessage> {
+    Some(ContextMessage {
+        id: message.tag("reply-parent-msg-id").map(String::from),
+        sender: message.tag("reply-parent-user-login")?.to_string(),
+        text: message.tag("reply-parent-msg-body")?.to_string(),
+    })
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+// Parses the short durations mods type in chat: "30", "30s", "10m", "2h", "1d".
+// A bare number is treated as seconds, matching Twitch's own /timeout.
+pub fn parse_chat_duration(input: &str) -> Option<Duration> {
+    let input = input.trim().to_lowercase();
+    if input.is_empty() {
+        return None;
+    }
+
+    let split_at = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split_at);
+
+    let amount: u64 = amount.parse().ok()?;
+    let multiplier = match unit {
+        "" | "s" | "sec" | "secs" => 1,
+        "m" | "min" | "mins" => 60,
+        "h" | "hr" | "hrs" => 60 * 60,
+        "d" | "day" | "days" => 24 * 60 * 60,
+        "w" | "wk" | "wks" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+
+    amount.checked_mul(multiplier).map(Duration::from_secs)
+}
+
+// Formats a duration back into the largest whole unit for chat replies
+pub fn format_chat_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+
+    match secs {
+        s if s >= 24 * 60 * 60 && s % (24 * 60 * 60) == 0 => format!("{}d", s / (24 * 60 * 60)),
+        s if s >= 60 * 60 && s % (60 * 60) == 0 => format!("{}h", s / (60 * 60)),
+        s if s >= 60 && s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+// Formats how long a stream has been live, e.g. "2h 5m" or "45m"
+pub fn format_uptime(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    let (hours, minutes) = (minutes / 60, minutes % 60);
+
+    match (hours, minutes) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_chat_durations() {
+        assert_eq!(parse_chat_duration("30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_chat_duration("10m"), Some(Duration::from_secs(600)));
+        assert_eq!(parse_chat_duration(" 2H "), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_chat_duration("1w"), Some(Duration::from_secs(7 * 24 * 60 * 60)));
+        assert_eq!(parse_chat_duration(""), None);
+        assert_eq!(parse_chat_duration("m"), None);
+        assert_eq!(parse_chat_duration("5 years"), None);
+        assert_eq!(parse_chat_duration("99999999999999999999w"), None);
+    }
+
+    #[test]
+    fn formats_in_the_largest_whole_unit() {
+        assert_eq!(format_chat_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(format_chat_duration(Duration::from_secs(90)), "90s");
+        assert_eq!(format_chat_duration(Duration::from_secs(600)), "10m");
+        assert_eq!(format_chat_duration(Duration::from_secs(2 * 60 * 60)), "2h");
+        assert_eq!(format_chat_duration(Duration::from_secs(24 * 60 * 60)), "1d");
+    }
+}
@@ -1,16 +1,202 @@
+use super::chat_duration::format_uptime;
+use super::cooldown::CooldownTracker;
+use super::counter_commands::{CountCommand, CounterNames};
+use super::giveaway_commands::{CurrentGiveaway, GiveawayCommand};
+use super::helix::{HelixClient, HelixError};
+use super::permission::{CallerRoles, PermissionLevel};
+use super::points_commands::{GambleCommand, GiveCommand, LeaderboardCommand, PointsCommand};
+use super::poll_commands::{CurrentPoll, PollCommand, VoteCommand};
+use super::quote_commands::{AddQuoteCommand, DelQuoteCommand, QuoteCommand};
+use super::template::{Template, TemplateContext, TemplateError};
 use super::twitch_api::TwitchMessage;
+use crate::db::counters;
+use crate::db::custom_commands::{self, CustomCommandRecord};
+use async_trait::async_trait;
+use chrono::Utc;
+use colored::Colorize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
 
-pub trait Command: Send {
-    fn execute(&self, message: &TwitchMessage) -> String;
+// Something a command wants sent once it has run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandOutput {
+    Reply(String),
+    Whisper { to: String, text: String },
+    Announce(String),
+}
+
+#[derive(Debug)]
+pub enum CommandError {
+    // Shown to the caller in chat
+    Usage(String),
+    Database(sqlx::Error),
+    Helix(HelixError),
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CommandError::Usage(usage) => write!(f, "{}", usage),
+            CommandError::Database(e) => write!(f, "Database Error: {}", e),
+            CommandError::Helix(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<sqlx::Error> for CommandError {
+    fn from(err: sqlx::Error) -> Self {
+        CommandError::Database(err)
+    }
+}
+
+impl From<HelixError> for CommandError {
+    fn from(err: HelixError) -> Self {
+        CommandError::Helix(err)
+    }
+}
+
+// Why a stored custom command can't be loaded
+#[derive(Debug)]
+pub enum InvalidCommand {
+    Template(TemplateError),
+    Permission(String),
+}
+
+impl std::fmt::Display for InvalidCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            InvalidCommand::Template(e) => write!(f, "{}", e),
+            InvalidCommand::Permission(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for InvalidCommand {}
+
+impl From<TemplateError> for InvalidCommand {
+    fn from(err: TemplateError) -> Self {
+        InvalidCommand::Template(err)
+    }
+}
+
+// What a command runs with: who invoked it and how, the bot's storage and Helix
+// client, and a sink for whatever it wants sent back. Commands can send nothing,
+// one message or several.
+pub struct CommandContext<'a> {
+    pub message: &'a TwitchMessage,
+    pub channel: &'a str,
+    pub broadcaster_id: Option<&'a str>,
+    pub caller: CallerRoles,
+    // The name or alias the command was invoked with, without the "!"
+    pub invoked_as: String,
+    pub args: Vec<&'a str>,
+    pub pool: &'a PgPool,
+    pub helix: &'a HelixClient,
+    outputs: Mutex<Vec<CommandOutput>>,
+}
+
+impl<'a> CommandContext<'a> {
+    pub fn new(
+        message: &'a TwitchMessage,
+        channel: &'a str,
+        broadcaster_id: Option<&'a str>,
+        pool: &'a PgPool,
+        helix: &'a HelixClient,
+    ) -> Self {
+        CommandContext {
+            message,
+            channel,
+            broadcaster_id,
+            caller: CallerRoles::of(message),
+            invoked_as: command_name(&message.text).unwrap_or_default(),
+            args: command_args(&message.text),
+            pool,
+            helix,
+            outputs: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn reply(&self, text: impl Into<String>) {
+        self.push(CommandOutput::Reply(text.into()));
+    }
+
+    pub fn whisper(&self, to: impl Into<String>, text: impl Into<String>) {
+        self.push(CommandOutput::Whisper {
+            to: to.into(),
+            text: text.into(),
+        });
+    }
+
+    pub fn announce(&self, text: impl Into<String>) {
+        self.push(CommandOutput::Announce(text.into()));
+    }
+
+    fn push(&self, output: CommandOutput) {
+        if let Ok(mut outputs) = self.outputs.lock() {
+            outputs.push(output);
+        }
+    }
+
+    // Everything the command sent, in order
+    pub fn take_outputs(&self) -> Vec<CommandOutput> {
+        self.outputs
+            .lock()
+            .map(|mut outputs| std::mem::take(&mut *outputs))
+            .unwrap_or_default()
+    }
+
+    // How long the stream has been live, None while it's offline
+    pub async fn uptime(&self) -> Result<Option<String>, CommandError> {
+        let broadcaster_id = match self.broadcaster_id {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+
+        let started_at = self.helix.get_stream_started_at(broadcaster_id).await?;
+
+        Ok(started_at.map(|started_at| {
+            format_uptime((Utc::now() - started_at).to_std().unwrap_or_default())
+        }))
+    }
+}
+
+#[async_trait]
+pub trait Command: Send + Sync {
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError>;
     fn get_name(&self) -> String;
     fn get_action(&self) -> String;
+
+    // Other names the command answers to, without the leading "!"
+    fn get_aliases(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn get_permission(&self) -> PermissionLevel {
+        PermissionLevel::Everyone
+    }
+
+    // Time before anyone can run the command again
+    fn get_global_cooldown(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    // Time before the same user can run the command again
+    fn get_user_cooldown(&self) -> Duration {
+        Duration::ZERO
+    }
 }
 
 pub struct PingCommand;
 
+#[async_trait]
 impl Command for PingCommand {
-    fn execute(&self, _message: &TwitchMessage) -> String {
-        "Pong!".to_string()
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        context.reply("Pong!");
+        Ok(())
     }
 
     fn get_name(&self) -> String {
@@ -20,13 +206,19 @@ impl Command for PingCommand {
     fn get_action(&self) -> String {
         "!ping".to_string()
     }
+
+    fn get_global_cooldown(&self) -> Duration {
+        Duration::from_secs(5)
+    }
 }
 
 pub struct TestCommand;
 
+#[async_trait]
 impl Command for TestCommand {
-    fn execute(&self, _message: &TwitchMessage) -> String {
-        "Test Works!".to_string()
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        context.reply("Test Works!");
+        Ok(())
     }
 
     fn get_name(&self) -> String {
@@ -39,14 +231,87 @@ impl Command for TestCommand {
 }
 
 pub struct CustomCommand {
+    pub id: i64,
     pub name: String,
     pub action: String,
-    pub callback: Box<dyn Fn(&TwitchMessage) -> String + Send>,
+    pub template: Template,
+    pub aliases: Vec<String>,
+    pub permission: PermissionLevel,
+    pub global_cooldown: Duration,
+    pub user_cooldown: Duration,
+}
+
+impl CustomCommand {
+    pub fn from_record(record: CustomCommandRecord) -> Result<Self, InvalidCommand> {
+        let cooldown = |secs: i32| Duration::from_secs(secs.max(0) as u64);
+
+        Ok(CustomCommand {
+            id: record.id,
+            action: format!("!{}", record.name),
+            template: Template::parse(&record.response)?,
+            name: record.name,
+            aliases: record.aliases,
+            permission: record.permission.parse().map_err(InvalidCommand::Permission)?,
+            global_cooldown: cooldown(record.global_cooldown_secs),
+            user_cooldown: cooldown(record.user_cooldown_secs),
+        })
+    }
+
+    async fn counter_values(&self, context: &CommandContext<'_>) -> Result<HashMap<String, i64>, CommandError> {
+        let mut values = HashMap::new();
+
+        for (name, delta) in self.template.counter_changes() {
+            let value = match delta {
+                0 => counters::get_counter(context.pool, context.channel, name)
+                    .await?
+                    .map(|counter| counter.value)
+                    .unwrap_or(0),
+                delta => {
+                    counters::add_to_counter(context.pool, context.channel, name, delta)
+                        .await?
+                        .value
+                }
+            };
+
+            values.insert(name.to_string(), value);
+        }
+
+        Ok(values)
+    }
 }
 
+#[async_trait]
 impl Command for CustomCommand {
-    fn execute(&self, message: &TwitchMessage) -> String {
-        (self.callback)(message)
+    // Looks up (and bumps) the use count, counters and uptime the template needs,
+    // then replies with the rendered template
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        let count = match self.template.uses_count() {
+            true => Some(custom_commands::increment_use_count(context.pool, self.id).await?),
+            false => None,
+        };
+
+        let uptime = match self.template.uses_uptime() {
+            true => context.uptime().await.unwrap_or_else(|e| {
+                eprintln!("{} {}", "Error Getting Stream:".bright_red(), e);
+                None
+            }),
+            false => None,
+        };
+
+        let response = self.template.render(
+            &TemplateContext {
+                user: &context.message.sender,
+                channel: context.channel,
+                args: &context.args,
+                count,
+                uptime,
+                counters: self.counter_values(context).await?,
+            },
+            &mut rand::thread_rng(),
+        );
+
+        context.reply(response);
+        Ok(())
     }
 
     fn get_name(&self) -> String {
@@ -56,11 +321,44 @@ impl Command for CustomCommand {
     fn get_action(&self) -> String {
         self.action.clone()
     }
+
+    fn get_aliases(&self) -> Vec<String> {
+        self.aliases.clone()
+    }
+
+    fn get_permission(&self) -> PermissionLevel {
+        self.permission
+    }
+
+    fn get_global_cooldown(&self) -> Duration {
+        self.global_cooldown
+    }
+
+    fn get_user_cooldown(&self) -> Duration {
+        self.user_cooldown
+    }
+}
+
+// Everything after the command name, split on whitespace
+pub fn command_args(message: &str) -> Vec<&str> {
+    message.split_whitespace().skip(1).collect()
+}
+
+pub fn command_name(message: &str) -> Option<String> {
+    message
+        .split_whitespace()
+        .next()?
+        .strip_prefix('!')
+        .map(str::to_lowercase)
 }
 
 pub struct CommandHandler {
     pub builtin_commands: Vec<Box<dyn Command>>,
     pub custom_commands: Vec<CustomCommand>,
+    counter_names: CounterNames,
+    current_poll: CurrentPoll,
+    current_giveaway: CurrentGiveaway,
+    cooldowns: CooldownTracker,
 }
 
 impl CommandHandler {
@@ -68,40 +366,190 @@ impl CommandHandler {
     where
         F: FnOnce() -> Vec<CustomCommand> + Send + 'static,
     {
-        let builtin_commands: Vec<Box<dyn Command>> =
-            vec![Box::new(PingCommand), Box::new(TestCommand)];
+        let counter_names = CounterNames::default();
+        let current_poll = CurrentPoll::default();
+        let current_giveaway = CurrentGiveaway::default();
+        let builtin_commands: Vec<Box<dyn Command>> = vec![
+            Box::new(PingCommand),
+            Box::new(TestCommand),
+            Box::new(CountCommand::new(counter_names.clone())),
+            Box::new(QuoteCommand),
+            Box::new(AddQuoteCommand),
+            Box::new(DelQuoteCommand),
+            Box::new(PointsCommand),
+            Box::new(GiveCommand),
+            Box::new(GambleCommand),
+            Box::new(LeaderboardCommand),
+            Box::new(PollCommand::new(current_poll.clone())),
+            Box::new(VoteCommand::new(current_poll.clone())),
+            Box::new(GiveawayCommand::new(current_giveaway.clone())),
+        ];
         let custom_commands = get_custom_commands();
         CommandHandler {
             builtin_commands,
             custom_commands,
+            counter_names,
+            current_poll,
+            current_giveaway,
+            cooldowns: CooldownTracker::default(),
         }
     }
 
+    pub fn set_custom_commands(&mut self, custom_commands: Vec<CustomCommand>) {
+        self.custom_commands = custom_commands;
+    }
+
+    pub fn set_counter_names(&mut self, names: Vec<String>) {
+        self.counter_names.set(names);
+    }
+
+    pub fn current_poll(&self) -> &CurrentPoll {
+        &self.current_poll
+    }
+
+    pub fn current_giveaway(&self) -> &CurrentGiveaway {
+        &self.current_giveaway
+    }
+
+    fn commands(&self) -> impl Iterator<Item = &dyn Command> {
+        self.builtin_commands
+            .iter()
+            .map(|command| command.as_ref())
+            .chain(self.custom_commands.iter().map(|command| command as &dyn Command))
+    }
+
+    // Matches the first word of the message against command names, then aliases,
+    // then counters ("!deaths" is short for "!count deaths"). Built-in commands take
+    // precedence over custom ones with the same name.
     pub fn get_command(&self, message: &str) -> Option<&dyn Command> {
+        let command_name = command_name(message)?;
 
-        if message.starts_with('!') {
-            let command_name = &message[1..].to_lowercase();
+        self.commands()
+            .find(|command| command.get_name() == command_name)
+            .or_else(|| {
+                self.commands()
+                    .find(|command| command.get_aliases().contains(&command_name))
+            })
+            .or_else(|| {
+                self.counter_names
+                    .contains(&command_name)
+                    .then(|| self.commands().find(|command| command.get_name() == "count"))
+                    .flatten()
+            })
+    }
 
-            for command in &self.builtin_commands {
-    
-                if command.get_name() == command_name.to_string() {
-     
-                    return Some(command.as_ref());
-                }
-            }
+    // Whether the sender may run the command in this message right now. Starts the
+    // command's cooldowns when they may. The broadcaster and mods skip cooldowns.
+    pub fn check_access(&mut self, message: &TwitchMessage) -> bool {
+        let (name, permission, global_cooldown, user_cooldown) = match self.get_command(&message.text) {
+            Some(command) => (
+                command.get_name(),
+                command.get_permission(),
+                command.get_global_cooldown(),
+                command.get_user_cooldown(),
+            ),
+            None => return false,
+        };
 
-            for command in &self.custom_commands {
+        if !permission.allows(message) {
+            return false;
+        }
 
-                if command.get_name() == command_name.to_string() {
+        if message.is_moderator() {
+            return true;
+        }
 
-                    return Some(command);
-                }
-            }
+        self.cooldowns
+            .try_start(&name, &message.sender, global_cooldown, user_cooldown)
+    }
+}
 
-        } else {
-            ()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(name: &str, aliases: &[&str], permission: &str, user_cooldown_secs: i32) -> CustomCommandRecord {
+        CustomCommandRecord {
+            id: 1,
+            channel: "berry".to_string(),
+            name: name.to_string(),
+            response: "hi {user}".to_string(),
+            enabled: true,
+            created_by: "berry".to_string(),
+            use_count: 0,
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            permission: permission.to_string(),
+            global_cooldown_secs: 0,
+            user_cooldown_secs,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
         }
+    }
+
+    fn handler(records: Vec<CustomCommandRecord>) -> CommandHandler {
+        let commands = records
+            .into_iter()
+            .map(|record| CustomCommand::from_record(record).expect("valid command"))
+            .collect();
+        CommandHandler::new(move || commands)
+    }
+
+    fn chat(sender: &str, badges: &str, text: &str) -> TwitchMessage {
+        TwitchMessage {
+            sender: sender.to_string(),
+            text: text.to_string(),
+            tags: HashMap::from([("badges".to_string(), badges.to_string())]),
+        }
+    }
+
+    fn resolved(handler: &CommandHandler, message: &str) -> Option<String> {
+        handler.get_command(message).map(|command| command.get_name())
+    }
+
+    #[test]
+    fn resolves_names_then_aliases_then_counters() {
+        let mut handler = handler(vec![
+            record("discord", &["dc", "server"], "everyone", 0),
+            record("ping", &[], "everyone", 0),
+            record("pong", &["test"], "everyone", 0),
+        ]);
+        handler.set_counter_names(vec!["deaths".to_string()]);
+
+        assert_eq!(resolved(&handler, "!discord"), Some("discord".to_string()));
+        assert_eq!(resolved(&handler, "!DC please"), Some("discord".to_string()));
+        assert_eq!(resolved(&handler, "!server"), Some("discord".to_string()));
+        assert_eq!(resolved(&handler, "!deaths"), Some("count".to_string()));
+        assert_eq!(resolved(&handler, "!nothing"), None);
+        assert_eq!(resolved(&handler, "discord"), None);
+
+        // Built-in commands win over custom ones and their aliases
+        let ping = handler.get_command("!ping").expect("ping resolves");
+        assert_eq!(ping.get_global_cooldown(), Duration::from_secs(5));
+        assert_eq!(resolved(&handler, "!test"), Some("test".to_string()));
+    }
+
+    #[test]
+    fn checks_permission_and_cooldowns() {
+        let mut handler = handler(vec![
+            record("hug", &[], "everyone", 60),
+            record("so", &[], "moderator", 0),
+        ]);
+
+        assert!(handler.check_access(&chat("alice", "", "!hug bob")));
+        assert!(!handler.check_access(&chat("alice", "", "!hug bob")));
+        assert!(handler.check_access(&chat("bob", "", "!hug alice")));
+        // Mods skip cooldowns
+        assert!(handler.check_access(&chat("a_mod", "moderator/1", "!hug")));
+        assert!(handler.check_access(&chat("a_mod", "moderator/1", "!hug")));
+
+        assert!(!handler.check_access(&chat("alice", "subscriber/3", "!so someone")));
+        assert!(handler.check_access(&chat("berry", "broadcaster/1", "!so someone")));
+        assert!(!handler.check_access(&chat("alice", "", "!unknown")));
+    }
 
-        None
+    #[test]
+    fn refuses_stored_commands_with_an_unknown_permission() {
+        let error = CustomCommand::from_record(record("hug", &[], "mods", 0)).err();
+        assert!(matches!(error, Some(InvalidCommand::Permission(_))));
     }
 }
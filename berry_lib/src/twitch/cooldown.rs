@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// Longest cooldown a command can have
+pub const MAX_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+const PRUNE_AFTER_ENTRIES: usize = 5_000;
+
+// When each command was last run, channel-wide and per user
+#[derive(Default)]
+pub struct CooldownTracker {
+    global: HashMap<String, Instant>,
+    per_user: HashMap<(String, String), Instant>,
+}
+
+impl CooldownTracker {
+    // Returns false while either cooldown is still running. Otherwise starts both
+    // cooldowns and returns true.
+    pub fn try_start(
+        &mut self,
+        command: &str,
+        user: &str,
+        global: Duration,
+        per_user: Duration,
+    ) -> bool {
+        self.try_start_at(command, user, global, per_user, Instant::now())
+    }
+
+    fn try_start_at(
+        &mut self,
+        command: &str,
+        user: &str,
+        global: Duration,
+        per_user: Duration,
+        now: Instant,
+    ) -> bool {
+        let user_key = (command.to_string(), user.to_lowercase());
+
+        let running = |last: Option<&Instant>, cooldown: Duration| {
+            last.is_some_and(|last| now.duration_since(*last) < cooldown)
+        };
+
+        if running(self.global.get(command), global) || running(self.per_user.get(&user_key), per_user) {
+            return false;
+        }
+
+        if self.per_user.len() >= PRUNE_AFTER_ENTRIES {
+            self.prune(now);
+        }
+
+        if !global.is_zero() {
+            self.global.insert(command.to_string(), now);
+        }
+        if !per_user.is_zero() {
+            self.per_user.insert(user_key, now);
+        }
+
+        true
+    }
+
+    // Drops expired cooldowns so the map doesn't grow with every chatter
+    fn prune(&mut self, now: Instant) {
+        self.global.retain(|_, last| now.duration_since(*last) < MAX_COOLDOWN);
+        self.per_user.retain(|_, last| now.duration_since(*last) < MAX_COOLDOWN);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn global_cooldown_applies_to_everyone() {
+        let mut cooldowns = CooldownTracker::default();
+        let start = Instant::now();
+        let global = 30 * SECOND;
+
+        assert!(cooldowns.try_start_at("lurk", "alice", global, Duration::ZERO, start));
+        assert!(!cooldowns.try_start_at("lurk", "bob", global, Duration::ZERO, start + 29 * SECOND));
+        // Other commands aren't held up
+        assert!(cooldowns.try_start_at("hug", "bob", global, Duration::ZERO, start + 29 * SECOND));
+        assert!(cooldowns.try_start_at("lurk", "bob", global, Duration::ZERO, start + global));
+    }
+
+    #[test]
+    fn user_cooldown_applies_per_user() {
+        let mut cooldowns = CooldownTracker::default();
+        let start = Instant::now();
+        let per_user = 60 * SECOND;
+
+        assert!(cooldowns.try_start_at("hug", "Alice", Duration::ZERO, per_user, start));
+        assert!(!cooldowns.try_start_at("hug", "alice", Duration::ZERO, per_user, start + SECOND));
+        assert!(cooldowns.try_start_at("hug", "bob", Duration::ZERO, per_user, start + SECOND));
+        assert!(cooldowns.try_start_at("hug", "alice", Duration::ZERO, per_user, start + per_user));
+    }
+
+    #[test]
+    fn a_refused_run_starts_no_cooldown() {
+        let mut cooldowns = CooldownTracker::default();
+        let start = Instant::now();
+
+        assert!(cooldowns.try_start_at("hug", "alice", 10 * SECOND, 60 * SECOND, start));
+        // Bob is refused by the global cooldown, which mustn't start his own
+        assert!(!cooldowns.try_start_at("hug", "bob", 10 * SECOND, 60 * SECOND, start + SECOND));
+        assert!(cooldowns.try_start_at("hug", "bob", 10 * SECOND, 60 * SECOND, start + 10 * SECOND));
+    }
+}
@@ -0,0 +1,165 @@
+use super::commands::{Command, CommandContext, CommandError};
+use crate::db::counters::{self, normalize_counter_name};
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+const USAGE: &str = "Usage: !count <name> [+|-|+N|-N|set N|reset|delete]";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CounterChange {
+    Add(i64),
+    Set(i64),
+    Delete,
+}
+
+// Returns None when there's nothing after the counter name (just show it), and
+// Err with a usage line when the change doesn't parse.
+pub fn parse_counter_change(args: &[&str]) -> Option<Result<CounterChange, String>> {
+    let parsed = match args {
+        [] => return None,
+        ["+"] => Ok(CounterChange::Add(1)),
+        ["-"] => Ok(CounterChange::Add(-1)),
+        [delta] if delta.starts_with(['+', '-']) => delta.parse().map(CounterChange::Add).map_err(|_| USAGE),
+        ["set", value] => value.parse().map(CounterChange::Set).map_err(|_| USAGE),
+        ["reset"] => Ok(CounterChange::Set(0)),
+        ["delete"] | ["remove"] => Ok(CounterChange::Delete),
+        _ => Err(USAGE),
+    };
+
+    Some(parsed.map_err(String::from))
+}
+
+// Names of the channel's counters, shared between the command handler (which
+// routes "!deaths" to !count) and !count (which creates and deletes them)
+#[derive(Clone, Default)]
+pub struct CounterNames {
+    names: Arc<RwLock<HashSet<String>>>,
+}
+
+impl CounterNames {
+    pub fn set(&self, names: Vec<String>) {
+        if let Ok(mut current) = self.names.write() {
+            *current = names.into_iter().collect();
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names
+            .read()
+            .map(|names| names.contains(name))
+            .unwrap_or(false)
+    }
+
+    fn insert(&self, name: &str) {
+        if let Ok(mut names) = self.names.write() {
+            names.insert(name.to_string());
+        }
+    }
+
+    fn remove(&self, name: &str) {
+        if let Ok(mut names) = self.names.write() {
+            names.remove(name);
+        }
+    }
+}
+
+// !count lists the counters, !count <name> shows one, and mods change them with
+// !count <name> + / - / set 5 / reset / delete. Every counter also answers to its
+// own name, so "!deaths +" works the same as "!count deaths +".
+pub struct CountCommand {
+    counter_names: CounterNames,
+}
+
+impl CountCommand {
+    pub fn new(counter_names: CounterNames) -> Self {
+        CountCommand { counter_names }
+    }
+
+    async fn list(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        let counters = counters::list_counters(context.pool, context.channel).await?;
+
+        if counters.is_empty() {
+            context.reply("No counters yet");
+            return Ok(());
+        }
+
+        let list: Vec<String> = counters
+            .iter()
+            .map(|counter| format!("{}: {}", counter.name, counter.value))
+            .collect();
+
+        context.reply(format!("Counters: {}", list.join(", ")));
+        Ok(())
+    }
+
+    async fn show(&self, context: &CommandContext<'_>, name: &str) -> Result<(), CommandError> {
+        match counters::get_counter(context.pool, context.channel, name).await? {
+            Some(counter) => context.reply(format!("{}: {}", counter.name, counter.value)),
+            None => context.reply(format!("No counter named {}", name)),
+        }
+
+        Ok(())
+    }
+
+    async fn change(
+        &self,
+        context: &CommandContext<'_>,
+        name: &str,
+        change: CounterChange,
+    ) -> Result<(), CommandError> {
+        let counter = match change {
+            CounterChange::Add(delta) => counters::add_to_counter(context.pool, context.channel, name, delta).await?,
+            CounterChange::Set(value) => counters::set_counter(context.pool, context.channel, name, value).await?,
+            CounterChange::Delete => {
+                let deleted = counters::delete_counter(context.pool, context.channel, name).await?;
+                self.counter_names.remove(name);
+
+                match deleted {
+                    true => context.reply(format!("Deleted counter {}", name)),
+                    false => context.reply(format!("No counter named {}", name)),
+                }
+                return Ok(());
+            }
+        };
+
+        self.counter_names.insert(&counter.name);
+        context.reply(format!("{}: {}", counter.name, counter.value));
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Command for CountCommand {
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        let (name, args) = match context.invoked_as.as_str() {
+            "count" => match context.args.split_first() {
+                Some((name, args)) => (*name, args),
+                None => ("", &[][..]),
+            },
+            counter => (counter, &context.args[..]),
+        };
+
+        if name.is_empty() {
+            return self.list(context).await;
+        }
+
+        let name = normalize_counter_name(name).ok_or_else(|| CommandError::Usage(USAGE.to_string()))?;
+
+        match parse_counter_change(args) {
+            None => self.show(context, &name).await,
+            // Only mods change counters, everyone else is ignored
+            Some(_) if !context.caller.is_moderator => Ok(()),
+            Some(Err(usage)) => Err(CommandError::Usage(usage)),
+            Some(Ok(change)) => self.change(context, &name, change).await,
+        }
+    }
+
+    fn get_name(&self) -> String {
+        "count".to_string()
+    }
+
+    fn get_action(&self) -> String {
+        "!count".to_string()
+    }
+}
@@ -0,0 +1,299 @@
+use super::chat_duration::format_chat_duration;
+use super::helix::HelixClient;
+use crate::db::moderation_audit;
+use crate::openai::moderation::{ModerationCategory, PunishmentAction};
+use chrono::{DateTime, Utc};
+use colored::*;
+use sqlx::PgPool;
+use std::time::Duration;
+
+// Shadow mode logs what automatic moderation would have done without acting on it.
+// Moderator commands are always enforced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModMode {
+    Shadow,
+    Enforce,
+}
+
+impl ModMode {
+    pub fn from_env() -> Self {
+        std::env::var("MODERATION_MODE")
+            .ok()
+            .and_then(|mode| mode.parse().ok())
+            .unwrap_or(ModMode::Shadow)
+    }
+
+    fn shadows(&self, source: &AuditSource) -> bool {
+        matches!(source, AuditSource::Automatic { .. }) && *self == ModMode::Shadow
+    }
+}
+
+impl std::fmt::Display for ModMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ModMode::Shadow => write!(f, "shadow"),
+            ModMode::Enforce => write!(f, "enforce"),
+        }
+    }
+}
+
+impl std::str::FromStr for ModMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "shadow" => Ok(ModMode::Shadow),
+            "enforce" => Ok(ModMode::Enforce),
+            other => Err(format!("Unknown mod mode: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnforcementAction {
+    Warn,
+    Delete(Option<String>),
+    Timeout(u64),
+    Ban,
+    Unban,
+    Purge(Vec<String>),
+}
+
+impl EnforcementAction {
+    pub fn from_punishment(punishment: PunishmentAction, message_id: Option<&str>) -> Option<Self> {
+        match punishment {
+            PunishmentAction::Timeout(duration) => Some(EnforcementAction::Timeout(duration)),
+            PunishmentAction::Ban => Some(EnforcementAction::Ban),
+            PunishmentAction::Delete => Some(EnforcementAction::Delete(message_id.map(String::from))),
+            PunishmentAction::Warn => Some(EnforcementAction::Warn),
+            PunishmentAction::None => None,
+        }
+    }
+
+    fn counts_as_strike(&self) -> bool {
+        !matches!(self, EnforcementAction::Unban | EnforcementAction::Purge(_))
+    }
+}
+
+impl std::fmt::Display for EnforcementAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EnforcementAction::Warn => write!(f, "warn"),
+            EnforcementAction::Delete(_) => write!(f, "delete"),
+            EnforcementAction::Timeout(secs) => {
+                write!(f, "timeout {}", format_chat_duration(Duration::from_secs(*secs)))
+            }
+            EnforcementAction::Ban => write!(f, "ban"),
+            EnforcementAction::Unban => write!(f, "unban"),
+            EnforcementAction::Purge(ids) => write!(f, "purge {} messages", ids.len()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum AuditSource {
+    Automatic { category: ModerationCategory, score: f64 },
+    Moderator(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditOutcome {
+    Applied,
+    Shadowed,
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub source: AuditSource,
+    pub target: String,
+    pub action: EnforcementAction,
+    pub reason: String,
+    pub outcome: AuditOutcome,
+}
+
+impl AuditEntry {
+    // Offences count as strikes even in shadow mode, so the mode can be judged on
+    // them, but not when Helix refused the action
+    pub fn is_strike(&self) -> bool {
+        self.action.counts_as_strike() && !matches!(self.outcome, AuditOutcome::Failed(_))
+    }
+}
+
+pub struct EnforcementTarget {
+    pub login: String,
+    pub user_id: Option<String>,
+}
+
+// Every moderation action, automatic or typed by a mod, goes through here so it hits
+// Helix the same way and lands in the same audit log, which strikes are counted from.
+pub struct Enforcer {
+    mode: ModMode,
+    channel: String,
+    pool: PgPool,
+}
+
+impl Enforcer {
+    pub fn new(mode: ModMode, channel: &str, pool: PgPool) -> Self {
+        Enforcer {
+            mode,
+            channel: channel.to_string(),
+            pool,
+        }
+    }
+
+    pub fn mode(&self) -> ModMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: ModMode) {
+        self.mode = mode;
+    }
+
+    pub async fn strikes(&self, login: &str) -> Result<i64, sqlx::Error> {
+        moderation_audit::count_strikes(&self.pool, &self.channel, login).await
+    }
+
+    pub async fn enforce(
+        &mut self,
+        helix: &HelixClient,
+        broadcaster_id: &str,
+        target: EnforcementTarget,
+        action: EnforcementAction,
+        reason: &str,
+        source: AuditSource,
+    ) -> AuditOutcome {
+        let outcome = if self.mode.shadows(&source) {
+            AuditOutcome::Shadowed
+        } else {
+            match apply_action(helix, broadcaster_id, &target, &action, reason).await {
+                Ok(()) => AuditOutcome::Applied,
+                Err(e) => AuditOutcome::Failed(e),
+            }
+        };
+
+        let entry = AuditEntry {
+            at: Utc::now(),
+            source,
+            target: target.login.to_lowercase(),
+            action,
+            reason: reason.to_string(),
+            outcome: outcome.clone(),
+        };
+
+        println!(
+            "{} {} {} {:?} ({}) -> {:?}",
+            "MOD AUDIT".bright_magenta().bold().underline(),
+            entry.target,
+            entry.action,
+            entry.source,
+            entry.reason,
+            entry.outcome
+        );
+
+        if let Err(e) = moderation_audit::record_entry(&self.pool, &self.channel, &entry).await {
+            eprintln!("{} {}", "Error Recording Moderation Audit:".bright_red(), e);
+        }
+
+        outcome
+    }
+}
+
+async fn apply_action(
+    helix: &HelixClient,
+    broadcaster_id: &str,
+    target: &EnforcementTarget,
+    action: &EnforcementAction,
+    reason: &str,
+) -> Result<(), String> {
+    match action {
+        EnforcementAction::Warn => Ok(()),
+        EnforcementAction::Delete(Some(message_id)) => helix
+            .delete_chat_message(broadcaster_id, message_id)
+            .await
+            .map_err(|e| e.to_string()),
+        EnforcementAction::Delete(None) => Err("Message has no id to delete".to_string()),
+        EnforcementAction::Purge(message_ids) => {
+            let mut failed = 0;
+            for message_id in message_ids {
+                if helix.delete_chat_message(broadcaster_id, message_id).await.is_err() {
+                    failed += 1;
+                }
+            }
+
+            match failed {
+                0 => Ok(()),
+                failed => Err(format!("Failed to delete {} of {} messages", failed, message_ids.len())),
+            }
+        }
+        EnforcementAction::Timeout(_) | EnforcementAction::Ban | EnforcementAction::Unban => {
+            let user_id = match &target.user_id {
+                Some(user_id) => user_id.clone(),
+                None => helix
+                    .get_user_id(&target.login)
+                    .await
+                    .map_err(|e| e.to_string())?,
+            };
+
+            let result = match action {
+                EnforcementAction::Timeout(secs) => {
+                    helix.ban_user(broadcaster_id, &user_id, Some(*secs), reason).await
+                }
+                EnforcementAction::Ban => helix.ban_user(broadcaster_id, &user_id, None, reason).await,
+                _ => helix.unban_user(broadcaster_id, &user_id).await,
+            };
+
+            result.map_err(|e| e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn automatic() -> AuditSource {
+        AuditSource::Automatic {
+            category: ModerationCategory::Harassment,
+            score: 0.9,
+        }
+    }
+
+    fn entry(action: EnforcementAction, outcome: AuditOutcome) -> AuditEntry {
+        AuditEntry {
+            at: Utc::now(),
+            source: automatic(),
+            target: "someone".to_string(),
+            action,
+            reason: String::new(),
+            outcome,
+        }
+    }
+
+    #[test]
+    fn shadow_mode_only_holds_back_automatic_actions() {
+        let moderator = AuditSource::Moderator("a_mod".to_string());
+
+        assert!(ModMode::Shadow.shadows(&automatic()));
+        assert!(!ModMode::Shadow.shadows(&moderator));
+        assert!(!ModMode::Enforce.shadows(&automatic()));
+        assert!(!ModMode::Enforce.shadows(&moderator));
+    }
+
+    #[test]
+    fn failed_actions_and_cleanups_are_not_strikes() {
+        assert!(entry(EnforcementAction::Timeout(600), AuditOutcome::Applied).is_strike());
+        assert!(entry(EnforcementAction::Warn, AuditOutcome::Shadowed).is_strike());
+        assert!(!entry(EnforcementAction::Ban, AuditOutcome::Failed("403".to_string())).is_strike());
+        assert!(!entry(EnforcementAction::Unban, AuditOutcome::Applied).is_strike());
+        assert!(!entry(EnforcementAction::Purge(vec![]), AuditOutcome::Applied).is_strike());
+    }
+
+    #[test]
+    fn parses_mod_modes() {
+        assert_eq!(" Enforce ".parse::<ModMode>(), Ok(ModMode::Enforce));
+        assert_eq!("shadow".parse::<ModMode>(), Ok(ModMode::Shadow));
+        assert!("off".parse::<ModMode>().is_err());
+    }
+}
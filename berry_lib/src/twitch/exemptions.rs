@@ -0,0 +1,330 @@
+use super::chat_duration::{format_chat_duration, parse_chat_duration};
+use super::twitch_api::TwitchMessage;
+use colored::*;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::time::{Duration, Instant};
+
+const BUNDLED_EXEMPTIONS: &str = include_str!("../config/moderation_exemptions.json");
+const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(5 * 60);
+const MAX_GRACE_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExemptionSettings {
+    #[serde(default = "default_true")]
+    pub exempt_vips: bool,
+    #[serde(default)]
+    pub exempt_subscribers: bool,
+    #[serde(default)]
+    pub trusted_users: Vec<String>,
+}
+
+impl Default for ExemptionSettings {
+    fn default() -> Self {
+        ExemptionSettings {
+            exempt_vips: true,
+            exempt_subscribers: false,
+            trusted_users: vec![],
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct ExemptionConfig {
+    default: ExemptionSettings,
+    #[serde(default)]
+    channels: HashMap<String, ExemptionSettings>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExemptionReason {
+    Broadcaster,
+    Moderator,
+    Vip,
+    Subscriber,
+    TrustedUser,
+    Grace,
+}
+
+impl std::fmt::Display for ExemptionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ExemptionReason::Broadcaster => write!(f, "Broadcaster"),
+            ExemptionReason::Moderator => write!(f, "Moderator"),
+            ExemptionReason::Vip => write!(f, "VIP"),
+            ExemptionReason::Subscriber => write!(f, "Subscriber"),
+            ExemptionReason::TrustedUser => write!(f, "Trusted User"),
+            ExemptionReason::Grace => write!(f, "Grace Period"),
+        }
+    }
+}
+
+// Who the moderation pipeline skips in a channel. Broadcaster and mods are always
+// exempt, the rest is driven by the channel's settings and mod-granted grace periods.
+pub struct ModerationExemptions {
+    settings: ExemptionSettings,
+    trusted_users: HashSet<String>,
+    grace_periods: HashMap<String, Instant>,
+}
+
+impl ModerationExemptions {
+    pub fn new(settings: ExemptionSettings) -> Self {
+        let trusted_users = settings
+            .trusted_users
+            .iter()
+            .map(|user| normalize_login(user))
+            .collect();
+
+        ModerationExemptions {
+            settings,
+            trusted_users,
+            grace_periods: HashMap::new(),
+        }
+    }
+
+    pub fn for_channel(channel: &str) -> Self {
+        let settings = match load_exemption_settings(channel) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!(
+                    "{} {}",
+                    "Error Loading Exemption Settings, Using Bundled Defaults:".yellow(),
+                    e
+                );
+                parse_exemption_settings(BUNDLED_EXEMPTIONS, channel).unwrap_or_default()
+            }
+        };
+
+        ModerationExemptions::new(settings)
+    }
+
+    pub fn exemption_for(&mut self, message: &TwitchMessage) -> Option<ExemptionReason> {
+        if message.is_broadcaster() {
+            return Some(ExemptionReason::Broadcaster);
+        }
+
+        if message.is_moderator() {
+            return Some(ExemptionReason::Moderator);
+        }
+
+        if self.settings.exempt_vips && message.is_vip() {
+            return Some(ExemptionReason::Vip);
+        }
+
+        if self.settings.exempt_subscribers && message.is_subscriber() {
+            return Some(ExemptionReason::Subscriber);
+        }
+
+        let login = normalize_login(&message.sender);
+
+        if self.trusted_users.contains(&login) {
+            return Some(ExemptionReason::TrustedUser);
+        }
+
+        match self.grace_periods.get(&login) {
+            Some(expires) if *expires > Instant::now() => Some(ExemptionReason::Grace),
+            Some(_) => {
+                self.grace_periods.remove(&login);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn grant_grace(&mut self, user: &str, duration: Duration) {
+        let duration = duration.min(MAX_GRACE_PERIOD);
+        self.grace_periods
+            .insert(normalize_login(user), Instant::now() + duration);
+    }
+
+    pub fn revoke_grace(&mut self, user: &str) -> bool {
+        self.grace_periods.remove(&normalize_login(user)).is_some()
+    }
+
+    // Handles "!permit user [duration]" from a mod. Returns the chat reply if the
+    // message was a permit command, None otherwise.
+    pub fn handle_permit_command(&mut self, message: &TwitchMessage) -> Option<String> {
+        let mut parts = message.text.split_whitespace();
+
+        if !parts.next()?.eq_ignore_ascii_case("!permit") {
+            return None;
+        }
+
+        if !message.is_moderator() {
+            return Some(String::new());
+        }
+
+        let user = match parts.next() {
+            Some(user) => normalize_login(user),
+            None => return Some("Usage: !permit <user> [duration]".to_string()),
+        };
+
+        let duration = match parts.next() {
+            Some(raw) => match parse_chat_duration(raw) {
+                Some(duration) => duration,
+                None => return Some(format!("Invalid duration: {}", raw)),
+            },
+            None => DEFAULT_GRACE_PERIOD,
+        };
+
+        self.grant_grace(&user, duration);
+
+        Some(format!(
+            "{} is exempt from moderation for {}",
+            user,
+            format_chat_duration(duration.min(MAX_GRACE_PERIOD))
+        ))
+    }
+}
+
+// "@Someone " -> "someone", for comparing logins typed in chat
+pub(crate) fn normalize_login(user: &str) -> String {
+    user.trim().trim_start_matches('@').to_lowercase()
+}
+
+// Read when the bot joins a channel, so edits apply on the bot's next start
+fn load_exemption_settings(channel: &str) -> Result<ExemptionSettings, String> {
+    match std::env::var("MODERATION_EXEMPTIONS_PATH") {
+        Ok(path) => {
+            let config_data = fs::read_to_string(&path)
+                .map_err(|e| format!("Error reading config file {}: {}", path, e))?;
+            parse_exemption_settings(&config_data, channel)
+        }
+        Err(_) => parse_exemption_settings(BUNDLED_EXEMPTIONS, channel),
+    }
+}
+
+fn parse_exemption_settings(config_data: &str, channel: &str) -> Result<ExemptionSettings, String> {
+    let mut config: ExemptionConfig = serde_json::from_str(config_data)
+        .map_err(|e| format!("Error parsing config file: {}", e))?;
+
+    Ok(config
+        .channels
+        .remove(&channel.to_lowercase())
+        .unwrap_or(config.default))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(sender: &str, badges: &str, text: &str) -> TwitchMessage {
+        TwitchMessage {
+            sender: sender.to_string(),
+            text: text.to_string(),
+            tags: HashMap::from([("badges".to_string(), badges.to_string())]),
+        }
+    }
+
+    fn exemptions() -> ModerationExemptions {
+        ModerationExemptions::new(ExemptionSettings {
+            trusted_users: vec!["@Trusted_Bot".to_string()],
+            ..ExemptionSettings::default()
+        })
+    }
+
+    #[test]
+    fn exempt_roles_bypass_moderation() {
+        let mut exemptions = exemptions();
+
+        let cases = [
+            (message("streamer", "broadcaster/1", "hi"), Some(ExemptionReason::Broadcaster)),
+            (message("a_mod", "moderator/1", "hi"), Some(ExemptionReason::Moderator)),
+            (message("a_vip", "vip/1", "hi"), Some(ExemptionReason::Vip)),
+            (message("trusted_bot", "", "hi"), Some(ExemptionReason::TrustedUser)),
+            // Subscribers aren't exempt unless the channel says so
+            (message("a_sub", "subscriber/6", "hi"), None),
+            (message("viewer", "", "hi"), None),
+        ];
+
+        for (message, reason) in &cases {
+            assert_eq!(exemptions.exemption_for(message), *reason, "{}", message.sender);
+        }
+
+        let mut lenient = ModerationExemptions::new(ExemptionSettings {
+            exempt_vips: false,
+            exempt_subscribers: true,
+            trusted_users: vec![],
+        });
+        assert_eq!(lenient.exemption_for(&message("a_vip", "vip/1", "hi")), None);
+        assert_eq!(
+            lenient.exemption_for(&message("a_sub", "subscriber/6", "hi")),
+            Some(ExemptionReason::Subscriber)
+        );
+    }
+
+    #[test]
+    fn bundled_settings_load_for_any_channel() {
+        let settings = parse_exemption_settings(BUNDLED_EXEMPTIONS, "SomeChannel").unwrap();
+        assert!(settings.exempt_vips);
+        assert!(!settings.exempt_subscribers);
+    }
+
+    #[test]
+    fn channel_settings_override_the_default() {
+        let config = r#"{
+            "default": { "exempt_vips": true, "exempt_subscribers": false, "trusted_users": [] },
+            "channels": { "berry": { "exempt_vips": false, "exempt_subscribers": true, "trusted_users": ["nightbot"] } }
+        }"#;
+
+        let berry = parse_exemption_settings(config, "Berry").unwrap();
+        assert!(!berry.exempt_vips);
+        assert!(berry.exempt_subscribers);
+        assert_eq!(berry.trusted_users, vec!["nightbot".to_string()]);
+
+        assert!(parse_exemption_settings(config, "other").unwrap().exempt_vips);
+        assert!(parse_exemption_settings("{", "berry").is_err());
+    }
+
+    #[test]
+    fn logins_compare_without_at_or_case() {
+        assert_eq!(normalize_login(" @Berry_Fan "), "berry_fan");
+        assert_eq!(normalize_login("berry"), "berry");
+    }
+
+    #[test]
+    fn permits_expire() {
+        let mut exemptions = exemptions();
+        let viewer = message("Viewer", "", "look at this link");
+
+        exemptions.grant_grace("@viewer", Duration::from_secs(60));
+        assert_eq!(exemptions.exemption_for(&viewer), Some(ExemptionReason::Grace));
+        assert!(exemptions.revoke_grace("VIEWER"));
+        assert_eq!(exemptions.exemption_for(&viewer), None);
+
+        // A permit that has run out is dropped the next time it's checked
+        exemptions.grant_grace("viewer", Duration::ZERO);
+        assert_eq!(exemptions.exemption_for(&viewer), None);
+        assert!(!exemptions.revoke_grace("viewer"));
+    }
+
+    #[test]
+    fn only_mods_can_permit() {
+        let mut exemptions = exemptions();
+
+        assert_eq!(exemptions.handle_permit_command(&message("viewer", "", "hello")), None);
+        assert_eq!(
+            exemptions.handle_permit_command(&message("viewer", "", "!permit friend")),
+            Some(String::new())
+        );
+        assert_eq!(exemptions.exemption_for(&message("friend", "", "hi")), None);
+
+        assert_eq!(
+            exemptions.handle_permit_command(&message("a_mod", "moderator/1", "!permit @Friend 2d")),
+            Some("friend is exempt from moderation for 1d".to_string())
+        );
+        assert_eq!(
+            exemptions.exemption_for(&message("friend", "", "hi")),
+            Some(ExemptionReason::Grace)
+        );
+        assert_eq!(
+            exemptions.handle_permit_command(&message("a_mod", "moderator/1", "!permit friend soon")),
+            Some("Invalid duration: soon".to_string())
+        );
+    }
+}
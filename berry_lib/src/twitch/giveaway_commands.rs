@@ -0,0 +1,293 @@
+use super::commands::{Command, CommandContext, CommandError};
+use super::helix::HelixClient;
+use crate::db::giveaways::{self, GiveawayDraw, GiveawayEntry, GiveawayRecord};
+use async_trait::async_trait;
+use colored::Colorize;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+pub const DEFAULT_SUBSCRIBER_WEIGHT: i32 = 2;
+pub const MAX_SUBSCRIBER_WEIGHT: i32 = 10;
+const MAX_KEYWORD_LENGTH: usize = 25;
+const USAGE: &str = "Usage: !giveaway [start <keyword> | close | draw | end]";
+
+// Keywords are a single word, matched case-insensitively
+pub fn normalize_keyword(keyword: &str) -> Option<String> {
+    let keyword = keyword.trim().to_lowercase();
+
+    let valid = !keyword.is_empty()
+        && keyword.len() <= MAX_KEYWORD_LENGTH
+        && !keyword.chars().any(char::is_whitespace);
+
+    valid.then_some(keyword)
+}
+
+// Picks a winner with odds proportional to each entry's weight. Entries for whom
+// `is_present` is false are skipped and the draw goes on among the rest, so the
+// same seed, entries and presence always give the same result.
+pub fn draw_winner<F>(entries: &[GiveawayEntry], seed: u64, is_present: F) -> (Option<String>, Vec<String>)
+where
+    F: Fn(&str) -> bool,
+{
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+    let mut remaining: Vec<&GiveawayEntry> = entries.iter().collect();
+    remaining.sort_by(|a, b| a.login.cmp(&b.login));
+    let mut skipped = Vec::new();
+
+    while !remaining.is_empty() {
+        let tickets: i64 = remaining.iter().map(|entry| entry.weight.max(1) as i64).sum();
+        let mut ticket = rng.gen_range(0..tickets);
+
+        let index = remaining
+            .iter()
+            .position(|entry| {
+                let weight = entry.weight.max(1) as i64;
+                if ticket < weight {
+                    return true;
+                }
+                ticket -= weight;
+                false
+            })
+            .unwrap_or(0);
+
+        let entry = remaining.remove(index);
+        if is_present(&entry.login) {
+            return (Some(entry.login.clone()), skipped);
+        }
+        skipped.push(entry.login.clone());
+    }
+
+    (None, skipped)
+}
+
+// Draws a winner among the entries that haven't won yet, skipping anyone who has
+// left chat. When the viewer list can't be fetched everyone counts as present.
+pub async fn draw(
+    pool: &PgPool,
+    helix: &HelixClient,
+    broadcaster_id: Option<&str>,
+    giveaway: &GiveawayRecord,
+    drawn_by: &str,
+) -> Result<GiveawayDraw, CommandError> {
+    let entries = giveaways::eligible_entries(pool, giveaway.id).await?;
+
+    let present: Option<HashSet<String>> = match broadcaster_id {
+        Some(broadcaster_id) => match helix.get_chatters(broadcaster_id).await {
+            Ok(chatters) => Some(chatters.into_iter().map(|login| login.to_lowercase()).collect()),
+            Err(e) => {
+                eprintln!("{} {}", "Error Getting Chatters:".bright_red(), e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let seed: i64 = rand::random();
+    let (winner, skipped) = draw_winner(&entries, seed as u64, |login| {
+        present.as_ref().is_none_or(|present| present.contains(login))
+    });
+
+    Ok(giveaways::record_draw(pool, giveaway.id, seed, winner.as_deref(), &skipped, drawn_by).await?)
+}
+
+pub fn format_draw(draw: &GiveawayDraw) -> String {
+    match &draw.winner {
+        Some(winner) => format!("@{} won the giveaway!", winner),
+        None => "Nobody left to draw".to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveGiveaway {
+    pub id: i64,
+    pub keyword: String,
+    pub accepting_entries: bool,
+    pub subscriber_weight: i32,
+}
+
+impl From<&GiveawayRecord> for ActiveGiveaway {
+    fn from(giveaway: &GiveawayRecord) -> Self {
+        ActiveGiveaway {
+            id: giveaway.id,
+            keyword: giveaway.keyword.clone(),
+            accepting_entries: giveaway.is_open(),
+            subscriber_weight: giveaway.subscriber_weight,
+        }
+    }
+}
+
+// The channel's active giveaway, shared between !giveaway and the bot (which enters
+// everyone who types the keyword)
+#[derive(Clone, Default)]
+pub struct CurrentGiveaway {
+    giveaway: Arc<RwLock<Option<ActiveGiveaway>>>,
+}
+
+impl CurrentGiveaway {
+    pub fn get(&self) -> Option<ActiveGiveaway> {
+        self.giveaway.read().ok().and_then(|giveaway| giveaway.clone())
+    }
+
+    pub fn set(&self, giveaway: Option<ActiveGiveaway>) {
+        if let Ok(mut current) = self.giveaway.write() {
+            *current = giveaway;
+        }
+    }
+}
+
+// !giveaway shows the running giveaway. Mods run it with !giveaway start <keyword>,
+// !giveaway close (stop entries), !giveaway draw (as often as needed) and !giveaway end.
+pub struct GiveawayCommand {
+    current_giveaway: CurrentGiveaway,
+}
+
+impl GiveawayCommand {
+    pub fn new(current_giveaway: CurrentGiveaway) -> Self {
+        GiveawayCommand { current_giveaway }
+    }
+
+    async fn start(&self, context: &CommandContext<'_>, keyword: &str) -> Result<(), CommandError> {
+        let keyword = normalize_keyword(keyword)
+            .ok_or_else(|| CommandError::Usage(format!("Keywords are one word, up to {} characters", MAX_KEYWORD_LENGTH)))?;
+
+        let giveaway = match giveaways::create_giveaway(
+            context.pool,
+            context.channel,
+            &keyword,
+            DEFAULT_SUBSCRIBER_WEIGHT,
+            &context.caller.login,
+        )
+        .await
+        {
+            Ok(giveaway) => giveaway,
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                return Err(CommandError::Usage(
+                    "A giveaway is already running, end it with !giveaway end".to_string(),
+                ))
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        self.current_giveaway.set(Some(ActiveGiveaway::from(&giveaway)));
+        context.reply(format!("Giveaway started! Type {} to enter", keyword));
+        Ok(())
+    }
+
+    async fn change_status(&self, context: &CommandContext<'_>, action: &str) -> Result<(), CommandError> {
+        let giveaway = match giveaways::get_active_giveaway(context.pool, context.channel).await? {
+            Some(giveaway) => giveaway,
+            None => {
+                context.reply("No giveaway running");
+                return Ok(());
+            }
+        };
+
+        match action {
+            "draw" => {
+                let draw = draw(context.pool, context.helix, context.broadcaster_id, &giveaway, &context.caller.login).await?;
+                let giveaway = giveaways::get_giveaway(context.pool, context.channel, giveaway.id).await?;
+                self.current_giveaway.set(giveaway.as_ref().map(ActiveGiveaway::from));
+                context.reply(format_draw(&draw));
+            }
+            "close" => {
+                match giveaways::set_status(context.pool, context.channel, giveaway.id, "closed").await? {
+                    Some(giveaway) => {
+                        self.current_giveaway.set(Some(ActiveGiveaway::from(&giveaway)));
+                        context.reply("Giveaway entries are closed");
+                    }
+                    None => context.reply("Entries were already closed"),
+                }
+            }
+            _ => {
+                giveaways::set_status(context.pool, context.channel, giveaway.id, "ended").await?;
+                self.current_giveaway.set(None);
+                context.reply("Giveaway ended");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Command for GiveawayCommand {
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        match context.args.as_slice() {
+            [] => match giveaways::get_active_giveaway(context.pool, context.channel).await? {
+                Some(giveaway) => {
+                    let summary = giveaways::giveaway_summary(context.pool, giveaway).await?;
+                    match summary.giveaway.is_open() {
+                        true => context.reply(format!(
+                            "Giveaway running! Type {} to enter ({} entered)",
+                            summary.giveaway.keyword, summary.entries
+                        )),
+                        false => context.reply(format!("Giveaway entries are closed ({} entered)", summary.entries)),
+                    }
+                }
+                None => context.reply("No giveaway running"),
+            },
+            // Only mods run giveaways, everyone else is ignored
+            _ if !context.caller.is_moderator => {}
+            ["start", keyword] => return self.start(context, keyword).await,
+            [action @ ("close" | "draw" | "end")] => return self.change_status(context, action).await,
+            _ => return Err(CommandError::Usage(USAGE.to_string())),
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> String {
+        "giveaway".to_string()
+    }
+
+    fn get_action(&self) -> String {
+        "!giveaway".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(login: &str, weight: i32) -> GiveawayEntry {
+        GiveawayEntry {
+            login: login.to_string(),
+            weight,
+        }
+    }
+
+    #[test]
+    fn same_seed_draws_same_winner() {
+        let entries = vec![entry("a", 1), entry("b", 2), entry("c", 1)];
+        let shuffled = vec![entry("c", 1), entry("a", 1), entry("b", 2)];
+
+        for seed in 0..20 {
+            assert_eq!(draw_winner(&entries, seed, |_| true), draw_winner(&shuffled, seed, |_| true));
+        }
+    }
+
+    #[test]
+    fn weights_change_the_odds() {
+        let entries = vec![entry("sub", 3), entry("viewer", 1)];
+
+        let sub_wins = (0..1000)
+            .filter(|seed| draw_winner(&entries, *seed, |_| true).0.as_deref() == Some("sub"))
+            .count();
+
+        assert!((650..850).contains(&sub_wins), "sub won {} of 1000", sub_wins);
+    }
+
+    #[test]
+    fn skips_absent_winners() {
+        let entries = vec![entry("gone", 100), entry("here", 1)];
+
+        assert_eq!(
+            draw_winner(&entries, 7, |login| login == "here"),
+            (Some("here".to_string()), vec!["gone".to_string()])
+        );
+        assert_eq!(draw_winner(&entries, 7, |_| false).0, None);
+    }
+}
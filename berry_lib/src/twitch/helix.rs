@@ -0,0 +1,275 @@
+use colored::*;
+use reqwest::{Client, Method, StatusCode};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+
+const HELIX_BASE_URL: &str = "https://api.twitch.tv/helix";
+const MAX_CHATTER_PAGES: usize = 10;
+
+#[derive(Debug)]
+pub enum HelixError {
+    RequestError(reqwest::Error),
+    ApiError(StatusCode, String),
+    NotFound(String),
+}
+
+impl std::fmt::Display for HelixError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            HelixError::RequestError(e) => write!(f, "Helix Request Error: {}", e),
+            HelixError::ApiError(status, body) => write!(f, "Helix API Error ({}): {}", status, body),
+            HelixError::NotFound(what) => write!(f, "Not Found: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for HelixError {}
+
+impl From<reqwest::Error> for HelixError {
+    fn from(err: reqwest::Error) -> Self {
+        HelixError::RequestError(err)
+    }
+}
+
+#[derive(Deserialize)]
+struct HelixData<T> {
+    data: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct HelixPage<T> {
+    data: Vec<T>,
+    #[serde(default)]
+    pagination: HelixPagination,
+}
+
+#[derive(Deserialize, Default)]
+struct HelixPagination {
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HelixChatter {
+    user_login: String,
+}
+
+#[derive(Deserialize)]
+struct HelixUser {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct HelixChannel {
+    game_name: String,
+}
+
+#[derive(Deserialize)]
+struct HelixStream {
+    started_at: DateTime<Utc>,
+}
+
+// Thin client over the Helix endpoints the bot acts through, authenticated as the
+// broadcaster whose token the bot runs with.
+#[derive(Clone)]
+pub struct HelixClient {
+    access_token: String,
+    client_id: String,
+    http: Client,
+}
+
+impl HelixClient {
+    pub fn new(access_token: &str) -> Self {
+        HelixClient {
+            access_token: access_token.to_string(),
+            client_id: std::env::var("TWITCH_CLIENT_ID").expect("TWITCH_CLIENT_ID must be set"),
+            http: Client::new(),
+        }
+    }
+
+    pub fn set_access_token(&mut self, access_token: &str) {
+        self.access_token = access_token.to_string();
+    }
+
+    fn request(&self, method: Method, path: &str) -> reqwest::RequestBuilder {
+        self.http
+            .request(method, format!("{}{}", HELIX_BASE_URL, path))
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Client-Id", &self.client_id)
+    }
+
+    async fn check(response: reqwest::Response) -> Result<reqwest::Response, HelixError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        eprintln!("{} {} {}", "Helix Error:".bright_red().bold(), status, body);
+        Err(HelixError::ApiError(status, body))
+    }
+
+    pub async fn get_user_id(&self, login: &str) -> Result<String, HelixError> {
+        let response = self
+            .request(Method::GET, "/users")
+            .query(&[("login", login)])
+            .send()
+            .await?;
+
+        let users: HelixData<HelixUser> = Self::check(response).await?.json().await?;
+
+        users
+            .data
+            .into_iter()
+            .next()
+            .map(|user| user.id)
+            .ok_or_else(|| HelixError::NotFound(format!("user {}", login)))
+    }
+
+    // Bans the user, or times them out when a duration (in seconds) is given
+    pub async fn ban_user(
+        &self,
+        broadcaster_id: &str,
+        user_id: &str,
+        duration: Option<u64>,
+        reason: &str,
+    ) -> Result<(), HelixError> {
+        let mut data = json!({ "user_id": user_id, "reason": reason });
+        if let Some(duration) = duration {
+            data["duration"] = json!(duration);
+        }
+
+        let response = self
+            .request(Method::POST, "/moderation/bans")
+            .query(&[("broadcaster_id", broadcaster_id), ("moderator_id", broadcaster_id)])
+            .json(&json!({ "data": data }))
+            .send()
+            .await?;
+
+        Self::check(response).await.map(|_| ())
+    }
+
+    pub async fn unban_user(&self, broadcaster_id: &str, user_id: &str) -> Result<(), HelixError> {
+        let response = self
+            .request(Method::DELETE, "/moderation/bans")
+            .query(&[
+                ("broadcaster_id", broadcaster_id),
+                ("moderator_id", broadcaster_id),
+                ("user_id", user_id),
+            ])
+            .send()
+            .await?;
+
+        Self::check(response).await.map(|_| ())
+    }
+
+    pub async fn delete_chat_message(
+        &self,
+        broadcaster_id: &str,
+        message_id: &str,
+    ) -> Result<(), HelixError> {
+        let response = self
+            .request(Method::DELETE, "/moderation/chat")
+            .query(&[
+                ("broadcaster_id", broadcaster_id),
+                ("moderator_id", broadcaster_id),
+                ("message_id", message_id),
+            ])
+            .send()
+            .await?;
+
+        Self::check(response).await.map(|_| ())
+    }
+
+    // When the broadcaster went live, or None while they're offline
+    pub async fn get_stream_started_at(
+        &self,
+        broadcaster_id: &str,
+    ) -> Result<Option<DateTime<Utc>>, HelixError> {
+        let response = self
+            .request(Method::GET, "/streams")
+            .query(&[("user_id", broadcaster_id)])
+            .send()
+            .await?;
+
+        let streams: HelixData<HelixStream> = Self::check(response).await?.json().await?;
+
+        Ok(streams.data.into_iter().next().map(|stream| stream.started_at))
+    }
+
+    // Whispers are sent from the broadcaster's account, which needs a verified phone number
+    pub async fn send_whisper(
+        &self,
+        from_user_id: &str,
+        to_user_id: &str,
+        message: &str,
+    ) -> Result<(), HelixError> {
+        let response = self
+            .request(Method::POST, "/whispers")
+            .query(&[("from_user_id", from_user_id), ("to_user_id", to_user_id)])
+            .json(&json!({ "message": message }))
+            .send()
+            .await?;
+
+        Self::check(response).await.map(|_| ())
+    }
+
+    pub async fn send_announcement(&self, broadcaster_id: &str, message: &str) -> Result<(), HelixError> {
+        let response = self
+            .request(Method::POST, "/chat/announcements")
+            .query(&[("broadcaster_id", broadcaster_id), ("moderator_id", broadcaster_id)])
+            .json(&json!({ "message": message }))
+            .send()
+            .await?;
+
+        Self::check(response).await.map(|_| ())
+    }
+
+    // The category the channel is set to, None when it has none
+    pub async fn get_channel_game(&self, broadcaster_id: &str) -> Result<Option<String>, HelixError> {
+        let response = self
+            .request(Method::GET, "/channels")
+            .query(&[("broadcaster_id", broadcaster_id)])
+            .send()
+            .await?;
+
+        let channels: HelixData<HelixChannel> = Self::check(response).await?.json().await?;
+
+        Ok(channels
+            .data
+            .into_iter()
+            .next()
+            .map(|channel| channel.game_name)
+            .filter(|game| !game.is_empty()))
+    }
+
+    // Logins of everyone connected to the channel's chat, lurkers included. Very
+    // large chats are cut off after MAX_CHATTER_PAGES pages.
+    pub async fn get_chatters(&self, broadcaster_id: &str) -> Result<Vec<String>, HelixError> {
+        let mut chatters = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..MAX_CHATTER_PAGES {
+            let mut query = vec![
+                ("broadcaster_id", broadcaster_id.to_string()),
+                ("moderator_id", broadcaster_id.to_string()),
+                ("first", "1000".to_string()),
+            ];
+            if let Some(after) = cursor.take() {
+                query.push(("after", after));
+            }
+
+            let response = self.request(Method::GET, "/chat/chatters").query(&query).send().await?;
+            let page: HelixPage<HelixChatter> = Self::check(response).await?.json().await?;
+
+            chatters.extend(page.data.into_iter().map(|chatter| chatter.user_login));
+
+            match page.pagination.cursor {
+                Some(next) if !next.is_empty() => cursor = Some(next),
+                _ => break,
+            }
+        }
+
+        Ok(chatters)
+    }
+}
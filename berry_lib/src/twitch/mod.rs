@@ -1,5 +1,23 @@
 pub mod bot;
+pub mod bot_registry;
+pub mod chat_context;
+pub mod chat_duration;
 pub mod commands;
+pub mod cooldown;
+pub mod counter_commands;
+pub mod enforcement;
+pub mod exemptions;
+pub mod giveaway_commands;
+pub mod helix;
+pub mod mod_commands;
+pub mod permission;
+pub mod points;
+pub mod points_commands;
+pub mod poll_commands;
+pub mod quote_commands;
+pub mod template;
+pub mod timers;
+pub mod token_refresher;
 pub mod twitch_access_token;
 pub mod twitch_api;
 pub mod twitch_endpoint;
@@ -0,0 +1,153 @@
+use super::chat_duration::parse_chat_duration;
+use super::enforcement::ModMode;
+use super::exemptions::normalize_login;
+use std::time::Duration;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const MIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Built-in moderator commands. These are only acted on for callers with a mod badge.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ModCommand {
+    Timeout {
+        user: String,
+        duration: Duration,
+        reason: String,
+    },
+    Ban {
+        user: String,
+        reason: String,
+    },
+    Unban {
+        user: String,
+    },
+    Strikes {
+        user: String,
+    },
+    Purge {
+        user: String,
+    },
+    ModMode(Option<ModMode>),
+}
+
+// Returns None when the message isn't a mod command at all, and Err with a usage
+// line when it is one but the arguments don't parse.
+pub fn parse_mod_command(text: &str) -> Option<Result<ModCommand, String>> {
+    let mut parts = text.split_whitespace();
+    let name = parts.next()?.to_lowercase();
+    let args: Vec<&str> = parts.collect();
+
+    let user = args.first().map(|user| normalize_login(user));
+
+    let parsed = match name.as_str() {
+        "!timeout" => user
+            .ok_or("Usage: !timeout <user> [duration] [reason]")
+            .and_then(|user| {
+                let (duration, reason_start) = match args.get(1).and_then(|d| parse_chat_duration(d)) {
+                    Some(duration) => (duration, 2),
+                    None => (DEFAULT_TIMEOUT, 1),
+                };
+
+                // Helix rejects zero-length timeouts, and "0" is more likely a typo than a request
+                if duration < MIN_TIMEOUT {
+                    return Err("Timeouts must be at least 1s");
+                }
+
+                Ok(ModCommand::Timeout {
+                    user,
+                    duration,
+                    reason: args[reason_start.min(args.len())..].join(" "),
+                })
+            }),
+        "!ban" => user
+            .ok_or("Usage: !ban <user> [reason]")
+            .map(|user| ModCommand::Ban {
+                user,
+                reason: args[1..].join(" "),
+            }),
+        "!unban" => user
+            .ok_or("Usage: !unban <user>")
+            .map(|user| ModCommand::Unban { user }),
+        "!strikes" => user
+            .ok_or("Usage: !strikes <user>")
+            .map(|user| ModCommand::Strikes { user }),
+        "!purge" => user
+            .ok_or("Usage: !purge <user>")
+            .map(|user| ModCommand::Purge { user }),
+        "!modmode" => match args.first() {
+            Some(mode) => mode
+                .parse::<ModMode>()
+                .map(|mode| ModCommand::ModMode(Some(mode)))
+                .map_err(|_| "Usage: !modmode shadow|enforce"),
+            None => Ok(ModCommand::ModMode(None)),
+        },
+        _ => return None,
+    };
+
+    Some(parsed.map_err(String::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<ModCommand, String> {
+        parse_mod_command(text).expect("should be a mod command")
+    }
+
+    #[test]
+    fn ignores_other_messages() {
+        assert_eq!(parse_mod_command("hello chat"), None);
+        assert_eq!(parse_mod_command("!quote 4"), None);
+        assert_eq!(parse_mod_command(""), None);
+    }
+
+    #[test]
+    fn parses_timeouts() {
+        assert_eq!(
+            parse("!timeout @SomeUser 5m spamming links"),
+            Ok(ModCommand::Timeout {
+                user: "someuser".to_string(),
+                duration: Duration::from_secs(300),
+                reason: "spamming links".to_string(),
+            })
+        );
+
+        // Without a duration the default is used and everything after the user is the reason
+        assert_eq!(
+            parse("!TIMEOUT someuser calm down"),
+            Ok(ModCommand::Timeout {
+                user: "someuser".to_string(),
+                duration: DEFAULT_TIMEOUT,
+                reason: "calm down".to_string(),
+            })
+        );
+
+        assert!(parse("!timeout").is_err());
+        assert!(parse("!timeout someuser 0").is_err());
+        assert!(parse("!timeout someuser 0m rude").is_err());
+    }
+
+    #[test]
+    fn parses_other_commands() {
+        assert_eq!(
+            parse("!ban troll being a troll"),
+            Ok(ModCommand::Ban {
+                user: "troll".to_string(),
+                reason: "being a troll".to_string(),
+            })
+        );
+        assert_eq!(parse("!unban @Troll"), Ok(ModCommand::Unban { user: "troll".to_string() }));
+        assert_eq!(parse("!strikes troll"), Ok(ModCommand::Strikes { user: "troll".to_string() }));
+        assert_eq!(parse("!purge troll"), Ok(ModCommand::Purge { user: "troll".to_string() }));
+        assert!(parse("!ban").is_err());
+        assert!(parse("!purge").is_err());
+    }
+
+    #[test]
+    fn parses_mod_mode() {
+        assert_eq!(parse("!modmode"), Ok(ModCommand::ModMode(None)));
+        assert_eq!(parse("!modmode enforce"), Ok(ModCommand::ModMode(Some(ModMode::Enforce))));
+        assert!(parse("!modmode loud").is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use super::twitch_api::TwitchMessage;
+use serde::{Deserialize, Serialize};
+
+// Who may run a command. Levels are ordered, each one includes everyone above it
+// in the list (a moderator may run subscriber commands).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionLevel {
+    #[default]
+    Everyone,
+    Subscriber,
+    Vip,
+    Moderator,
+    Broadcaster,
+}
+
+impl PermissionLevel {
+    // The highest level the message's sender holds, going by their badges
+    pub fn of(message: &TwitchMessage) -> Self {
+        if message.is_broadcaster() {
+            PermissionLevel::Broadcaster
+        } else if message.is_moderator() {
+            PermissionLevel::Moderator
+        } else if message.is_vip() {
+            PermissionLevel::Vip
+        } else if message.is_subscriber() {
+            PermissionLevel::Subscriber
+        } else {
+            PermissionLevel::Everyone
+        }
+    }
+
+    pub fn allows(&self, message: &TwitchMessage) -> bool {
+        PermissionLevel::of(message) >= *self
+    }
+}
+
+// Who sent a message and which roles they hold in the channel
+#[derive(Debug, Clone)]
+pub struct CallerRoles {
+    pub login: String,
+    pub user_id: Option<String>,
+    pub level: PermissionLevel,
+    pub is_broadcaster: bool,
+    pub is_moderator: bool,
+    pub is_vip: bool,
+    pub is_subscriber: bool,
+}
+
+impl CallerRoles {
+    pub fn of(message: &TwitchMessage) -> Self {
+        CallerRoles {
+            login: message.sender.clone(),
+            user_id: message.tag("user-id").map(String::from),
+            level: PermissionLevel::of(message),
+            is_broadcaster: message.is_broadcaster(),
+            is_moderator: message.is_moderator(),
+            is_vip: message.is_vip(),
+            is_subscriber: message.is_subscriber(),
+        }
+    }
+
+    pub fn has(&self, level: PermissionLevel) -> bool {
+        self.level >= level
+    }
+}
+
+impl std::fmt::Display for PermissionLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PermissionLevel::Everyone => write!(f, "everyone"),
+            PermissionLevel::Subscriber => write!(f, "subscriber"),
+            PermissionLevel::Vip => write!(f, "vip"),
+            PermissionLevel::Moderator => write!(f, "moderator"),
+            PermissionLevel::Broadcaster => write!(f, "broadcaster"),
+        }
+    }
+}
+
+impl std::str::FromStr for PermissionLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "everyone" => Ok(PermissionLevel::Everyone),
+            "subscriber" => Ok(PermissionLevel::Subscriber),
+            "vip" => Ok(PermissionLevel::Vip),
+            "moderator" => Ok(PermissionLevel::Moderator),
+            "broadcaster" => Ok(PermissionLevel::Broadcaster),
+            other => Err(format!("Unknown permission level: {}", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn message_with(tags: &[(&str, &str)]) -> TwitchMessage {
+        TwitchMessage {
+            sender: "someone".to_string(),
+            text: "!cmd".to_string(),
+            tags: tags
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn each_badge_allows_its_level_and_below() {
+        let cases = [
+            (message_with(&[]), PermissionLevel::Everyone),
+            (message_with(&[("badges", "subscriber/6")]), PermissionLevel::Subscriber),
+            (message_with(&[("badges", "founder/0")]), PermissionLevel::Subscriber),
+            (message_with(&[("badges", "vip/1")]), PermissionLevel::Vip),
+            (message_with(&[("badges", "moderator/1,subscriber/12")]), PermissionLevel::Moderator),
+            (message_with(&[("mod", "1")]), PermissionLevel::Moderator),
+            (message_with(&[("badges", "broadcaster/1")]), PermissionLevel::Broadcaster),
+        ];
+
+        let levels = [
+            PermissionLevel::Everyone,
+            PermissionLevel::Subscriber,
+            PermissionLevel::Vip,
+            PermissionLevel::Moderator,
+            PermissionLevel::Broadcaster,
+        ];
+
+        for (message, held) in &cases {
+            assert_eq!(PermissionLevel::of(message), *held);
+
+            for level in levels {
+                assert_eq!(level.allows(message), level <= *held, "{} running a {} command", held, level);
+            }
+        }
+    }
+
+    #[test]
+    fn parses_levels_it_displays() {
+        for level in ["everyone", "subscriber", "vip", "moderator", "broadcaster"] {
+            assert_eq!(level.parse::<PermissionLevel>().map(|level| level.to_string()), Ok(level.to_string()));
+        }
+
+        assert_eq!(" VIP ".parse::<PermissionLevel>(), Ok(PermissionLevel::Vip));
+        assert!("mods".parse::<PermissionLevel>().is_err());
+    }
+}
@@ -0,0 +1,280 @@
+use super::twitch_api::TwitchMessage;
+use crate::db::points::PointsChange;
+use colored::*;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PointsSettings {
+    pub enabled: bool,
+    pub currency_name: String,
+    pub earn_interval_minutes: i64,
+    // Paid every interval to everyone in chat while the stream is live
+    pub present_amount: i64,
+    // Paid on top to everyone who sent a message during the interval
+    pub chatting_amount: i64,
+    pub subscriber_multiplier: i64,
+    pub sub_bonus: i64,
+    pub gift_bonus_per_sub: i64,
+    pub raid_bonus: i64,
+    pub raid_bonus_per_viewer: i64,
+    pub raid_bonus_max: i64,
+    pub min_gamble: i64,
+}
+
+impl Default for PointsSettings {
+    fn default() -> Self {
+        PointsSettings {
+            enabled: true,
+            currency_name: "points".to_string(),
+            earn_interval_minutes: 5,
+            present_amount: 5,
+            chatting_amount: 5,
+            subscriber_multiplier: 2,
+            sub_bonus: 500,
+            gift_bonus_per_sub: 250,
+            raid_bonus: 100,
+            raid_bonus_per_viewer: 2,
+            raid_bonus_max: 1000,
+            min_gamble: 10,
+        }
+    }
+}
+
+impl PointsSettings {
+    pub fn for_channel(channel: &str) -> Self {
+        match load_points_settings(channel) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("{} {}", "Error Loading Points Settings:".bright_red(), e);
+                PointsSettings::default()
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PointsConfig {
+    default: PointsSettings,
+    #[serde(default)]
+    channels: HashMap<String, PointsSettings>,
+}
+
+fn load_points_settings(channel: &str) -> Result<PointsSettings, String> {
+    let mut config: PointsConfig = serde_json::from_str(include_str!("../config/points.json"))
+        .map_err(|e| format!("Error parsing config file: {}", e))?;
+
+    Ok(config
+        .channels
+        .remove(&channel.to_lowercase())
+        .unwrap_or(config.default))
+}
+
+// Tracks who chatted during the current earning window and works out what everyone
+// is owed when it closes. Windows are fixed slices of wall-clock time, and each
+// payout is keyed by its window, so a restarted bot can't pay the same window twice.
+pub struct PointsTracker {
+    settings: PointsSettings,
+    window: Option<i64>,
+    chatters: HashSet<String>,
+    subscribers: HashSet<String>,
+}
+
+impl PointsTracker {
+    pub fn new(settings: PointsSettings) -> Self {
+        PointsTracker {
+            settings,
+            window: None,
+            chatters: HashSet::new(),
+            subscribers: HashSet::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &PointsSettings {
+        &self.settings
+    }
+
+    fn window_at(&self, unix_secs: i64) -> i64 {
+        unix_secs / (self.settings.earn_interval_minutes.max(1) * 60)
+    }
+
+    pub fn record_chatter(&mut self, message: &TwitchMessage) {
+        let login = message.sender.to_lowercase();
+
+        if message.is_subscriber() {
+            self.subscribers.insert(login.clone());
+        }
+        self.chatters.insert(login);
+    }
+
+    // Returns the window that just closed, if one has. The first window the bot sees
+    // is only partly covered, so it is never paid.
+    pub fn closed_window(&mut self, unix_secs: i64, live: bool) -> Option<i64> {
+        let current = self.window_at(unix_secs);
+
+        let closed = match self.window {
+            Some(window) if window != current => Some(window),
+            _ => None,
+        };
+        self.window = Some(current);
+
+        match closed {
+            Some(window) if live && self.settings.enabled => Some(window),
+            // Nothing is paid for windows while offline
+            Some(_) => {
+                self.chatters.clear();
+                self.subscribers.clear();
+                None
+            }
+            None => None,
+        }
+    }
+
+    // What everyone present in the closed window earns. `present` is the viewer list,
+    // people who chatted are included even when it's missing them.
+    pub fn earnings(&mut self, window: i64, present: &[String]) -> Vec<PointsChange> {
+        let chatters = std::mem::take(&mut self.chatters);
+        let subscribers = std::mem::take(&mut self.subscribers);
+
+        let mut logins: HashSet<String> = present.iter().map(|login| login.to_lowercase()).collect();
+        logins.extend(chatters.iter().cloned());
+
+        let mut earnings: Vec<PointsChange> = logins
+            .into_iter()
+            .filter_map(|login| {
+                let mut amount = self.settings.present_amount;
+                if chatters.contains(&login) {
+                    amount += self.settings.chatting_amount;
+                }
+                if subscribers.contains(&login) {
+                    amount *= self.settings.subscriber_multiplier.max(1);
+                }
+
+                (amount > 0).then(|| PointsChange {
+                    idempotency_key: Some(format!("earn:{}:{}", window, login)),
+                    ..PointsChange::new(&login, amount, "watching")
+                })
+            })
+            .collect();
+
+        earnings.sort_by(|a, b| a.login.cmp(&b.login));
+        earnings
+    }
+
+    // Bonus for a sub, resub, gifted subs or a raid, keyed by the notice's id
+    pub fn notice_bonus(&self, notice: &TwitchMessage) -> Option<PointsChange> {
+        if !self.settings.enabled {
+            return None;
+        }
+
+        let id = notice.tag("id")?;
+        let login = notice.sender.to_lowercase();
+        let param = |name: &str| notice.tag(name).and_then(|value| value.parse::<i64>().ok());
+
+        let (amount, reason) = match notice.tag("msg-id")? {
+            "sub" | "resub" => (self.settings.sub_bonus, "subscription"),
+            "subgift" => (self.settings.gift_bonus_per_sub, "gifted sub"),
+            "submysterygift" => (
+                self.settings.gift_bonus_per_sub * param("msg-param-mass-gift-count").unwrap_or(1),
+                "gifted subs",
+            ),
+            "raid" => (
+                (self.settings.raid_bonus
+                    + self.settings.raid_bonus_per_viewer * param("msg-param-viewerCount").unwrap_or(0))
+                .min(self.settings.raid_bonus_max),
+                "raid",
+            ),
+            _ => return None,
+        };
+
+        (amount > 0 && !login.is_empty()).then(|| PointsChange {
+            idempotency_key: Some(format!("notice:{}", id)),
+            ..PointsChange::new(&login, amount, reason)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(sender: &str, tags: &[(&str, &str)]) -> TwitchMessage {
+        TwitchMessage {
+            sender: sender.to_string(),
+            text: String::new(),
+            tags: tags
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn pays_closed_windows_only_while_live() {
+        let mut tracker = PointsTracker::new(PointsSettings::default());
+        let window = 5 * 60;
+
+        assert_eq!(tracker.closed_window(10 * window + 1, true), None);
+        assert_eq!(tracker.closed_window(10 * window + 200, true), None);
+        assert_eq!(tracker.closed_window(11 * window, true), Some(10));
+        assert_eq!(tracker.closed_window(12 * window, false), None);
+    }
+
+    #[test]
+    fn earnings_are_keyed_by_window() {
+        let mut tracker = PointsTracker::new(PointsSettings::default());
+        tracker.record_chatter(&message("Talker", &[]));
+        tracker.record_chatter(&message("sub", &[("badges", "subscriber/3")]));
+
+        let earnings = tracker.earnings(42, &["lurker".to_string(), "talker".to_string()]);
+        let summary: Vec<(&str, i64, &str)> = earnings
+            .iter()
+            .map(|change| (change.login.as_str(), change.amount, change.idempotency_key.as_deref().unwrap()))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("lurker", 5, "earn:42:lurker"),
+                ("sub", 20, "earn:42:sub"),
+                ("talker", 10, "earn:42:talker"),
+            ]
+        );
+        assert!(tracker.earnings(43, &[]).is_empty());
+    }
+
+    #[test]
+    fn subscribers_are_only_multiplied_in_windows_they_chatted() {
+        let mut tracker = PointsTracker::new(PointsSettings::default());
+        tracker.record_chatter(&message("sub", &[("badges", "subscriber/3")]));
+        tracker.earnings(42, &[]);
+
+        // Lurking in the next window earns the plain amount
+        let earnings = tracker.earnings(43, &["sub".to_string()]);
+        assert_eq!(earnings[0].amount, 5);
+
+        tracker.record_chatter(&message("sub", &[("badges", "subscriber/3")]));
+        assert_eq!(tracker.closed_window(10 * 5 * 60, false), None);
+        assert_eq!(tracker.closed_window(11 * 5 * 60, false), None);
+        assert_eq!(tracker.earnings(44, &["sub".to_string()])[0].amount, 5);
+    }
+
+    #[test]
+    fn bundled_settings_parse() {
+        assert!(load_points_settings("any_channel").is_ok());
+    }
+
+    #[test]
+    fn raid_bonus_is_capped() {
+        let tracker = PointsTracker::new(PointsSettings::default());
+        let raid = message(
+            "raider",
+            &[("id", "abc"), ("msg-id", "raid"), ("msg-param-viewerCount", "5000")],
+        );
+
+        let bonus = tracker.notice_bonus(&raid).unwrap();
+        assert_eq!(bonus.amount, 1000);
+        assert_eq!(bonus.idempotency_key.as_deref(), Some("notice:abc"));
+    }
+}
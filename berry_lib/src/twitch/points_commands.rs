@@ -0,0 +1,213 @@
+use super::commands::{Command, CommandContext, CommandError};
+use super::points::PointsSettings;
+use crate::db::points::{self, PointsError};
+use async_trait::async_trait;
+use rand::Rng;
+use std::time::Duration;
+
+const LEADERBOARD_SIZE: i64 = 5;
+const GAMBLE_COOLDOWN: Duration = Duration::from_secs(30);
+
+fn login_arg(arg: &str) -> String {
+    arg.trim_start_matches('@').to_lowercase()
+}
+
+fn points_error(error: PointsError, settings: &PointsSettings) -> CommandError {
+    match error {
+        PointsError::InsufficientPoints => {
+            CommandError::Usage(format!("You don't have enough {}", settings.currency_name))
+        }
+        PointsError::Database(e) => CommandError::Database(e),
+    }
+}
+
+// "all", "25%" or a plain number, out of the caller's balance
+fn parse_wager(arg: &str, balance: i64) -> Option<i64> {
+    if arg.eq_ignore_ascii_case("all") {
+        return Some(balance);
+    }
+
+    match arg.strip_suffix('%') {
+        Some(percent) => percent
+            .parse::<i64>()
+            .ok()
+            .filter(|percent| (1..=100).contains(percent))
+            .map(|percent| balance * percent / 100),
+        None => arg.parse::<i64>().ok(),
+    }
+}
+
+// !points shows your balance, !points <user> someone else's. Mods change balances
+// with !points add <user> <n> and !points remove <user> <n>.
+pub struct PointsCommand;
+
+#[async_trait]
+impl Command for PointsCommand {
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        let settings = PointsSettings::for_channel(context.channel);
+        let usage = || CommandError::Usage("Usage: !points [user] | !points add|remove <user> <amount>".to_string());
+
+        match context.args.as_slice() {
+            [] | [_] => {
+                let login = context.args.first().map(|arg| login_arg(arg)).unwrap_or_else(|| context.caller.login.clone());
+                let balance = points::get_balance(context.pool, context.channel, &login).await?;
+                context.reply(format!("{} has {} {}", login, balance, settings.currency_name));
+            }
+            // Only mods change balances, everyone else is ignored
+            [_, _, _] if !context.caller.is_moderator => {}
+            [action @ ("add" | "remove"), user, amount] => {
+                let amount = amount.parse::<i64>().ok().filter(|amount| *amount > 0).ok_or_else(usage)?;
+                let (amount, reason) = match *action {
+                    "add" => (amount, format!("added by {}", context.caller.login)),
+                    _ => (-amount, format!("removed by {}", context.caller.login)),
+                };
+
+                let login = login_arg(user);
+                let balance = points::adjust(context.pool, context.channel, &login, amount, &reason)
+                    .await
+                    .map_err(|e| match e {
+                        PointsError::InsufficientPoints => {
+                            CommandError::Usage(format!("{} doesn't have that many {}", login, settings.currency_name))
+                        }
+                        e => points_error(e, &settings),
+                    })?;
+                context.reply(format!("{} now has {} {}", login, balance, settings.currency_name));
+            }
+            _ => return Err(usage()),
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> String {
+        "points".to_string()
+    }
+
+    fn get_action(&self) -> String {
+        "!points".to_string()
+    }
+}
+
+// !give <user> <amount> moves points from the caller to someone else
+pub struct GiveCommand;
+
+#[async_trait]
+impl Command for GiveCommand {
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        let settings = PointsSettings::for_channel(context.channel);
+        let usage = || CommandError::Usage("Usage: !give <user> <amount>".to_string());
+
+        let (to, amount) = match context.args.as_slice() {
+            [user, amount] => (login_arg(user), amount.parse::<i64>().ok().filter(|amount| *amount > 0).ok_or_else(usage)?),
+            _ => return Err(usage()),
+        };
+
+        if to == context.caller.login {
+            return Err(CommandError::Usage(format!("You can't give {} to yourself", settings.currency_name)));
+        }
+
+        let (balance, _) = points::transfer(context.pool, context.channel, &context.caller.login, &to, amount)
+            .await
+            .map_err(|e| points_error(e, &settings))?;
+
+        context.reply(format!(
+            "{} gave {} {} to {}, and has {} left",
+            context.caller.login, amount, settings.currency_name, to, balance
+        ));
+        Ok(())
+    }
+
+    fn get_name(&self) -> String {
+        "give".to_string()
+    }
+
+    fn get_action(&self) -> String {
+        "!give".to_string()
+    }
+}
+
+// !gamble <amount | all | N%>: double it or lose it, at even odds
+pub struct GambleCommand;
+
+#[async_trait]
+impl Command for GambleCommand {
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        let settings = PointsSettings::for_channel(context.channel);
+        let login = &context.caller.login;
+
+        let [arg] = context.args.as_slice() else {
+            return Err(CommandError::Usage("Usage: !gamble <amount | all | N%>".to_string()));
+        };
+
+        let balance = points::get_balance(context.pool, context.channel, login).await?;
+        let amount = parse_wager(arg, balance)
+            .ok_or_else(|| CommandError::Usage("Usage: !gamble <amount | all | N%>".to_string()))?;
+
+        if amount < settings.min_gamble {
+            return Err(CommandError::Usage(format!(
+                "You have to gamble at least {} {}",
+                settings.min_gamble, settings.currency_name
+            )));
+        }
+
+        let won = rand::thread_rng().gen_bool(0.5);
+        let balance = points::gamble(context.pool, context.channel, login, amount, won)
+            .await
+            .map_err(|e| points_error(e, &settings))?;
+
+        match won {
+            true => context.reply(format!("{} won {} {} and now has {}", login, amount, settings.currency_name, balance)),
+            false => context.reply(format!("{} lost {} {} and now has {}", login, amount, settings.currency_name, balance)),
+        }
+        Ok(())
+    }
+
+    fn get_name(&self) -> String {
+        "gamble".to_string()
+    }
+
+    fn get_action(&self) -> String {
+        "!gamble".to_string()
+    }
+
+    fn get_user_cooldown(&self) -> Duration {
+        GAMBLE_COOLDOWN
+    }
+}
+
+// !leaderboard (or !top) lists the channel's biggest balances
+pub struct LeaderboardCommand;
+
+#[async_trait]
+impl Command for LeaderboardCommand {
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        let settings = PointsSettings::for_channel(context.channel);
+        let top = points::leaderboard(context.pool, context.channel, LEADERBOARD_SIZE).await?;
+
+        if top.is_empty() {
+            context.reply(format!("Nobody has any {} yet", settings.currency_name));
+            return Ok(());
+        }
+
+        let list: Vec<String> = top
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| format!("{}. {} ({})", index + 1, entry.login, entry.balance))
+            .collect();
+
+        context.reply(format!("Top {}: {}", settings.currency_name, list.join(", ")));
+        Ok(())
+    }
+
+    fn get_name(&self) -> String {
+        "leaderboard".to_string()
+    }
+
+    fn get_action(&self) -> String {
+        "!leaderboard".to_string()
+    }
+
+    fn get_aliases(&self) -> Vec<String> {
+        vec!["top".to_string()]
+    }
+}
@@ -0,0 +1,269 @@
+use super::commands::{Command, CommandContext, CommandError};
+use crate::db::polls::{self, PollRecord, PollResults};
+use async_trait::async_trait;
+use std::sync::{Arc, RwLock};
+
+const MAX_QUESTION_LENGTH: usize = 200;
+const MAX_OPTION_LENGTH: usize = 50;
+const MAX_OPTIONS: usize = 10;
+const START_USAGE: &str = "Usage: !poll start \"question\" option 1|option 2|...";
+
+// Trims and checks a poll's question and options, for chat and the API alike
+pub fn validate_poll(question: &str, options: &[String]) -> Result<(String, Vec<String>), String> {
+    let question = question.trim();
+    let options: Vec<String> = options
+        .iter()
+        .map(|option| option.trim().to_string())
+        .filter(|option| !option.is_empty())
+        .collect();
+
+    if question.is_empty() || question.len() > MAX_QUESTION_LENGTH {
+        return Err(format!("The question has to be 1 to {} characters", MAX_QUESTION_LENGTH));
+    }
+    if options.len() < 2 || options.len() > MAX_OPTIONS {
+        return Err(format!("A poll needs 2 to {} options", MAX_OPTIONS));
+    }
+    if options.iter().any(|option| option.len() > MAX_OPTION_LENGTH) {
+        return Err(format!("Options can be up to {} characters", MAX_OPTION_LENGTH));
+    }
+
+    Ok((question.to_string(), options))
+}
+
+// `"question" a|b|c`, the question in double quotes and the options split on "|"
+pub fn parse_poll_start(text: &str) -> Result<(String, Vec<String>), String> {
+    let rest = text.trim().strip_prefix('"').ok_or(START_USAGE)?;
+    let (question, options) = rest.split_once('"').ok_or(START_USAGE)?;
+    let options: Vec<String> = options.split('|').map(String::from).collect();
+
+    validate_poll(question, &options)
+}
+
+// The option a chat message votes for: its number, 1-based, as the whole message
+pub fn parse_vote(text: &str, options: usize) -> Option<i32> {
+    text.trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|number| (1..=options).contains(number))
+        .map(|number| number as i32 - 1)
+}
+
+pub fn format_poll(poll: &PollRecord) -> String {
+    let options: Vec<String> = poll
+        .options
+        .iter()
+        .enumerate()
+        .map(|(index, option)| format!("{}) {}", index + 1, option))
+        .collect();
+
+    format!("Poll: {} {} - vote with !vote <number>", poll.question, options.join(" "))
+}
+
+pub fn format_results(results: &PollResults) -> String {
+    let standings: Vec<String> = results
+        .poll
+        .options
+        .iter()
+        .zip(&results.votes)
+        .map(|(option, votes)| {
+            let percent = match results.total_votes {
+                0 => 0,
+                total => votes * 100 / total,
+            };
+            format!("{}: {} ({}%)", option, votes, percent)
+        })
+        .collect();
+
+    let top = results.votes.iter().copied().max().unwrap_or(0);
+    let leaders: Vec<&str> = results
+        .poll
+        .options
+        .iter()
+        .zip(&results.votes)
+        .filter(|(_, votes)| **votes == top)
+        .map(|(option, _)| option.as_str())
+        .collect();
+
+    let verdict = match (top, leaders.as_slice()) {
+        (0, _) => "No votes".to_string(),
+        (_, [winner]) => format!("Winner: {}", winner),
+        (_, tied) => format!("Tie: {}", tied.join(", ")),
+    };
+
+    let state = match results.poll.is_open() {
+        true => "Poll",
+        false => "Poll closed",
+    };
+
+    format!("{}: {} - {}. {}", state, results.poll.question, standings.join(", "), verdict)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivePoll {
+    pub id: i64,
+    pub options: usize,
+}
+
+impl From<&PollRecord> for ActivePoll {
+    fn from(poll: &PollRecord) -> Self {
+        ActivePoll {
+            id: poll.id,
+            options: poll.options.len(),
+        }
+    }
+}
+
+// The channel's open poll, shared between the poll commands and the bot (which
+// counts plain numbers in chat as votes)
+#[derive(Clone, Default)]
+pub struct CurrentPoll {
+    poll: Arc<RwLock<Option<ActivePoll>>>,
+}
+
+impl CurrentPoll {
+    pub fn get(&self) -> Option<ActivePoll> {
+        self.poll.read().ok().and_then(|poll| poll.clone())
+    }
+
+    pub fn set(&self, poll: Option<ActivePoll>) {
+        if let Ok(mut current) = self.poll.write() {
+            *current = poll;
+        }
+    }
+}
+
+// !poll shows the running poll. Mods start one with !poll start "question" a|b|c
+// and end it with !poll end, which posts the results.
+pub struct PollCommand {
+    current_poll: CurrentPoll,
+}
+
+impl PollCommand {
+    pub fn new(current_poll: CurrentPoll) -> Self {
+        PollCommand { current_poll }
+    }
+
+    async fn start(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        let (question, options) = parse_poll_start(&context.args[1..].join(" ")).map_err(CommandError::Usage)?;
+
+        let poll = match polls::create_poll(context.pool, context.channel, &question, &options, &context.caller.login).await {
+            Ok(poll) => poll,
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                return Err(CommandError::Usage("A poll is already running, end it with !poll end".to_string()))
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        self.current_poll.set(Some(ActivePoll::from(&poll)));
+        context.reply(format_poll(&poll));
+        Ok(())
+    }
+
+    async fn end(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        let poll = match polls::get_open_poll(context.pool, context.channel).await? {
+            Some(poll) => polls::close_poll(context.pool, context.channel, poll.id).await?,
+            None => None,
+        };
+        self.current_poll.set(None);
+
+        match poll {
+            Some(poll) => context.reply(format_results(&polls::poll_results(context.pool, poll).await?)),
+            None => context.reply("No poll running"),
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Command for PollCommand {
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        match context.args.first().copied() {
+            None => match polls::get_open_poll(context.pool, context.channel).await? {
+                Some(poll) => context.reply(format_results(&polls::poll_results(context.pool, poll).await?)),
+                None => context.reply("No poll running"),
+            },
+            // Only mods run polls, everyone else is ignored
+            Some(_) if !context.caller.is_moderator => {}
+            Some("start") => return self.start(context).await,
+            Some("end") | Some("stop") => return self.end(context).await,
+            Some(_) => return Err(CommandError::Usage(format!("{} | !poll end", START_USAGE))),
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> String {
+        "poll".to_string()
+    }
+
+    fn get_action(&self) -> String {
+        "!poll".to_string()
+    }
+}
+
+// !vote <number>. Votes are counted silently, voting again changes the vote.
+pub struct VoteCommand {
+    current_poll: CurrentPoll,
+}
+
+impl VoteCommand {
+    pub fn new(current_poll: CurrentPoll) -> Self {
+        VoteCommand { current_poll }
+    }
+}
+
+#[async_trait]
+impl Command for VoteCommand {
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        let poll = match self.current_poll.get() {
+            Some(poll) => poll,
+            None => {
+                context.reply("No poll running");
+                return Ok(());
+            }
+        };
+
+        let option = match context.args.as_slice() {
+            [number] => parse_vote(number, poll.options),
+            _ => None,
+        }
+        .ok_or_else(|| CommandError::Usage(format!("Usage: !vote <1-{}>", poll.options)))?;
+
+        polls::cast_vote(context.pool, poll.id, &context.caller.login, option).await?;
+        Ok(())
+    }
+
+    fn get_name(&self) -> String {
+        "vote".to_string()
+    }
+
+    fn get_action(&self) -> String {
+        "!vote".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_poll_start() {
+        assert_eq!(
+            parse_poll_start("\"Best berry?\" straw | blue|rasp"),
+            Ok((
+                "Best berry?".to_string(),
+                vec!["straw".to_string(), "blue".to_string(), "rasp".to_string()]
+            ))
+        );
+        assert!(parse_poll_start("Best berry? straw|blue").is_err());
+        assert!(parse_poll_start("\"Best berry?\" straw").is_err());
+    }
+
+    #[test]
+    fn parses_votes_in_range() {
+        assert_eq!(parse_vote(" 2 ", 3), Some(1));
+        assert_eq!(parse_vote("0", 3), None);
+        assert_eq!(parse_vote("4", 3), None);
+        assert_eq!(parse_vote("two", 3), None);
+    }
+}
@@ -0,0 +1,212 @@
+use super::commands::{Command, CommandContext, CommandError};
+use super::permission::PermissionLevel;
+use crate::db::quotes::{self, QuoteRecord};
+use async_trait::async_trait;
+use colored::Colorize;
+
+const MAX_QUOTE_LENGTH: usize = 400;
+const SEARCH_RESULTS: i64 = 1;
+
+fn format_quote(quote: &QuoteRecord) -> String {
+    let date = quote.created_at.format("%Y-%m-%d");
+
+    match &quote.game {
+        Some(game) => format!("#{}: \"{}\" [{}] ({})", quote.number, quote.text, game, date),
+        None => format!("#{}: \"{}\" ({})", quote.number, quote.text, date),
+    }
+}
+
+fn parse_quote_number(arg: &str) -> Option<i32> {
+    arg.trim_start_matches('#').parse().ok()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum QuoteLookup {
+    Random,
+    Number(i32),
+    Search(String),
+}
+
+fn parse_lookup(args: &[&str]) -> Result<QuoteLookup, String> {
+    match args {
+        [] => Ok(QuoteLookup::Random),
+        ["search"] => Err("Usage: !quote search <text>".to_string()),
+        ["search", query @ ..] => Ok(QuoteLookup::Search(query.join(" "))),
+        [number] => parse_quote_number(number)
+            .map(QuoteLookup::Number)
+            .ok_or_else(|| "Usage: !quote [number | search <text>]".to_string()),
+        _ => Err("Usage: !quote [number | search <text>]".to_string()),
+    }
+}
+
+// The quote text from !addquote's arguments, without the quotes people wrap it in
+fn parse_new_quote(args: &[&str]) -> Result<String, String> {
+    let text = args.join(" ");
+    let text = text.trim().trim_matches('"').trim();
+
+    if text.is_empty() || text.len() > MAX_QUOTE_LENGTH {
+        return Err(format!("Usage: !addquote <text>, up to {} characters", MAX_QUOTE_LENGTH));
+    }
+
+    Ok(text.to_string())
+}
+
+// !quote for a random one, !quote 42 for a number, !quote search <text> for the best match
+pub struct QuoteCommand;
+
+#[async_trait]
+impl Command for QuoteCommand {
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        let quote = match parse_lookup(&context.args).map_err(CommandError::Usage)? {
+            QuoteLookup::Random => quotes::random_quote(context.pool, context.channel).await?,
+            QuoteLookup::Number(number) => quotes::get_quote(context.pool, context.channel, number).await?,
+            QuoteLookup::Search(query) => quotes::search_quotes(context.pool, context.channel, &query, SEARCH_RESULTS)
+                .await?
+                .into_iter()
+                .next(),
+        };
+
+        match quote {
+            Some(quote) => context.reply(format_quote(&quote)),
+            None => context.reply("No quote found"),
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> String {
+        "quote".to_string()
+    }
+
+    fn get_action(&self) -> String {
+        "!quote".to_string()
+    }
+}
+
+// !addquote <text>, recording who added it and what was being played
+pub struct AddQuoteCommand;
+
+#[async_trait]
+impl Command for AddQuoteCommand {
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        let text = parse_new_quote(&context.args).map_err(CommandError::Usage)?;
+
+        let game = match context.broadcaster_id {
+            Some(broadcaster_id) => context
+                .helix
+                .get_channel_game(broadcaster_id)
+                .await
+                .unwrap_or_else(|e| {
+                    eprintln!("{} {}", "Error Getting Channel Game:".bright_red(), e);
+                    None
+                }),
+            None => None,
+        };
+
+        let quote = quotes::add_quote(
+            context.pool,
+            context.channel,
+            &text,
+            &context.caller.login,
+            game.as_deref(),
+        )
+        .await?;
+
+        context.reply(format!("Added quote #{}", quote.number));
+        Ok(())
+    }
+
+    fn get_name(&self) -> String {
+        "addquote".to_string()
+    }
+
+    fn get_action(&self) -> String {
+        "!addquote".to_string()
+    }
+
+    fn get_permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+}
+
+// !delquote 42
+pub struct DelQuoteCommand;
+
+#[async_trait]
+impl Command for DelQuoteCommand {
+    async fn execute(&self, context: &CommandContext<'_>) -> Result<(), CommandError> {
+        let number = match context.args.as_slice() {
+            [number] => parse_quote_number(number),
+            _ => None,
+        }
+        .ok_or_else(|| CommandError::Usage("Usage: !delquote <number>".to_string()))?;
+
+        match quotes::delete_quote(context.pool, context.channel, number).await? {
+            true => context.reply(format!("Deleted quote #{}", number)),
+            false => context.reply(format!("No quote #{}", number)),
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> String {
+        "delquote".to_string()
+    }
+
+    fn get_action(&self) -> String {
+        "!delquote".to_string()
+    }
+
+    fn get_permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn parses_quote_lookups() {
+        assert_eq!(parse_lookup(&[]), Ok(QuoteLookup::Random));
+        assert_eq!(parse_lookup(&["42"]), Ok(QuoteLookup::Number(42)));
+        assert_eq!(parse_lookup(&["#7"]), Ok(QuoteLookup::Number(7)));
+        assert_eq!(
+            parse_lookup(&["search", "\"boss", "fight\"", "-lava"]),
+            Ok(QuoteLookup::Search("\"boss fight\" -lava".to_string()))
+        );
+
+        assert!(parse_lookup(&["search"]).is_err());
+        assert!(parse_lookup(&["forty"]).is_err());
+        assert!(parse_lookup(&["99999999999"]).is_err());
+        assert!(parse_lookup(&["1", "2"]).is_err());
+    }
+
+    #[test]
+    fn parses_new_quotes() {
+        assert_eq!(parse_new_quote(&["\"I", "never", "miss\""]), Ok("I never miss".to_string()));
+        assert!(parse_new_quote(&[]).is_err());
+        assert!(parse_new_quote(&["\"\""]).is_err());
+        assert!(parse_new_quote(&[&"a".repeat(MAX_QUOTE_LENGTH + 1)]).is_err());
+        assert_eq!(parse_quote_number("#12"), Some(12));
+        assert_eq!(parse_quote_number("twelve"), None);
+    }
+
+    #[test]
+    fn formats_quotes_with_and_without_game() {
+        let mut quote = QuoteRecord {
+            id: 1,
+            channel: "berry".to_string(),
+            number: 3,
+            text: "I never miss".to_string(),
+            added_by: "a_mod".to_string(),
+            game: Some("Celeste".to_string()),
+            created_at: Utc.with_ymd_and_hms(2026, 10, 19, 20, 0, 0).unwrap(),
+        };
+
+        assert_eq!(format_quote(&quote), "#3: \"I never miss\" [Celeste] (2026-10-19)");
+        quote.game = None;
+        assert_eq!(format_quote(&quote), "#3: \"I never miss\" (2026-10-19)");
+    }
+}
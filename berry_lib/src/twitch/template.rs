@@ -0,0 +1,433 @@
+use crate::db::counters::normalize_counter_name;
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt;
+
+// Response templates for custom commands. Text is copied through as-is and
+// `${...}` blocks are replaced when the command runs:
+//
+//   ${user}          the caller's login
+//   ${touser}        the first argument without "@", or the caller when there is none
+//   ${args}          everything after the command name
+//   ${1}, ${2}, ...  a single argument (empty when missing)
+//   ${channel}       the channel the bot is running in
+//   ${count}         how many times the command has been used
+//   ${uptime}        how long the stream has been live
+//   ${random 1 100}  a random whole number between the two bounds, inclusive
+//   ${pick a|b|c}    one of the options, chosen at random
+//   ${counter deaths}     the value of a counter
+//   ${counter deaths +}   bumps the counter first ("+", "-", "+5", "-2")
+
+const MAX_ARG_INDEX: usize = 9;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateVariable {
+    User,
+    ToUser,
+    Args,
+    Arg(usize),
+    Channel,
+    Count,
+    Uptime,
+    Random(i64, i64),
+    Pick(Vec<String>),
+    // Counter name and how much to add to it before reading, 0 to just read it
+    Counter(String, i64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Variable(TemplateVariable),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct TemplateError {
+    // 1-based character position of the offending `${`
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (at character {})", self.message, self.position)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+// Values a template is rendered with. `count`, `uptime` and `counters` are only
+// looked up when the template uses them.
+#[derive(Debug, Default)]
+pub struct TemplateContext<'a> {
+    pub user: &'a str,
+    pub channel: &'a str,
+    pub args: &'a [&'a str],
+    pub count: Option<i64>,
+    pub uptime: Option<String>,
+    pub counters: HashMap<String, i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(input: &str) -> Result<Template, TemplateError> {
+        let chars: Vec<char> = input.chars().collect();
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            if chars[i] != '$' || chars.get(i + 1) != Some(&'{') {
+                text.push(chars[i]);
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            let end = match chars[i + 2..].iter().position(|c| *c == '}') {
+                Some(offset) => i + 2 + offset,
+                None => return Err(error(start, "Unclosed \"${\", expected \"}\"")),
+            };
+
+            let body: String = chars[i + 2..end].iter().collect();
+            if body.contains("${") {
+                return Err(error(start, "Variables can't be nested"));
+            }
+
+            if !text.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+            }
+            segments.push(Segment::Variable(parse_variable(&body, start)?));
+            i = end + 1;
+        }
+
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        Ok(Template { segments })
+    }
+
+    fn uses(&self, variable: fn(&TemplateVariable) -> bool) -> bool {
+        self.segments.iter().any(|segment| match segment {
+            Segment::Variable(v) => variable(v),
+            Segment::Text(_) => false,
+        })
+    }
+
+    pub fn uses_count(&self) -> bool {
+        self.uses(|v| *v == TemplateVariable::Count)
+    }
+
+    pub fn uses_uptime(&self) -> bool {
+        self.uses(|v| *v == TemplateVariable::Uptime)
+    }
+
+    // Counters the template reads or bumps, in order, with the amount to add
+    pub fn counter_changes(&self) -> Vec<(&str, i64)> {
+        self.segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Variable(TemplateVariable::Counter(name, delta)) => Some((name.as_str(), *delta)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn render(&self, context: &TemplateContext, rng: &mut impl Rng) -> String {
+        let mut output = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => output.push_str(text),
+                Segment::Variable(variable) => output.push_str(&render_variable(variable, context, rng)),
+            }
+        }
+
+        output
+    }
+}
+
+fn error(position: usize, message: &str) -> TemplateError {
+    TemplateError {
+        position: position + 1,
+        message: message.to_string(),
+    }
+}
+
+fn parse_variable(body: &str, position: usize) -> Result<TemplateVariable, TemplateError> {
+    let body = body.trim();
+    let (name, rest) = match body.split_once(char::is_whitespace) {
+        Some((name, rest)) => (name, rest.trim()),
+        None => (body, ""),
+    };
+
+    let no_arguments = |variable: TemplateVariable| {
+        if rest.is_empty() {
+            Ok(variable)
+        } else {
+            Err(error(position, &format!("${{{}}} doesn't take arguments", name)))
+        }
+    };
+
+    match name.to_lowercase().as_str() {
+        "" => Err(error(position, "Empty variable \"${}\"")),
+        "user" => no_arguments(TemplateVariable::User),
+        "touser" => no_arguments(TemplateVariable::ToUser),
+        "args" => no_arguments(TemplateVariable::Args),
+        "channel" => no_arguments(TemplateVariable::Channel),
+        "count" => no_arguments(TemplateVariable::Count),
+        "uptime" => no_arguments(TemplateVariable::Uptime),
+        "random" => parse_random(rest, position),
+        "pick" => parse_pick(rest, position),
+        "counter" => parse_counter(rest, position),
+        index if index.chars().all(|c| c.is_ascii_digit()) => match index.parse::<usize>() {
+            Ok(index) if (1..=MAX_ARG_INDEX).contains(&index) => {
+                no_arguments(TemplateVariable::Arg(index))
+            }
+            _ => Err(error(
+                position,
+                &format!("Argument numbers go from 1 to {}", MAX_ARG_INDEX),
+            )),
+        },
+        _ => Err(error(position, &format!("Unknown variable \"{}\"", name))),
+    }
+}
+
+fn parse_random(rest: &str, position: usize) -> Result<TemplateVariable, TemplateError> {
+    let usage = || error(position, "Usage: ${random <min> <max>}");
+
+    let bounds: Vec<&str> = rest.split_whitespace().collect();
+    if bounds.len() != 2 {
+        return Err(usage());
+    }
+
+    let min: i64 = bounds[0].parse().map_err(|_| usage())?;
+    let max: i64 = bounds[1].parse().map_err(|_| usage())?;
+
+    if min > max {
+        return Err(error(position, "${random} needs the smaller number first"));
+    }
+
+    Ok(TemplateVariable::Random(min, max))
+}
+
+fn parse_pick(rest: &str, position: usize) -> Result<TemplateVariable, TemplateError> {
+    let options: Vec<String> = rest.split('|').map(|option| option.trim().to_string()).collect();
+
+    if rest.is_empty() || options.iter().any(|option| option.is_empty()) {
+        return Err(error(position, "Usage: ${pick a|b|c}, options can't be empty"));
+    }
+
+    Ok(TemplateVariable::Pick(options))
+}
+
+fn parse_counter(rest: &str, position: usize) -> Result<TemplateVariable, TemplateError> {
+    let usage = || error(position, "Usage: ${counter <name>} or ${counter <name> +}");
+
+    let parts: Vec<&str> = rest.split_whitespace().collect();
+    let name = match parts.first().and_then(|name| normalize_counter_name(name)) {
+        Some(name) => name,
+        None => return Err(usage()),
+    };
+
+    let delta = match parts.get(1..) {
+        Some([]) => 0,
+        Some(["+"]) => 1,
+        Some(["-"]) => -1,
+        Some([delta]) if delta.starts_with(['+', '-']) => delta.parse().map_err(|_| usage())?,
+        _ => return Err(usage()),
+    };
+
+    Ok(TemplateVariable::Counter(name, delta))
+}
+
+fn render_variable(variable: &TemplateVariable, context: &TemplateContext, rng: &mut impl Rng) -> String {
+    match variable {
+        TemplateVariable::User => context.user.to_string(),
+        TemplateVariable::ToUser => context
+            .args
+            .first()
+            .map(|user| user.trim_start_matches('@'))
+            .filter(|user| !user.is_empty())
+            .unwrap_or(context.user)
+            .to_string(),
+        TemplateVariable::Args => context.args.join(" "),
+        TemplateVariable::Arg(index) => context
+            .args
+            .get(index - 1)
+            .map(|arg| arg.to_string())
+            .unwrap_or_default(),
+        TemplateVariable::Channel => context.channel.to_string(),
+        TemplateVariable::Count => context.count.unwrap_or(0).to_string(),
+        TemplateVariable::Uptime => context
+            .uptime
+            .clone()
+            .unwrap_or_else(|| "offline".to_string()),
+        TemplateVariable::Random(min, max) => rng.gen_range(*min..=*max).to_string(),
+        TemplateVariable::Pick(options) => options[rng.gen_range(0..options.len())].clone(),
+        TemplateVariable::Counter(name, _) => context.counters.get(name).copied().unwrap_or(0).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn render(template: &str, context: &TemplateContext) -> String {
+        Template::parse(template)
+            .expect("template should parse")
+            .render(context, &mut StdRng::seed_from_u64(7))
+    }
+
+    fn context<'a>(args: &'a [&'a str]) -> TemplateContext<'a> {
+        TemplateContext {
+            user: "viewer",
+            channel: "berry",
+            args,
+            count: None,
+            uptime: None,
+            counters: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn plain_text_is_unchanged() {
+        assert_eq!(render("Join the discord! $5 tips", &context(&[])), "Join the discord! $5 tips");
+    }
+
+    #[test]
+    fn user() {
+        assert_eq!(render("hi ${user}", &context(&[])), "hi viewer");
+    }
+
+    #[test]
+    fn touser_uses_first_argument() {
+        assert_eq!(render("hug ${touser}", &context(&["@friend", "x"])), "hug friend");
+    }
+
+    #[test]
+    fn touser_falls_back_to_caller() {
+        assert_eq!(render("hug ${touser}", &context(&[])), "hug viewer");
+    }
+
+    #[test]
+    fn args() {
+        assert_eq!(render("said: ${args}", &context(&["a", "b", "c"])), "said: a b c");
+        assert_eq!(render("said: ${args}", &context(&[])), "said: ");
+    }
+
+    #[test]
+    fn numbered_args() {
+        let args = ["first", "second"];
+        assert_eq!(render("${2} then ${1}", &context(&args)), "second then first");
+        assert_eq!(render("[${3}]", &context(&args)), "[]");
+    }
+
+    #[test]
+    fn channel() {
+        assert_eq!(render("welcome to ${channel}", &context(&[])), "welcome to berry");
+    }
+
+    #[test]
+    fn count() {
+        let mut ctx = context(&[]);
+        assert_eq!(render("used ${count} times", &ctx), "used 0 times");
+
+        ctx.count = Some(42);
+        assert_eq!(render("used ${count} times", &ctx), "used 42 times");
+    }
+
+    #[test]
+    fn uptime() {
+        let mut ctx = context(&[]);
+        assert_eq!(render("${uptime}", &ctx), "offline");
+
+        ctx.uptime = Some("1h 5m".to_string());
+        assert_eq!(render("live for ${uptime}", &ctx), "live for 1h 5m");
+    }
+
+    #[test]
+    fn random_stays_in_range() {
+        let template = Template::parse("${random 1 6}").unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for _ in 0..100 {
+            let roll: i64 = template.render(&context(&[]), &mut rng).parse().unwrap();
+            assert!((1..=6).contains(&roll));
+        }
+
+        assert_eq!(render("${random 5 5}", &context(&[])), "5");
+        assert_eq!(render("${random -3 -3}", &context(&[])), "-3");
+    }
+
+    #[test]
+    fn pick_chooses_an_option() {
+        let template = Template::parse("${pick heads | tails}").unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for _ in 0..20 {
+            let picked = template.render(&context(&[]), &mut rng);
+            assert!(picked == "heads" || picked == "tails");
+        }
+    }
+
+    #[test]
+    fn counter() {
+        let mut ctx = context(&[]);
+        assert_eq!(render("deaths: ${counter deaths}", &ctx), "deaths: 0");
+
+        ctx.counters.insert("deaths".to_string(), 12);
+        assert_eq!(render("deaths: ${counter Deaths +}", &ctx), "deaths: 12");
+    }
+
+    #[test]
+    fn counter_changes() {
+        let template = Template::parse("${counter deaths} ${counter wins +} ${counter wins -} ${counter losses +3}").unwrap();
+        assert_eq!(
+            template.counter_changes(),
+            vec![("deaths", 0), ("wins", 1), ("wins", -1), ("losses", 3)]
+        );
+    }
+
+    #[test]
+    fn uses_lookups() {
+        let template = Template::parse("${count} ${user}").unwrap();
+        assert!(template.uses_count());
+        assert!(!template.uses_uptime());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cases = [
+            ("oops ${user", 6, "Unclosed"),
+            ("${}", 1, "Empty variable"),
+            ("${nope}", 1, "Unknown variable \"nope\""),
+            ("${user extra}", 1, "doesn't take arguments"),
+            ("${0}", 1, "Argument numbers"),
+            ("${10}", 1, "Argument numbers"),
+            ("${random 1}", 1, "Usage: ${random"),
+            ("${random a b}", 1, "Usage: ${random"),
+            ("${random 10 1}", 1, "smaller number first"),
+            ("${pick}", 1, "Usage: ${pick"),
+            ("${pick a||b}", 1, "Usage: ${pick"),
+            ("${pick ${user}}", 1, "nested"),
+            ("${counter}", 1, "Usage: ${counter"),
+            ("${counter two words}", 1, "Usage: ${counter"),
+            ("${counter deaths 5}", 1, "Usage: ${counter"),
+            ("${counter deaths +x}", 1, "Usage: ${counter"),
+        ];
+
+        for (template, position, message) in cases {
+            let err = Template::parse(template).unwrap_err();
+            assert_eq!(err.position, position, "{}", template);
+            assert!(err.message.contains(message), "{}: {}", template, err.message);
+        }
+    }
+}
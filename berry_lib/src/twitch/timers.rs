@@ -0,0 +1,159 @@
+use crate::db::timers::TimerRecord;
+use std::time::{Duration, Instant};
+
+struct ScheduledTimer {
+    id: i64,
+    messages: Vec<String>,
+    interval: Duration,
+    min_lines: u32,
+    next_message: usize,
+    last_posted: Instant,
+    lines_since: u32,
+}
+
+// Decides when each of the channel's timers posts. A timer is due once its interval
+// has passed and enough chat lines have gone by since its last post. While the
+// stream is offline timers are paused: their interval starts over once it's live.
+#[derive(Default)]
+pub struct TimerScheduler {
+    timers: Vec<ScheduledTimer>,
+}
+
+impl TimerScheduler {
+    // Replaces the timers, keeping the progress of ones that were already running
+    pub fn set_timers(&mut self, records: Vec<TimerRecord>, now: Instant) {
+        let mut previous = std::mem::take(&mut self.timers);
+
+        self.timers = records
+            .into_iter()
+            .filter(|record| !record.messages.is_empty())
+            .map(|record| {
+                let existing = previous
+                    .iter()
+                    .position(|timer| timer.id == record.id)
+                    .map(|index| previous.swap_remove(index));
+
+                ScheduledTimer {
+                    id: record.id,
+                    interval: Duration::from_secs(record.interval_minutes.max(1) as u64 * 60),
+                    min_lines: record.min_lines.max(0) as u32,
+                    next_message: existing
+                        .as_ref()
+                        .map(|timer| timer.next_message % record.messages.len())
+                        .unwrap_or(0),
+                    last_posted: existing.as_ref().map(|timer| timer.last_posted).unwrap_or(now),
+                    lines_since: existing.as_ref().map(|timer| timer.lines_since).unwrap_or(0),
+                    messages: record.messages,
+                }
+            })
+            .collect();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    pub fn record_line(&mut self) {
+        for timer in &mut self.timers {
+            timer.lines_since = timer.lines_since.saturating_add(1);
+        }
+    }
+
+    // Messages to post now, rotating through each timer's list
+    pub fn due(&mut self, now: Instant, live: bool) -> Vec<String> {
+        let mut due = Vec::new();
+
+        for timer in &mut self.timers {
+            if !live {
+                timer.last_posted = now;
+                timer.lines_since = 0;
+                continue;
+            }
+
+            if now.duration_since(timer.last_posted) < timer.interval || timer.lines_since < timer.min_lines {
+                continue;
+            }
+
+            due.push(timer.messages[timer.next_message].clone());
+            timer.next_message = (timer.next_message + 1) % timer.messages.len();
+            timer.last_posted = now;
+            timer.lines_since = 0;
+        }
+
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn record(id: i64, messages: &[&str], interval_minutes: i32, min_lines: i32) -> TimerRecord {
+        TimerRecord {
+            id,
+            channel: "berry".to_string(),
+            name: format!("timer{}", id),
+            messages: messages.iter().map(|message| message.to_string()).collect(),
+            interval_minutes,
+            min_lines,
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn waits_for_interval_and_lines() {
+        let start = Instant::now();
+        let mut scheduler = TimerScheduler::default();
+        scheduler.set_timers(vec![record(1, &["follow!"], 10, 3)], start);
+
+        assert!(scheduler.due(start + minutes(5), true).is_empty());
+
+        scheduler.record_line();
+        scheduler.record_line();
+        assert!(scheduler.due(start + minutes(10), true).is_empty());
+
+        scheduler.record_line();
+        assert_eq!(scheduler.due(start + minutes(10), true), vec!["follow!"]);
+        assert!(scheduler.due(start + minutes(11), true).is_empty());
+    }
+
+    #[test]
+    fn rotates_messages() {
+        let start = Instant::now();
+        let mut scheduler = TimerScheduler::default();
+        scheduler.set_timers(vec![record(1, &["a", "b"], 1, 0)], start);
+
+        assert_eq!(scheduler.due(start + minutes(1), true), vec!["a"]);
+        assert_eq!(scheduler.due(start + minutes(2), true), vec!["b"]);
+        assert_eq!(scheduler.due(start + minutes(3), true), vec!["a"]);
+    }
+
+    #[test]
+    fn pauses_while_offline() {
+        let start = Instant::now();
+        let mut scheduler = TimerScheduler::default();
+        scheduler.set_timers(vec![record(1, &["a"], 10, 0)], start);
+
+        assert!(scheduler.due(start + minutes(30), false).is_empty());
+        assert!(scheduler.due(start + minutes(35), true).is_empty());
+        assert_eq!(scheduler.due(start + minutes(40), true), vec!["a"]);
+    }
+
+    #[test]
+    fn reload_keeps_progress() {
+        let start = Instant::now();
+        let mut scheduler = TimerScheduler::default();
+        scheduler.set_timers(vec![record(1, &["a", "b"], 1, 0)], start);
+        assert_eq!(scheduler.due(start + minutes(1), true), vec!["a"]);
+
+        scheduler.set_timers(vec![record(1, &["a", "b"], 1, 0), record(2, &["c"], 1, 0)], start + minutes(1));
+        assert_eq!(scheduler.due(start + minutes(2), true), vec!["b", "c"]);
+    }
+}
@@ -0,0 +1,105 @@
+// Keeps stored Twitch tokens usable. Every few minutes it refreshes tokens that
+// are about to expire and validates the rest hourly, as Twitch asks. When
+// Twitch rejects a refresh token the credentials are marked revoked and the
+// user has to sign in again (GET /auth/twitch tells them so).
+use super::bot_registry::{BotControl, BotRegistry};
+use super::twitch_access_token::{self, TwitchTokenError};
+use crate::auth::envelope::Envelope;
+use crate::db::twitch_credentials::{self, TwitchCredentials};
+use chrono::Utc;
+use colored::*;
+use reqwest::{Client, StatusCode};
+use sqlx::PgPool;
+use std::time::Duration;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+// Comfortably more than CHECK_INTERVAL, so tokens never lapse between checks
+const REFRESH_AHEAD: chrono::Duration = chrono::Duration::minutes(15);
+const VALIDATE_EVERY: chrono::Duration = chrono::Duration::hours(1);
+
+pub async fn run(pool: PgPool, client: Client, bot_registry: BotRegistry) {
+    let envelope = match Envelope::shared() {
+        Ok(envelope) => envelope,
+        Err(e) => {
+            eprintln!("{} {}", "Token Refresher Not Started:".bright_red(), e);
+            return;
+        }
+    };
+
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let now = Utc::now();
+        let due = match twitch_credentials::list_due(&pool, &envelope, now + REFRESH_AHEAD, now - VALIDATE_EVERY).await {
+            Ok(due) => due,
+            Err(e) => {
+                eprintln!("Error Listing Twitch Credentials: {}", e);
+                continue;
+            }
+        };
+
+        for credentials in due {
+            check(&pool, &client, &envelope, &bot_registry, &credentials).await;
+        }
+    }
+}
+
+async fn check(
+    pool: &PgPool,
+    client: &Client,
+    envelope: &Envelope,
+    bot_registry: &BotRegistry,
+    credentials: &TwitchCredentials,
+) {
+    let expiring = credentials.expires_at < Utc::now() + REFRESH_AHEAD;
+
+    if !expiring {
+        match twitch_access_token::validate_access_token(&credentials.access_token, client).await {
+            Ok(true) => {
+                if let Err(e) = twitch_credentials::mark_validated(pool, &credentials.unxid).await {
+                    eprintln!("Error Saving Token Validation: {}", e);
+                }
+                return;
+            }
+            // Revoked or expired early, the refresh token may still work
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!("Error Validating Twitch Token for {}: {}", credentials.unxid, e);
+                return;
+            }
+        }
+    }
+
+    match twitch_access_token::refresh_access_token(&credentials.refresh_token, client).await {
+        Ok(token) => match twitch_credentials::save_refreshed(pool, envelope, credentials, &token).await {
+            Ok(true) => {
+                println!("{} {}", "Twitch Token Refreshed:".bright_green(), credentials.unxid);
+                if let Some(channel) = channel_for(pool, &credentials.unxid).await {
+                    bot_registry.send(&channel, BotControl::TokenRefreshed(token.access_token));
+                }
+            }
+            // The user signed in again meanwhile, their new tokens stand
+            Ok(false) => {}
+            Err(e) => eprintln!("Error Saving Refreshed Twitch Token: {}", e),
+        },
+        Err(TwitchTokenError::Rejected(StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED, body)) => {
+            println!("{} {} {}", "Twitch Access Revoked:".bright_red(), credentials.unxid, body);
+            if let Err(e) = twitch_credentials::mark_revoked(pool, credentials).await {
+                eprintln!("Error Marking Twitch Token Revoked: {}", e);
+            }
+        }
+        // Twitch being down or rate limiting us, try again next round
+        Err(e) => eprintln!("Error Refreshing Twitch Token for {}: {}", credentials.unxid, e),
+    }
+}
+
+async fn channel_for(pool: &PgPool, unxid: &str) -> Option<String> {
+    sqlx::query_scalar::<_, String>("SELECT twitch_login FROM user_data WHERE unxid = $1")
+        .bind(unxid)
+        .fetch_optional(pool)
+        .await
+        .inspect_err(|e| eprintln!("Error Getting User: {}", e))
+        .ok()
+        .flatten()
+}
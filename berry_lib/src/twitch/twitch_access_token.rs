@@ -1,12 +1,40 @@
 use dotenv::dotenv;
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use serde::Deserialize;
 use serde_json;
 use std::env;
 
+const TWITCH_AUTHORIZE_URL: &str = "https://id.twitch.tv/oauth2/authorize";
+const TWITCH_TOKEN_URL: &str = "https://id.twitch.tv/oauth2/token";
+const TWITCH_VALIDATE_URL: &str = "https://id.twitch.tv/oauth2/validate";
+const TWITCH_REVOKE_URL: &str = "https://id.twitch.tv/oauth2/revoke";
+
+// What the bot needs: chat, moderation, announcements, whispers and chatters
+pub const DEFAULT_SCOPES: &[&str] = &[
+    "chat:read",
+    "chat:edit",
+    "moderator:manage:banned_users",
+    "moderator:manage:chat_messages",
+    "moderator:manage:announcements",
+    "moderator:read:chatters",
+    "user:manage:whispers",
+];
+
 pub enum TwitchTokenError {
     RequestError(reqwest::Error),
     JsonError(serde_json::Error),
+    // Twitch refused the token or code, e.g. access was revoked
+    Rejected(StatusCode, String),
+}
+
+impl std::fmt::Display for TwitchTokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TwitchTokenError::RequestError(e) => write!(f, "Request Error: {}", e),
+            TwitchTokenError::JsonError(e) => write!(f, "JSON Error: {}", e),
+            TwitchTokenError::Rejected(status, body) => write!(f, "Rejected: {} {}", status, body),
+        }
+    }
 }
 
 impl From<reqwest::Error> for TwitchTokenError {
@@ -27,46 +55,147 @@ pub struct TwitchAccessToken {
     pub refresh_token: String,
     pub expires_in: u64,
     pub token_type: String,
+    #[serde(default)]
+    pub scope: Vec<String>,
+}
+
+// Where to send the user to sign in. `state` comes back with the code, and
+// `code_challenge` is the PKCE challenge for the verifier sent with the exchange.
+pub fn authorize_url(scopes: &[String], state: &str, nonce: &str, code_challenge: &str) -> String {
+    dotenv().ok();
+
+    let client_id = env::var("TWITCH_CLIENT_ID").expect("TWITCH_CLIENT_ID must be set");
+    let redirect_uri = redirect_uri();
+    let scope = scopes.join(" ");
+
+    reqwest::Url::parse_with_params(
+        TWITCH_AUTHORIZE_URL,
+        &[
+            ("response_type", "code"),
+            ("client_id", client_id.as_str()),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("scope", scope.as_str()),
+            ("state", state),
+            ("nonce", nonce),
+            ("code_challenge", code_challenge),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .expect("TWITCH_AUTHORIZE_URL is valid")
+    .to_string()
 }
 
+// Credentials go in the form body, never the URL, where they'd end up in logs
 pub async fn get_twitch_access_token(
-    code: String,
+    code: &str,
+    code_verifier: &str,
     client: &Client,
 ) -> Result<TwitchAccessToken, TwitchTokenError> {
     dotenv().ok();
-    println!("Getting Twitch Access Token"); // !REMOVE
-    let twitch_code_url = construct_twitch_access_url(&code);
 
-    let res = client.post(&twitch_code_url).send().await?;
+    let client_id = env::var("TWITCH_CLIENT_ID").expect("TWITCH_CLIENT_ID must be set");
+    let client_secret = env::var("TWITCH_CLIENT_SECRET").expect("TWITCH_CLIENT_SECRET must be set");
+    let redirect_uri = redirect_uri();
+
+    let res = client
+        .post(TWITCH_TOKEN_URL)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("code_verifier", code_verifier),
+            ("redirect_uri", &redirect_uri),
+            ("client_id", &client_id),
+            ("client_secret", &client_secret),
+        ])
+        .send()
+        .await?;
 
-    let body = res.text().await;
-    let token_data: TwitchAccessToken = match serde_json::from_str(&body.unwrap()) {
-        Ok(data) => data,
-        Err(e) => return Err(TwitchTokenError::JsonError(e)),
-    };
+    let status = res.status();
+    let body = res.text().await?;
 
-    Ok(token_data)
+    if !status.is_success() {
+        return Err(TwitchTokenError::Rejected(status, body));
+    }
+
+    Ok(serde_json::from_str(&body)?)
 }
 
-// This Function constructs the URL to request the Twitch Access Token
-fn construct_twitch_access_url(code: &str) -> String {
-    dotenv().ok(); // Loads environment variables from .env file
+// Trades the refresh token for a new access token. Twitch may also rotate the
+// refresh token, so store both from the response.
+pub async fn refresh_access_token(
+    refresh_token: &str,
+    client: &Client,
+) -> Result<TwitchAccessToken, TwitchTokenError> {
+    dotenv().ok();
 
-    let local_mode = env::var("LOCAL_MODE").unwrap_or_else(|_| "false".to_string());
-    let redirect_uri = env::var("TWITCH_REDIRECT_URI").expect("TWITCH_REDIRECT_URI must be set");
-    let redirect_uri_local =
-        env::var("TWITCH_REDIRECT_URI").expect("TWITCH_REDIRECT_URI_LOCAL must be set");
     let client_id = env::var("TWITCH_CLIENT_ID").expect("TWITCH_CLIENT_ID must be set");
     let client_secret = env::var("TWITCH_CLIENT_SECRET").expect("TWITCH_CLIENT_SECRET must be set");
 
-    let redirect_uri = if local_mode == "true" {
-        &redirect_uri_local
-    } else {
-        &redirect_uri
-    };
+    let res = client
+        .post(TWITCH_TOKEN_URL)
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", &client_id),
+            ("client_secret", &client_secret),
+        ])
+        .send()
+        .await?;
 
-    format!(
-        "https://id.twitch.tv/oauth2/token?client_id={}&client_secret={}&code={}&grant_type=authorization_code&redirect_uri={}",
-        client_id, client_secret, code, redirect_uri
-    )
+    let status = res.status();
+    let body = res.text().await?;
+
+    if !status.is_success() {
+        return Err(TwitchTokenError::Rejected(status, body));
+    }
+
+    Ok(serde_json::from_str(&body)?)
+}
+
+// Twitch asks apps to check their tokens hourly. Ok(false) means the token was
+// revoked or has expired.
+pub async fn validate_access_token(access_token: &str, client: &Client) -> Result<bool, TwitchTokenError> {
+    let res = client
+        .get(TWITCH_VALIDATE_URL)
+        .header("Authorization", format!("OAuth {}", access_token))
+        .send()
+        .await?;
+
+    match res.status() {
+        StatusCode::UNAUTHORIZED => Ok(false),
+        status if status.is_success() => Ok(true),
+        status => Err(TwitchTokenError::Rejected(status, res.text().await.unwrap_or_default())),
+    }
+}
+
+// Revokes the token with Twitch, ending Berry's access until the user signs in
+// again. Twitch answers 400 for tokens that are already invalid, which is fine.
+pub async fn revoke_access_token(access_token: &str, client: &Client) -> Result<(), TwitchTokenError> {
+    dotenv().ok();
+
+    let client_id = env::var("TWITCH_CLIENT_ID").expect("TWITCH_CLIENT_ID must be set");
+
+    let res = client
+        .post(TWITCH_REVOKE_URL)
+        .form(&[("client_id", client_id.as_str()), ("token", access_token)])
+        .send()
+        .await?;
+
+    match res.status() {
+        status if status.is_success() || status == StatusCode::BAD_REQUEST => Ok(()),
+        status => Err(TwitchTokenError::Rejected(status, res.text().await.unwrap_or_default())),
+    }
+}
+
+// Must match between the authorize URL and the code exchange
+fn redirect_uri() -> String {
+    dotenv().ok(); // Loads environment variables from .env file
+
+    let local_mode = env::var("LOCAL_MODE").unwrap_or_else(|_| "false".to_string());
+
+    if local_mode == "true" {
+        env::var("TWITCH_REDIRECT_URI_LOCAL").expect("TWITCH_REDIRECT_URI_LOCAL must be set")
+    } else {
+        env::var("TWITCH_REDIRECT_URI").expect("TWITCH_REDIRECT_URI must be set")
+    }
 }
@@ -1,19 +1,63 @@
 // twitch_api.rs
+use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Write};
 use std::net::TcpStream;
 
+#[derive(Default)]
 pub struct TwitchMessage {
     pub sender: String,
     pub text: String,
+    // IRCv3 tags sent with the message (badges, user-id, display-name, ...)
+    pub tags: HashMap<String, String>,
 }
 
-impl Default for TwitchMessage {
-    fn default() -> Self {
-        TwitchMessage {
-            sender: String::new(),
-            text: String::new(),
-        }
+impl TwitchMessage {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .get(name)
+            .map(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+    }
+
+    // Badges come through as "broadcaster/1,subscriber/12"
+    pub fn badges(&self) -> HashMap<&str, &str> {
+        self.tag("badges")
+            .map(|badges| {
+                badges
+                    .split(',')
+                    .filter_map(|badge| badge.split_once('/'))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn has_badge(&self, badge: &str) -> bool {
+        self.badges().contains_key(badge)
+    }
+
+    pub fn is_broadcaster(&self) -> bool {
+        self.has_badge("broadcaster")
+    }
+
+    pub fn is_moderator(&self) -> bool {
+        self.is_broadcaster() || self.has_badge("moderator") || self.tag("mod") == Some("1")
     }
+
+    pub fn is_vip(&self) -> bool {
+        self.has_badge("vip") || self.tag("vip") == Some("1")
+    }
+
+    pub fn is_subscriber(&self) -> bool {
+        self.has_badge("subscriber") || self.has_badge("founder") || self.tag("subscriber") == Some("1")
+    }
+}
+
+// What the bot reads from chat: regular messages, and USERNOTICEs for subs, raids
+// and the like. A notice's `text` is the optional message the user attached, its
+// kind is in the "msg-id" tag.
+pub enum ChatEvent {
+    Message(TwitchMessage),
+    UserNotice(TwitchMessage),
 }
 
 #[derive(Debug)]
@@ -56,8 +100,9 @@ impl<'a> TwitchChatAPI<'a> {
         stream.set_nonblocking(true)?;
 
         let mut writer = stream.try_clone()?;
+        writer.write_all("CAP REQ :twitch.tv/tags twitch.tv/commands\r\n".as_bytes())?;
         writer.write_all(format!("PASS oauth:{}\r\n", self.access_token).as_bytes())?;
-        writer.write_all(format!("NICK bot_username\r\n").as_bytes())?;
+        writer.write_all("NICK bot_username\r\n".as_bytes())?;
         writer.write_all(format!("JOIN #{}\r\n", self.channel).as_bytes())?;
 
         self.stream = Some(stream);
@@ -66,20 +111,16 @@ impl<'a> TwitchChatAPI<'a> {
         Ok(())
     }
 
-    pub fn read_message(&mut self) -> Result<Option<TwitchMessage>, TwitchError> {
+    pub fn read_message(&mut self) -> Result<Option<ChatEvent>, TwitchError> {
         if let Some(reader) = &mut self.reader {
             let mut line = String::new();
             if reader.read_line(&mut line)? > 0 {
-                if line.starts_with("PING") {
-                    self.send_raw_message("PONG :tmi.twitch.tv\r\n")?;
-                } else if line.contains("PRIVMSG") {
-                    let parts: Vec<&str> = line.split(' ').collect();
-                    if parts.len() >= 4 {
-                        let sender = parts[0][1..].split('!').next().unwrap_or("").to_string();
-                        let text = parts[3..].join(" ")[1..].trim().to_string();
-                        return Ok(Some(TwitchMessage { sender, text }));
-                    }
-                    return Err(TwitchError::MessageParseError);
+                match classify_line(&line) {
+                    Incoming::Ping => self.send_raw_message("PONG :tmi.twitch.tv\r\n")?,
+                    Incoming::Chat(event) => return Ok(Some(event)),
+                    Incoming::Ignored => {}
+                    // One odd line mustn't stop the bot, so it's logged and skipped
+                    Incoming::Unparseable => eprintln!("Skipping unparseable IRC line: {}", line.trim_end()),
                 }
             }
         }
@@ -106,3 +147,262 @@ impl<'a> TwitchChatAPI<'a> {
         Ok(())
     }
 }
+
+// What a line from Twitch is to the bot
+enum Incoming {
+    Ping,
+    Chat(ChatEvent),
+    // CLEARMSG, NOTICE, JOIN and the rest, which the bot doesn't act on
+    Ignored,
+    Unparseable,
+}
+
+// "[@tags ][:prefix ]COMMAND[ params]". Tags and the prefix are optional; PING
+// has neither.
+struct IrcLine<'a> {
+    tags: HashMap<String, String>,
+    prefix: &'a str,
+    command: &'a str,
+    params: &'a str,
+}
+
+fn parse_line(line: &str) -> Option<IrcLine<'_>> {
+    let line = line.trim_end();
+
+    let (tags, rest) = match line.strip_prefix('@') {
+        Some(tagged) => {
+            let (raw_tags, rest) = tagged.split_once(' ')?;
+            (parse_tags(raw_tags), rest)
+        }
+        None => (HashMap::new(), line),
+    };
+
+    let (prefix, rest) = match rest.strip_prefix(':') {
+        Some(prefixed) => prefixed.split_once(' ')?,
+        None => ("", rest),
+    };
+
+    let (command, params) = rest.split_once(' ').unwrap_or((rest, ""));
+    if command.is_empty() {
+        return None;
+    }
+
+    Some(IrcLine {
+        tags,
+        prefix,
+        command,
+        params,
+    })
+}
+
+// Goes by the parsed command, never by what the line contains: a deleted
+// message's CLEARMSG carries its text, which can say anything
+fn classify_line(line: &str) -> Incoming {
+    let Some(line) = parse_line(line) else {
+        return Incoming::Unparseable;
+    };
+
+    match line.command {
+        "PING" => Incoming::Ping,
+        "PRIVMSG" | "USERNOTICE" => chat_event(line).map_or(Incoming::Unparseable, Incoming::Chat),
+        _ => Incoming::Ignored,
+    }
+}
+
+// "@tags :nick!nick@nick.tmi.twitch.tv PRIVMSG #channel :text" and
+// "@tags :tmi.twitch.tv USERNOTICE #channel[ :text]"
+fn chat_event(line: IrcLine) -> Option<ChatEvent> {
+    match line.command {
+        "PRIVMSG" => {
+            let (_channel, text) = line.params.split_once(" :")?;
+            let sender = line.prefix.split('!').next().unwrap_or("").to_string();
+            if sender.is_empty() {
+                return None;
+            }
+
+            Some(ChatEvent::Message(TwitchMessage {
+                sender,
+                text: text.trim().to_string(),
+                tags: line.tags,
+            }))
+        }
+        // Notices come from the server, the user they're about is in the "login" tag
+        "USERNOTICE" => {
+            let text = line.params.split_once(" :").map(|(_, text)| text.trim()).unwrap_or("");
+            let sender = line.tags.get("login").cloned().unwrap_or_default();
+
+            Some(ChatEvent::UserNotice(TwitchMessage {
+                sender,
+                text: text.to_string(),
+                tags: line.tags,
+            }))
+        }
+        _ => None,
+    }
+}
+
+fn parse_tags(raw_tags: &str) -> HashMap<String, String> {
+    raw_tags
+        .split(';')
+        .filter_map(|tag| {
+            let (key, value) = tag.split_once('=').unwrap_or((tag, ""));
+            if key.is_empty() {
+                return None;
+            }
+            Some((key.to_string(), unescape_tag_value(value)))
+        })
+        .collect()
+}
+
+fn unescape_tag_value(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some(':') => unescaped.push(';'),
+            Some('s') => unescaped.push(' '),
+            Some('r') => unescaped.push('\r'),
+            Some('n') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message_with_tags(raw_tags: &str) -> TwitchMessage {
+        TwitchMessage {
+            sender: "someone".to_string(),
+            text: String::new(),
+            tags: parse_tags(raw_tags),
+        }
+    }
+
+    fn message(line: &str) -> TwitchMessage {
+        match classify_line(line) {
+            Incoming::Chat(ChatEvent::Message(message)) => message,
+            _ => panic!("not a chat message: {}", line),
+        }
+    }
+
+    #[test]
+    fn unescapes_tag_values() {
+        assert_eq!(unescape_tag_value(r"Great\sstream\:\sthanks"), "Great stream; thanks");
+        assert_eq!(unescape_tag_value(r"back\\slash"), r"back\slash");
+        assert_eq!(unescape_tag_value(r"two\rlines\n"), "two\rlines\n");
+        // Unknown escapes keep the character, a trailing backslash is dropped
+        assert_eq!(unescape_tag_value(r"\q\"), "q");
+    }
+
+    #[test]
+    fn parses_tags_and_badges() {
+        let message = message_with_tags(r"badges=vip/1,subscriber/24;display-name=Some\sOne;color=;flag");
+
+        assert_eq!(message.tag("display-name"), Some("Some One"));
+        // Empty and valueless tags read as missing
+        assert_eq!(message.tag("color"), None);
+        assert_eq!(message.tag("flag"), None);
+
+        let badges = message.badges();
+        assert_eq!(badges.get("vip"), Some(&"1"));
+        assert_eq!(badges.get("subscriber"), Some(&"24"));
+        assert!(message.is_vip());
+        assert!(message.is_subscriber());
+        assert!(!message.is_moderator());
+        assert!(!message.is_broadcaster());
+    }
+
+    #[test]
+    fn broadcaster_counts_as_moderator() {
+        let broadcaster = message_with_tags("badges=broadcaster/1");
+        assert!(broadcaster.is_broadcaster());
+        assert!(broadcaster.is_moderator());
+
+        let tagged_mod = message_with_tags("badges=;mod=1");
+        assert!(tagged_mod.is_moderator());
+        assert!(tagged_mod.badges().is_empty());
+    }
+
+    #[test]
+    fn parses_tagged_privmsg() {
+        let message = message(
+            "@badges=moderator/1,subscriber/12;display-name=Berry_Fan;user-id=42;mod=1 \
+             :berry_fan!berry_fan@berry_fan.tmi.twitch.tv PRIVMSG #berry :hello :) there\r\n",
+        );
+
+        assert_eq!(message.sender, "berry_fan");
+        assert_eq!(message.text, "hello :) there");
+        assert_eq!(message.tag("user-id"), Some("42"));
+        assert_eq!(message.tag("display-name"), Some("Berry_Fan"));
+        assert_eq!(message.badges().get("subscriber"), Some(&"12"));
+        assert!(message.is_moderator());
+        assert!(message.is_subscriber());
+        assert!(!message.is_broadcaster());
+        assert!(!message.is_vip());
+    }
+
+    #[test]
+    fn parses_usernotice_with_and_without_text() {
+        let line = "@login=raider;msg-id=raid;msg-param-viewerCount=20 :tmi.twitch.tv USERNOTICE #berry";
+        match classify_line(line) {
+            Incoming::Chat(ChatEvent::UserNotice(notice)) => {
+                assert_eq!(notice.sender, "raider");
+                assert_eq!(notice.text, "");
+                assert_eq!(notice.tag("msg-id"), Some("raid"));
+            }
+            _ => panic!("not a user notice"),
+        }
+
+        let line = "@login=fan;msg-id=resub :tmi.twitch.tv USERNOTICE #berry :12 months!";
+        match classify_line(line) {
+            Incoming::Chat(ChatEvent::UserNotice(notice)) => assert_eq!(notice.text, "12 months!"),
+            _ => panic!("not a user notice"),
+        }
+    }
+
+    #[test]
+    fn dispatches_on_the_command_not_the_text() {
+        // A deleted message saying PRIVMSG must not be read as one
+        let clearmsg = "@login=troll;target-msg-id=abc :tmi.twitch.tv CLEARMSG #berry :PRIVMSG USERNOTICE";
+        assert!(matches!(classify_line(clearmsg), Incoming::Ignored));
+        assert!(matches!(
+            classify_line("@room-id=1 :tmi.twitch.tv CLEARCHAT #berry :troll"),
+            Incoming::Ignored
+        ));
+        assert!(matches!(
+            classify_line(":tmi.twitch.tv NOTICE #berry :PRIVMSG"),
+            Incoming::Ignored
+        ));
+
+        assert!(matches!(classify_line("PING :tmi.twitch.tv\r\n"), Incoming::Ping));
+        assert!(matches!(classify_line(""), Incoming::Unparseable));
+        assert!(matches!(classify_line("@only-tags"), Incoming::Unparseable));
+        assert!(matches!(
+            classify_line(":nick!nick@nick PRIVMSG #berry"),
+            Incoming::Unparseable
+        ));
+    }
+
+    #[test]
+    fn unescapes_tags_on_parsed_lines() {
+        let message = message(
+            "@system-msg=a\\sb\\:c\\\\d;empty=;flag :fan!fan@fan PRIVMSG #berry :hi",
+        );
+
+        assert_eq!(message.tags.get("system-msg").map(String::as_str), Some("a b;c\\d"));
+        assert_eq!(message.tags.get("empty").map(String::as_str), Some(""));
+        assert_eq!(message.tag("empty"), None);
+        assert!(message.tags.contains_key("flag"));
+    }
+}
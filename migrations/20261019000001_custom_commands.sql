@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS custom_commands (
+    id BIGSERIAL PRIMARY KEY,
+    channel TEXT NOT NULL,
+    name TEXT NOT NULL,
+    response TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (channel, name)
+);
@@ -0,0 +1 @@
+ALTER TABLE custom_commands ADD COLUMN IF NOT EXISTS use_count BIGINT NOT NULL DEFAULT 0;
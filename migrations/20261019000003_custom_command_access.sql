@@ -0,0 +1,5 @@
+ALTER TABLE custom_commands
+    ADD COLUMN IF NOT EXISTS aliases TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS permission TEXT NOT NULL DEFAULT 'everyone',
+    ADD COLUMN IF NOT EXISTS global_cooldown_secs INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS user_cooldown_secs INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS counters (
+    id BIGSERIAL PRIMARY KEY,
+    channel TEXT NOT NULL,
+    name TEXT NOT NULL,
+    value BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (channel, name)
+);
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS timers (
+    id BIGSERIAL PRIMARY KEY,
+    channel TEXT NOT NULL,
+    name TEXT NOT NULL,
+    messages TEXT[] NOT NULL,
+    interval_minutes INTEGER NOT NULL,
+    min_lines INTEGER NOT NULL DEFAULT 0,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (channel, name)
+);
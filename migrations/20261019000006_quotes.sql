@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS quotes (
+    id BIGSERIAL PRIMARY KEY,
+    channel TEXT NOT NULL,
+    number INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    added_by TEXT NOT NULL,
+    game TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    search TSVECTOR GENERATED ALWAYS AS (to_tsvector('english', text)) STORED,
+    UNIQUE (channel, number)
+);
+
+CREATE INDEX IF NOT EXISTS quotes_search_idx ON quotes USING GIN (search);
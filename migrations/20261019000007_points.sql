@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS points_balances (
+    channel TEXT NOT NULL,
+    login TEXT NOT NULL,
+    balance BIGINT NOT NULL DEFAULT 0 CHECK (balance >= 0),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (channel, login)
+);
+
+-- Every change to a balance, so balances can be audited and rebuilt. Earnings carry
+-- an idempotency key so a restarted bot can't pay the same window twice.
+CREATE TABLE IF NOT EXISTS points_ledger (
+    id BIGSERIAL PRIMARY KEY,
+    channel TEXT NOT NULL,
+    login TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    reason TEXT NOT NULL,
+    idempotency_key TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (channel, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS points_ledger_login_idx ON points_ledger (channel, login, created_at);
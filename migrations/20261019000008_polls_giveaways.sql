@@ -0,0 +1,55 @@
+CREATE TABLE IF NOT EXISTS polls (
+    id BIGSERIAL PRIMARY KEY,
+    channel TEXT NOT NULL,
+    question TEXT NOT NULL,
+    options TEXT[] NOT NULL,
+    status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'closed')),
+    started_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    closed_at TIMESTAMPTZ
+);
+
+-- Only one poll per channel can be taking votes
+CREATE UNIQUE INDEX IF NOT EXISTS polls_open_idx ON polls (channel) WHERE status = 'open';
+
+-- One vote per viewer, voting again changes it
+CREATE TABLE IF NOT EXISTS poll_votes (
+    poll_id BIGINT NOT NULL REFERENCES polls (id) ON DELETE CASCADE,
+    login TEXT NOT NULL,
+    option_index INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (poll_id, login)
+);
+
+CREATE TABLE IF NOT EXISTS giveaways (
+    id BIGSERIAL PRIMARY KEY,
+    channel TEXT NOT NULL,
+    keyword TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'closed', 'ended')),
+    subscriber_weight INTEGER NOT NULL DEFAULT 2 CHECK (subscriber_weight >= 1),
+    started_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    closed_at TIMESTAMPTZ
+);
+
+-- A giveaway stays active (open for entries, or closed and waiting on draws) until it ends
+CREATE UNIQUE INDEX IF NOT EXISTS giveaways_active_idx ON giveaways (channel) WHERE status <> 'ended';
+
+CREATE TABLE IF NOT EXISTS giveaway_entries (
+    giveaway_id BIGINT NOT NULL REFERENCES giveaways (id) ON DELETE CASCADE,
+    login TEXT NOT NULL,
+    weight INTEGER NOT NULL CHECK (weight >= 1),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (giveaway_id, login)
+);
+
+-- Every draw keeps its seed and who was skipped for having left, so it can be replayed
+CREATE TABLE IF NOT EXISTS giveaway_draws (
+    id BIGSERIAL PRIMARY KEY,
+    giveaway_id BIGINT NOT NULL REFERENCES giveaways (id) ON DELETE CASCADE,
+    seed BIGINT NOT NULL,
+    winner TEXT,
+    skipped TEXT[] NOT NULL DEFAULT '{}',
+    drawn_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,12 @@
+-- Each user's Twitch OAuth tokens. They stay on the server and are looked up by
+-- unxid, rather than travelling inside the JWT.
+CREATE TABLE IF NOT EXISTS user_twitch_credentials (
+    unxid TEXT PRIMARY KEY,
+    twitch_id TEXT NOT NULL,
+    access_token TEXT NOT NULL,
+    refresh_token TEXT NOT NULL,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,28 @@
+-- Tokens are now envelope encrypted (see berry_lib::auth::envelope). Plaintext
+-- tokens can't be encrypted from SQL, so any stored so far are dropped and those
+-- users sign in again.
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'user_twitch_credentials' AND column_name = 'access_token'
+    ) THEN
+        DELETE FROM user_twitch_credentials;
+    END IF;
+END $$;
+
+ALTER TABLE user_twitch_credentials
+    DROP COLUMN IF EXISTS access_token,
+    DROP COLUMN IF EXISTS refresh_token,
+    -- Master key that wrapped the data key
+    ADD COLUMN IF NOT EXISTS key_id TEXT NOT NULL,
+    ADD COLUMN IF NOT EXISTS wrapped_key BYTEA NOT NULL,
+    ADD COLUMN IF NOT EXISTS access_token_sealed BYTEA NOT NULL,
+    ADD COLUMN IF NOT EXISTS refresh_token_sealed BYTEA NOT NULL,
+    -- Twitch asks apps to validate tokens hourly
+    ADD COLUMN IF NOT EXISTS validated_at TIMESTAMPTZ,
+    -- Set when Twitch rejects the refresh token, the user has to sign in again
+    ADD COLUMN IF NOT EXISTS revoked_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS user_twitch_credentials_expires_idx
+    ON user_twitch_credentials (expires_at) WHERE revoked_at IS NULL;
@@ -0,0 +1,28 @@
+-- A signed-in device. Access tokens are short-lived and name their session, which
+-- is kept going with refresh tokens until it expires or is revoked.
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    unxid TEXT NOT NULL,
+    twitch_id TEXT NOT NULL,
+    user_agent TEXT,
+    ip_address TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    revoked_reason TEXT
+);
+
+CREATE INDEX IF NOT EXISTS sessions_unxid_idx ON sessions (unxid) WHERE revoked_at IS NULL;
+
+-- Every refresh token a session has been given, by SHA-256 hash. Each is good for
+-- one refresh. Presenting one that was already used means it leaked, and ends
+-- the session.
+CREATE TABLE IF NOT EXISTS session_refresh_tokens (
+    token_hash BYTEA PRIMARY KEY,
+    session_id TEXT NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    used_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS session_refresh_tokens_session_idx ON session_refresh_tokens (session_id);
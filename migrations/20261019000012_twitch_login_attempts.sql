@@ -0,0 +1,12 @@
+-- A Twitch login we started, by the nonce in its signed state. Holds the PKCE
+-- verifier for the code exchange, and is used up by it.
+CREATE TABLE IF NOT EXISTS twitch_login_attempts (
+    nonce TEXT PRIMARY KEY,
+    code_verifier TEXT NOT NULL,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS twitch_login_attempts_expires_at_idx ON twitch_login_attempts (expires_at);
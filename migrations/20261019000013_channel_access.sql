@@ -0,0 +1,19 @@
+-- Site-wide roles. Admins can manage every channel.
+CREATE TABLE IF NOT EXISTS user_roles (
+    unxid TEXT NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('admin')),
+    granted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (unxid, role)
+);
+
+-- Roles a broadcaster gave other Berry users on their channel
+CREATE TABLE IF NOT EXISTS channel_grants (
+    channel TEXT NOT NULL,
+    unxid TEXT NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('editor', 'moderator')),
+    granted_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (channel, unxid)
+);
+
+CREATE INDEX IF NOT EXISTS channel_grants_unxid_idx ON channel_grants (unxid);
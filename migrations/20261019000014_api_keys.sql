@@ -0,0 +1,20 @@
+-- Keys machine clients (overlays, Stream Deck plugins, scripts) call the API
+-- with instead of a JWT. The key itself is only shown when created; we keep its
+-- SHA-256 hash and a short prefix to tell keys apart.
+CREATE TABLE IF NOT EXISTS api_keys (
+    id TEXT PRIMARY KEY,
+    unxid TEXT NOT NULL,
+    twitch_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    prefix TEXT NOT NULL,
+    key_hash BYTEA NOT NULL UNIQUE,
+    -- rbac permissions the key may use, on top of the owner's role
+    scopes TEXT[] NOT NULL,
+    -- Requests per minute
+    rate_limit INTEGER NOT NULL,
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS api_keys_unxid_idx ON api_keys (unxid);
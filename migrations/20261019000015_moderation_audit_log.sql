@@ -0,0 +1,22 @@
+-- Every moderation action, automatic or typed by a mod, and how it went. A user's
+-- strikes are their rows with `strike` set: offences that were applied, or would
+-- have been in shadow mode.
+CREATE TABLE IF NOT EXISTS moderation_audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    channel TEXT NOT NULL,
+    target TEXT NOT NULL,
+    action TEXT NOT NULL,
+    reason TEXT NOT NULL DEFAULT '',
+    -- 'automatic' with the category and score, or 'moderator' with who typed it
+    source TEXT NOT NULL CHECK (source IN ('automatic', 'moderator')),
+    moderator TEXT,
+    category TEXT,
+    score DOUBLE PRECISION,
+    outcome TEXT NOT NULL CHECK (outcome IN ('applied', 'shadowed', 'failed')),
+    error TEXT,
+    strike BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS moderation_audit_log_channel_idx ON moderation_audit_log (channel, created_at DESC);
+CREATE INDEX IF NOT EXISTS moderation_audit_log_target_idx ON moderation_audit_log (channel, target) WHERE strike;
@@ -0,0 +1,207 @@
+//##############################################
+// COMMAND LINE
+// Usage:
+//   berry_backend import <format> <file> --channel <login> [--overwrite] [--dry-run]
+//   berry_backend export --channel <login> [--output <file>]
+//   berry_backend admin <grant|revoke> <login>
+// Without a subcommand the server starts as usual.
+//##############################################
+
+use berry_lib::db::{channel_access, custom_commands};
+use berry_lib::import::{self, export, ImportFormat, ImportReport};
+use colored::*;
+use sqlx::PgPool;
+
+const USAGE: &str = "Usage:
+  berry_backend import <format> <file> --channel <login> [--overwrite] [--dry-run]
+  berry_backend export --channel <login> [--output <file>]
+  berry_backend admin <grant|revoke> <login>
+
+Formats: berry, nightbot, streamelements, fossabot, streamlabs";
+
+#[derive(Debug, PartialEq)]
+enum CliCommand {
+    Import {
+        format: ImportFormat,
+        file: String,
+        channel: String,
+        overwrite: bool,
+        dry_run: bool,
+    },
+    Export {
+        channel: String,
+        output: Option<String>,
+    },
+    Admin {
+        login: String,
+        admin: bool,
+    },
+}
+
+fn parse_args(args: &[String]) -> Result<CliCommand, String> {
+    let mut positional = Vec::new();
+    let mut channel = None;
+    let mut output = None;
+    let mut overwrite = false;
+    let mut dry_run = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--channel" => channel = Some(args.next().ok_or("--channel needs a login")?.to_lowercase()),
+            "--output" => output = Some(args.next().ok_or("--output needs a file")?.clone()),
+            "--overwrite" => overwrite = true,
+            "--dry-run" => dry_run = true,
+            flag if flag.starts_with("--") => return Err(format!("Unknown option \"{}\"", flag)),
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    let channel = || channel.clone().ok_or("--channel is required");
+
+    match positional.as_slice() {
+        ["import", format, file] => Ok(CliCommand::Import {
+            format: format.parse()?,
+            file: file.to_string(),
+            channel: channel()?,
+            overwrite,
+            dry_run,
+        }),
+        ["export"] => Ok(CliCommand::Export {
+            channel: channel()?,
+            output,
+        }),
+        ["admin", action @ ("grant" | "revoke"), login] => Ok(CliCommand::Admin {
+            login: login.trim_start_matches('@').to_lowercase(),
+            admin: *action == "grant",
+        }),
+        _ => Err("Unrecognised command".to_string()),
+    }
+}
+
+fn print_report(report: &ImportReport) {
+    let verb = match report.dry_run {
+        true => "Would create",
+        false => "Created",
+    };
+    println!("{} {} command(s)", verb, report.commands.created.len());
+
+    let verb = match report.dry_run {
+        true => "Would update",
+        false => "Updated",
+    };
+    println!("{} {} command(s)", verb, report.commands.updated.len());
+
+    if !report.commands.existing.is_empty() {
+        println!(
+            "Kept {} existing command(s), use --overwrite to replace them: {}",
+            report.commands.existing.len(),
+            report.commands.existing.join(", ")
+        );
+    }
+
+    for note in &report.warnings {
+        println!("{} {}: {}", "Warning".bright_yellow(), note.name, note.message);
+    }
+
+    for note in &report.unconverted {
+        println!("{} {}: {}", "Not Imported".bright_red(), note.name, note.message);
+    }
+}
+
+// Runs a subcommand when one was given. Returns false when the server should
+// start instead.
+pub async fn run(pool: &PgPool, args: &[String]) -> Result<bool, String> {
+    if args.is_empty() {
+        return Ok(false);
+    }
+
+    match parse_args(args).map_err(|e| format!("{}\n\n{}", e, USAGE))? {
+        CliCommand::Import {
+            format,
+            file,
+            channel,
+            overwrite,
+            dry_run,
+        } => {
+            let data = std::fs::read_to_string(&file).map_err(|e| format!("Could not read {}: {}", file, e))?;
+
+            // Imported from the command line, so the channel owner is the author
+            let report = import::import_commands(pool, &channel, &channel, format, &data, overwrite, dry_run)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            print_report(&report);
+        }
+        CliCommand::Export { channel, output } => {
+            let commands = custom_commands::list_commands(pool, &channel)
+                .await
+                .map_err(|e| e.to_string())?;
+            let export = export::export_commands(&channel, commands);
+            let json = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+
+            match output {
+                Some(file) => {
+                    std::fs::write(&file, json).map_err(|e| format!("Could not write {}: {}", file, e))?;
+                    println!("Exported {} command(s) to {}", export.commands.len(), file);
+                }
+                None => println!("{}", json),
+            }
+        }
+        CliCommand::Admin { login, admin } => {
+            let found = channel_access::set_admin(pool, &login, admin)
+                .await
+                .map_err(|e| e.to_string())?;
+            if !found {
+                return Err(format!("No Berry user named {}, they have to sign in once first", login));
+            }
+
+            match admin {
+                true => println!("{} is now an admin", login),
+                false => println!("{} is no longer an admin", login),
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parses_subcommands() {
+        assert_eq!(
+            parse_args(&args("import nightbot commands.json --channel Berry --dry-run")),
+            Ok(CliCommand::Import {
+                format: ImportFormat::Nightbot,
+                file: "commands.json".to_string(),
+                channel: "berry".to_string(),
+                overwrite: false,
+                dry_run: true,
+            })
+        );
+        assert_eq!(
+            parse_args(&args("export --channel berry")),
+            Ok(CliCommand::Export {
+                channel: "berry".to_string(),
+                output: None
+            })
+        );
+        assert_eq!(
+            parse_args(&args("admin grant @SomeMod")),
+            Ok(CliCommand::Admin {
+                login: "somemod".to_string(),
+                admin: true
+            })
+        );
+        assert!(parse_args(&args("admin promote somemod")).is_err());
+        assert!(parse_args(&args("import nightbot commands.json")).is_err());
+        assert!(parse_args(&args("import moobot commands.json --channel berry")).is_err());
+    }
+}
@@ -0,0 +1,39 @@
+//##############################################
+// CHANNEL TRENDS ROUTE
+// Endpoint: /analytics/{channel}/trends
+// Method: GET
+// Query: minutes (usize, optional, default 60)
+//##############################################
+
+use actix_web::http::StatusCode;
+use actix_web::web;
+use berry_lib::analytics::trends::{TrendRegistry, TrendReport};
+use berry_lib::api::api_response::ApiResponse;
+
+const DEFAULT_WINDOW_MINUTES: usize = 60;
+const MAX_WINDOW_MINUTES: usize = 24 * 60;
+
+#[derive(serde::Deserialize)]
+pub struct TrendsQuery {
+    minutes: Option<usize>,
+}
+
+pub async fn get_channel_trends(
+    trends: web::Data<TrendRegistry>,
+    channel: web::Path<String>,
+    query: web::Query<TrendsQuery>,
+) -> ApiResponse<TrendReport> {
+    let window_minutes = query
+        .minutes
+        .unwrap_or(DEFAULT_WINDOW_MINUTES)
+        .clamp(1, MAX_WINDOW_MINUTES);
+
+    match trends.report(&channel, window_minutes) {
+        Some(report) => ApiResponse::new(Some(report), None, Some(StatusCode::OK)),
+        None => ApiResponse::new(
+            None,
+            Some("No chat activity recorded for this channel".to_string()),
+            Some(StatusCode::NOT_FOUND),
+        ),
+    }
+}
@@ -0,0 +1,172 @@
+//##############################################
+// API KEY ROUTES
+// Endpoint: /auth/api-keys
+// Method: GET, POST
+// Request Body (POST): name (String), scopes ([String]), rate_limit (i32, optional),
+// expires_in_days (i64, optional, never by default)
+// Endpoint: /auth/api-keys/{id}
+// Method: PUT, DELETE
+// Request Body (PUT): name, scopes and rate_limit, each optional
+// Keys let scripts and plugins act as the user without signing in. The key is
+// only returned by POST; we keep a hash of it.
+//##############################################
+
+use crate::middleware::auth_user::AuthUser;
+use actix_web::http::StatusCode;
+use actix_web::web;
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::auth::rbac::Permission;
+use berry_lib::db::api_keys::{self, ApiKey, DEFAULT_RATE_LIMIT, MAX_KEYS_PER_USER, MAX_RATE_LIMIT};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+const MAX_NAME_LEN: usize = 64;
+const MAX_LIFETIME_DAYS: i64 = 365;
+
+#[derive(Deserialize)]
+pub struct CreateApiKey {
+    name: String,
+    scopes: Vec<String>,
+    rate_limit: Option<i32>,
+    expires_in_days: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateApiKey {
+    name: Option<String>,
+    scopes: Option<Vec<String>>,
+    rate_limit: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    api_key: ApiKey,
+    // Shown this once
+    key: String,
+}
+
+pub async fn list_api_keys(user: AuthUser, pool: web::Data<PgPool>) -> ApiResponse<Vec<ApiKey>> {
+    match api_keys::list_api_keys(&pool, &user.unxid).await {
+        Ok(keys) => ApiResponse::new(Some(keys), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error("Error Getting API Keys", e),
+    }
+}
+
+pub async fn create_api_key(
+    user: AuthUser,
+    pool: web::Data<PgPool>,
+    data: web::Json<CreateApiKey>,
+) -> ApiResponse<CreatedApiKey> {
+    let name = match parse_name(&data.name) {
+        Ok(name) => name,
+        Err(e) => return bad_request(e),
+    };
+    let scopes = match parse_scopes(&data.scopes) {
+        Ok(scopes) => scopes,
+        Err(e) => return bad_request(e),
+    };
+    let rate_limit = match check_rate_limit(data.rate_limit.unwrap_or(DEFAULT_RATE_LIMIT)) {
+        Ok(rate_limit) => rate_limit,
+        Err(e) => return bad_request(e),
+    };
+    let expires_at = match data.expires_in_days {
+        Some(days) if !(1..=MAX_LIFETIME_DAYS).contains(&days) => {
+            return bad_request(format!("expires_in_days must be between 1 and {}", MAX_LIFETIME_DAYS))
+        }
+        Some(days) => Some(Utc::now() + Duration::days(days)),
+        None => None,
+    };
+
+    match api_keys::count_api_keys(&pool, &user.unxid).await {
+        Ok(count) if count >= MAX_KEYS_PER_USER => {
+            return bad_request(format!(
+                "You can have at most {} API keys, delete one first",
+                MAX_KEYS_PER_USER
+            ))
+        }
+        Ok(_) => {}
+        Err(e) => return ApiResponse::db_error("Error Creating API Key", e),
+    }
+
+    match api_keys::create_api_key(&pool, &user.unxid, &user.twitch_id, &name, &scopes, rate_limit, expires_at).await {
+        Ok((api_key, key)) => ApiResponse::new(Some(CreatedApiKey { api_key, key }), None, Some(StatusCode::CREATED)),
+        Err(e) => ApiResponse::db_error("Error Creating API Key", e),
+    }
+}
+
+pub async fn update_api_key(
+    user: AuthUser,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    data: web::Json<UpdateApiKey>,
+) -> ApiResponse<ApiKey> {
+    let name = match data.name.as_deref().map(parse_name).transpose() {
+        Ok(name) => name,
+        Err(e) => return bad_request(e),
+    };
+    let scopes = match data.scopes.as_deref().map(parse_scopes).transpose() {
+        Ok(scopes) => scopes,
+        Err(e) => return bad_request(e),
+    };
+    let rate_limit = match data.rate_limit.map(check_rate_limit).transpose() {
+        Ok(rate_limit) => rate_limit,
+        Err(e) => return bad_request(e),
+    };
+
+    match api_keys::update_api_key(&pool, &user.unxid, &path, name.as_deref(), scopes.as_deref(), rate_limit).await {
+        Ok(Some(api_key)) => ApiResponse::new(Some(api_key), None, Some(StatusCode::OK)),
+        Ok(None) => not_found(),
+        Err(e) => ApiResponse::db_error("Error Updating API Key", e),
+    }
+}
+
+pub async fn delete_api_key(user: AuthUser, pool: web::Data<PgPool>, path: web::Path<String>) -> ApiResponse<bool> {
+    match api_keys::delete_api_key(&pool, &user.unxid, &path).await {
+        Ok(true) => ApiResponse::new(Some(true), None, Some(StatusCode::OK)),
+        Ok(false) => not_found(),
+        Err(e) => ApiResponse::db_error("Error Deleting API Key", e),
+    }
+}
+
+fn parse_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    match name.chars().count() {
+        0 => Err("Name can't be empty".to_string()),
+        len if len > MAX_NAME_LEN => Err(format!("Name can be at most {} characters", MAX_NAME_LEN)),
+        _ => Ok(name.to_string()),
+    }
+}
+
+fn parse_scopes(scopes: &[String]) -> Result<Vec<Permission>, String> {
+    let mut parsed: Vec<Permission> = Vec::new();
+    for scope in scopes {
+        let permission = scope.trim().parse::<Permission>()?;
+        if !parsed.contains(&permission) {
+            parsed.push(permission);
+        }
+    }
+
+    if parsed.is_empty() {
+        let known: Vec<&str> = Permission::ALL.iter().map(Permission::as_str).collect();
+        return Err(format!("Give the key at least one scope: {}", known.join(", ")));
+    }
+
+    Ok(parsed)
+}
+
+fn check_rate_limit(rate_limit: i32) -> Result<i32, String> {
+    match rate_limit {
+        1..=MAX_RATE_LIMIT => Ok(rate_limit),
+        _ => Err(format!("rate_limit must be between 1 and {} requests a minute", MAX_RATE_LIMIT)),
+    }
+}
+
+fn bad_request<T: Serialize + std::fmt::Debug>(error: String) -> ApiResponse<T> {
+    ApiResponse::new(None, Some(error), Some(StatusCode::BAD_REQUEST))
+}
+
+fn not_found<T: Serialize + std::fmt::Debug>() -> ApiResponse<T> {
+    ApiResponse::new(None, Some("API key not found".to_string()), Some(StatusCode::NOT_FOUND))
+}
@@ -0,0 +1,48 @@
+use crate::middleware::auth_user::AuthUser;
+use crate::middleware::channel_access::ChannelAccess;
+use crate::models::user::get_user_db::get_twitch_login;
+use actix_web::http::StatusCode;
+use actix_web::{HttpMessage, HttpRequest};
+use berry_lib::api::api_response::db_error;
+use sqlx::PgPool;
+
+// The authenticated user behind a request and the channel they're acting on.
+// On a route declared with `require` that's the channel checked, which may belong to
+// someone else; otherwise it's the caller's own.
+pub struct Caller {
+    pub unxid: String,
+    pub twitch_id: String,
+    // The caller's own Twitch login, for recording who did something
+    pub login: String,
+    pub channel: String,
+    // None when the request came with an API key
+    pub session_id: Option<String>,
+}
+
+pub async fn resolve_caller(req: &HttpRequest, pool: &PgPool) -> Result<Caller, (StatusCode, String)> {
+    if let Some(access) = req.extensions().get::<ChannelAccess>().cloned() {
+        return Ok(Caller {
+            unxid: access.unxid,
+            twitch_id: access.twitch_id,
+            login: access.login,
+            channel: access.channel,
+            session_id: access.session_id,
+        });
+    }
+
+    let user = AuthUser::from_request_with(req, pool).await?;
+
+    let login = match get_twitch_login(pool, &user.unxid).await {
+        Ok(Some(login)) => login.to_lowercase(),
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "User not found".to_string())),
+        Err(e) => return Err(db_error("Error Getting User", e)),
+    };
+
+    Ok(Caller {
+        unxid: user.unxid,
+        twitch_id: user.twitch_id,
+        channel: login.clone(),
+        login,
+        session_id: Some(user.session_id),
+    })
+}
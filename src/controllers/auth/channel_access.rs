@@ -0,0 +1,155 @@
+//##############################################
+// CHANNEL ACCESS ROUTES
+// Endpoint: /access
+// Method: GET
+// Endpoint: /access/{login}
+// Method: PUT, DELETE
+// Request Body: role ("editor" or "moderator")
+// Endpoint: /auth/channels
+// Method: GET
+// Broadcasters let other Berry users manage their channel. The grantee picks
+// the channel with the X-Berry-Channel header.
+//##############################################
+
+use crate::controllers::auth::caller::resolve_caller;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest};
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::auth::rbac::{Permission, Role};
+use berry_lib::db::channel_access::{self, ChannelGrant};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+#[derive(Deserialize)]
+pub struct GrantRequest {
+    role: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManagedChannel {
+    channel: String,
+    role: Role,
+    permissions: &'static [Permission],
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManagedChannels {
+    // Admins can manage every channel, not only those listed
+    admin: bool,
+    channels: Vec<ManagedChannel>,
+}
+
+pub async fn list_grants(req: HttpRequest, pool: web::Data<PgPool>) -> ApiResponse<Vec<ChannelGrant>> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    match channel_access::list_grants(&pool, &caller.channel).await {
+        Ok(grants) => ApiResponse::new(Some(grants), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error("Error Getting Access", e),
+    }
+}
+
+pub async fn grant_access(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    data: web::Json<GrantRequest>,
+) -> ApiResponse<ChannelGrant> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let role = match data.role.parse::<Role>() {
+        Ok(role) if role.is_grantable() => role,
+        Ok(role) => {
+            return ApiResponse::new(
+                None,
+                Some(format!("The {} role can't be granted, use editor or moderator", role.as_str())),
+                Some(StatusCode::BAD_REQUEST),
+            )
+        }
+        Err(e) => return ApiResponse::new(None, Some(e), Some(StatusCode::BAD_REQUEST)),
+    };
+
+    let login = path.trim().trim_start_matches('@').to_lowercase();
+    if login == caller.channel {
+        return ApiResponse::new(
+            None,
+            Some("The broadcaster already has every permission".to_string()),
+            Some(StatusCode::BAD_REQUEST),
+        );
+    }
+
+    match channel_access::grant(&pool, &caller.channel, &login, role, &caller.login).await {
+        Ok(Some(grant)) => ApiResponse::new(Some(grant), None, Some(StatusCode::OK)),
+        Ok(None) => ApiResponse::new(
+            None,
+            Some(format!("{} hasn't signed in to Berry yet", login)),
+            Some(StatusCode::NOT_FOUND),
+        ),
+        Err(e) => ApiResponse::db_error("Error Granting Access", e),
+    }
+}
+
+pub async fn revoke_access(req: HttpRequest, pool: web::Data<PgPool>, path: web::Path<String>) -> ApiResponse<bool> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let login = path.trim().trim_start_matches('@').to_lowercase();
+
+    match channel_access::revoke(&pool, &caller.channel, &login).await {
+        Ok(true) => ApiResponse::new(Some(true), None, Some(StatusCode::OK)),
+        Ok(false) => ApiResponse::new(
+            None,
+            Some(format!("{} has no role on this channel", login)),
+            Some(StatusCode::NOT_FOUND),
+        ),
+        Err(e) => ApiResponse::db_error("Error Revoking Access", e),
+    }
+}
+
+// The channels the caller can manage, their own first
+pub async fn list_managed_channels(req: HttpRequest, pool: web::Data<PgPool>) -> ApiResponse<ManagedChannels> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let admin = match channel_access::is_admin(&pool, &caller.unxid).await {
+        Ok(admin) => admin,
+        Err(e) => return ApiResponse::db_error("Error Getting Channels", e),
+    };
+
+    let grants = match channel_access::list_user_grants(&pool, &caller.unxid).await {
+        Ok(grants) => grants,
+        Err(e) => return ApiResponse::db_error("Error Getting Channels", e),
+    };
+
+    let own = ManagedChannel {
+        channel: caller.login.clone(),
+        role: Role::Broadcaster,
+        permissions: Role::Broadcaster.permissions(),
+    };
+    let granted = grants.into_iter().filter_map(|grant| {
+        let role = grant.role.parse::<Role>().ok()?;
+        Some(ManagedChannel {
+            channel: grant.channel,
+            role,
+            permissions: role.permissions(),
+        })
+    });
+
+    ApiResponse::new(
+        Some(ManagedChannels {
+            admin,
+            channels: std::iter::once(own).chain(granted).collect(),
+        }),
+        None,
+        Some(StatusCode::OK),
+    )
+}
@@ -2,15 +2,26 @@
 // LOGIN ROUTE
 // Endpoint: /auth/login
 // Method: POST
-// Request Body: code (String)
+// Request Body: code (String), state (String)
+// `state` is the one from GET /auth/twitch/authorize that Twitch sent back
+// with the code. Needs the login cookie that route set, and clears it.
 //##############################################
 
+use crate::controllers::auth::sessions::{session_tokens, SessionTokens};
+use crate::controllers::auth::twitch_authorize::{expired_login_cookie, LOGIN_COOKIE};
 use crate::models::user::set_user_db::{set_user_to_db, SetUserReturn};
+use actix_web::http::header::{SET_COOKIE, USER_AGENT};
 use actix_web::http::StatusCode;
-use actix_web::web;
-use berry_lib::api::api_response::ApiResponse;
-use berry_lib::auth::jwt;
+use actix_web::{web, CustomizeResponder, HttpRequest, Responder};
+use berry_lib::analytics::trends::TrendRegistry;
+use berry_lib::api::api_response::{db_error, ApiResponse};
+use berry_lib::auth::envelope::Envelope;
+use berry_lib::auth::jwt::JwtConfig;
+use berry_lib::auth::oauth::{self, LoginState};
+use berry_lib::db::twitch_login_attempts::{self, LoginAttempt};
+use berry_lib::db::{sessions, twitch_credentials};
 use berry_lib::twitch::bot::Bot;
+use berry_lib::twitch::bot_registry::BotRegistry;
 use berry_lib::twitch::twitch_user_data::{TwitchUserData, UserTwitchData};
 use berry_lib::twitch::{
     self,
@@ -24,21 +35,47 @@ use sqlx::PgPool;
 #[derive(serde::Deserialize)]
 pub struct LoginResponse {
     code: String,
+    #[serde(default)]
+    state: String,
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Debug)]
+#[derive(serde::Serialize, Debug)]
 
 pub struct LoginApiRes {
-    token: String,
+    #[serde(flatten)]
+    tokens: SessionTokens,
     data: UserTwitchData,
+    // Requested scopes the user didn't grant. The features needing them won't
+    // work until they sign in again and approve them.
+    missing_scopes: Vec<String>,
 }
 
 // ** MAIN LOGIN FUNCTION START **
 
+// The login cookie is only good for one attempt, so it's cleared however the login went
 pub async fn login_twitch(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    jwt_config: web::Data<JwtConfig>,
+    data: web::Json<LoginResponse>,
+    reqwest_client: web::Data<Client>,
+    trends: web::Data<TrendRegistry>,
+    bot_registry: web::Data<BotRegistry>,
+) -> CustomizeResponder<ApiResponse<LoginApiRes>> {
+    login(req, pool, jwt_config, data, reqwest_client, trends, bot_registry)
+        .await
+        .customize()
+        .append_header((SET_COOKIE, expired_login_cookie().to_string()))
+}
+
+async fn login(
+    req: HttpRequest,
     pool: web::Data<PgPool>,
+    jwt_config: web::Data<JwtConfig>,
     data: web::Json<LoginResponse>,
     reqwest_client: web::Data<Client>,
+    trends: web::Data<TrendRegistry>,
+    bot_registry: web::Data<BotRegistry>,
 ) -> ApiResponse<LoginApiRes> {
     let res_data = data.into_inner();
 
@@ -54,7 +91,22 @@ pub async fn login_twitch(
         );
     }
 
-    let twitch_creds = match retrieve_twitch_creds(code, reqwest_client).await {
+    if res_data.state.is_empty() {
+        return ApiResponse::new(
+            None,
+            Some("State is required".to_string()),
+            Some(StatusCode::BAD_REQUEST),
+        );
+    }
+
+    let cookie_nonce = req.cookie(LOGIN_COOKIE).map(|cookie| cookie.value().to_string());
+
+    let attempt = match take_login_attempt(&pool, &jwt_config, &res_data.state, cookie_nonce.as_deref()).await {
+        Ok(attempt) => attempt,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let twitch_creds = match retrieve_twitch_creds(&code, &attempt.code_verifier, reqwest_client).await {
         Ok(data) => data,
         Err(e) => match e {
             TwitchTokenError::JsonError(err) => {
@@ -73,6 +125,14 @@ pub async fn login_twitch(
                     Some(StatusCode::INTERNAL_SERVER_ERROR),
                 );
             }
+            TwitchTokenError::Rejected(status, body) => {
+                eprintln!("{} {} {}", "Error Getting Twitch Creds...".red(), status, body);
+                return ApiResponse::new(
+                    None,
+                    Some("Twitch rejected the login code".to_string()),
+                    Some(StatusCode::BAD_REQUEST),
+                );
+            }
         },
     };
 
@@ -103,23 +163,68 @@ pub async fn login_twitch(
         }
     };
 
+    // Kept server-side, the JWT only says who the user is
+    let saved = match Envelope::shared() {
+        Ok(envelope) => {
+            twitch_credentials::save_credentials(&pool, &envelope, &user_data.unxid, &user_data.twitch_id, &twitch_creds)
+                .await
+        }
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = saved {
+        eprintln!("{} {}", "Error Saving Twitch Credentials:".bright_red(), e);
+        return ApiResponse::new(
+            None,
+            Some("Error Saving Twitch Credentials".to_string()),
+            Some(StatusCode::INTERNAL_SERVER_ERROR),
+        );
+    }
+
+    let user_agent = req.headers().get(USER_AGENT).and_then(|value| value.to_str().ok());
+    let ip_address = req.connection_info().realip_remote_addr().map(str::to_string);
+
+    let (session, refresh_token) =
+        match sessions::create_session(&pool, &user_data.unxid, &user_data.twitch_id, user_agent, ip_address.as_deref()).await {
+            Ok(created) => created,
+            Err(e) => {
+                eprintln!("{} {}", "Error Creating Session:".bright_red(), e);
+                return ApiResponse::new(
+                    None,
+                    Some("Error Creating Session".to_string()),
+                    Some(StatusCode::INTERNAL_SERVER_ERROR),
+                );
+            }
+        };
+
+    let tokens = match session_tokens(&jwt_config, &session, refresh_token) {
+        Ok(tokens) => tokens,
+        Err(e) => return ApiResponse::new(None, Some(e), Some(StatusCode::INTERNAL_SERVER_ERROR)),
+    };
+
+    // Only once the login has gone through, so a failed one leaves no bot behind
     let channel_to_join = user_data.twitch_login.clone();
 
     // FOR TESTING
 
     // let channel_to_join = String::from("tarik");
 
-    initiate_twitch_bot(twitch_creds.access_token.clone(), channel_to_join);
-
-
-    let jwt_token = match init_and_get_jwt(twitch_creds.access_token, &user_data).await {
-        Ok(token) => token,
-        Err(e) => return ApiResponse::new(None, Some(e), Some(StatusCode::INTERNAL_SERVER_ERROR)),
-    };
+    initiate_twitch_bot(
+        twitch_creds.access_token.clone(),
+        channel_to_join,
+        trends.get_ref().clone(),
+        pool.get_ref().clone(),
+        bot_registry.get_ref().clone(),
+    );
+
+    let missing_scopes = oauth::missing_scopes(&attempt.scopes, &twitch_creds.scope);
+    if !missing_scopes.is_empty() {
+        println!("{} {:?}", "Scopes Not Granted:".yellow(), missing_scopes);
+    }
 
     let full_response = LoginApiRes {
-        token: jwt_token,
+        tokens,
         data: user_data,
+        missing_scopes,
     };
 
     ApiResponse::new(Some(full_response), None, Some(StatusCode::OK))
@@ -132,9 +237,20 @@ pub async fn login_twitch(
 // ** ******************************* **
 
 
-fn initiate_twitch_bot(token: String, channel: String) {
+fn initiate_twitch_bot(
+    token: String,
+    channel: String,
+    trends: TrendRegistry,
+    pool: PgPool,
+    bot_registry: BotRegistry,
+) {
+    if bot_registry.is_running(&channel) {
+        println!("{} {}", "Bot Already Running:".yellow(), channel);
+        return;
+    }
+
     tokio::spawn(async move {
-        match Bot::new(&token, &channel) {
+        match Bot::new(&token, &channel, trends, pool, bot_registry) {
             Ok(mut bot) => {
                 println!("Bot Created!"); // !REMOVE
                 if let Err(e) = bot.run().await {
@@ -148,13 +264,44 @@ fn initiate_twitch_bot(token: String, channel: String) {
     });
 }
 
+// The login attempt the state was issued for. Each can be used once, and only by
+// the browser holding the attempt's login cookie.
+async fn take_login_attempt(
+    pool: &PgPool,
+    jwt_config: &JwtConfig,
+    state: &str,
+    cookie_nonce: Option<&str>,
+) -> Result<LoginAttempt, (StatusCode, String)> {
+    let invalid = || (StatusCode::BAD_REQUEST, "Invalid or expired login state".to_string());
+
+    let nonce = LoginState::verify(jwt_config, state).map_err(|e| {
+        println!("{} {}", "Login State Rejected:".red(), e);
+        invalid()
+    })?;
+
+    if cookie_nonce != Some(nonce.as_str()) {
+        println!("{}", "Login State From Another Browser Rejected".red());
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "This login was started in another browser".to_string(),
+        ));
+    }
+
+    match twitch_login_attempts::take_attempt(pool, &nonce).await {
+        Ok(Some(attempt)) => Ok(attempt),
+        Ok(None) => Err(invalid()),
+        Err(e) => Err(db_error("Error Checking Login State", e)),
+    }
+}
+
 // Retrieve Twitch Creds
 
 async fn retrieve_twitch_creds(
-    code: String,
+    code: &str,
+    code_verifier: &str,
     reqwest_client: &Client,
 ) -> Result<TwitchAccessToken, TwitchTokenError> {
-    let twitch_creds = twitch_access_token::get_twitch_access_token(code, reqwest_client).await;
+    let twitch_creds = twitch_access_token::get_twitch_access_token(code, code_verifier, reqwest_client).await;
 
     match twitch_creds {
         Ok(data) => Ok(data),
@@ -180,7 +327,7 @@ async fn get_and_set_user_to_db(
     data: TwitchUserData,
     pool: &PgPool,
 ) -> Result<UserTwitchData, sqlx::Error> {
-    let user_data = set_user_to_db(&data, &pool).await;
+    let user_data = set_user_to_db(&data, pool).await;
 
     match user_data {
         Ok(data) => match data {
@@ -190,23 +337,3 @@ async fn get_and_set_user_to_db(
         Err(e) => Err(e),
     }
 }
-
-async fn init_and_get_jwt(token: String, user_data: &UserTwitchData) -> Result<String, String> {
-    let jwt_algo = jwt::JwtAlgorithm::RS512;
-    let jwt_config = jwt::JwtConfig::new(jwt_algo.into());
-    let jwt_exp = jwt::JwtConfig::generate_exp_time(2);
-
-    let claims = jwt::Claims {
-        unxid: user_data.unxid.to_string(),
-        exp: jwt_exp,
-        twitch_access_token: token,
-        twitch_id: user_data.twitch_id.to_string(),
-    };
-
-    let jwt_token = match jwt_config.generate_token(&claims) {
-        Ok(token) => token,
-        Err(_) => return Err("Failed to generate JWT Token".to_string()),
-    };
-
-    Ok(jwt_token)
-}
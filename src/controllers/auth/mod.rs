@@ -1 +1,8 @@
+pub mod api_keys;
+pub mod caller;
+pub mod channel_access;
 pub mod login;
+pub mod sessions;
+pub mod well_known;
+pub mod twitch_authorize;
+pub mod twitch_status;
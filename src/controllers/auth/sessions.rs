@@ -0,0 +1,172 @@
+//##############################################
+// SESSION ROUTES
+// Endpoint: /auth/refresh
+// Method: POST
+// Request Body: refresh_token (String)
+// Endpoint: /auth/logout
+// Method: POST
+// Endpoint: /auth/sessions
+// Method: GET, DELETE (every session but the current one)
+// Endpoint: /auth/sessions/{id}
+// Method: DELETE
+// Access tokens are short lived. Refresh tokens last as long as the session
+// and change on every refresh.
+//##############################################
+
+use crate::middleware::auth_user::AuthUser;
+use actix_web::http::StatusCode;
+use actix_web::web;
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::auth::envelope::Envelope;
+use berry_lib::auth::jwt::{self, JwtConfig};
+use berry_lib::db::sessions::{self, Refreshed, SessionRecord};
+use berry_lib::db::twitch_credentials;
+use berry_lib::twitch::twitch_access_token;
+use chrono::Duration;
+use colored::*;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+const ACCESS_TOKEN_LIFETIME: Duration = Duration::minutes(15);
+
+#[derive(Debug, Serialize)]
+pub struct SessionTokens {
+    pub token: String,
+    pub refresh_token: String,
+    // Seconds until `token` expires
+    pub expires_in: i64,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    refresh_token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    #[serde(flatten)]
+    session: SessionRecord,
+    // The session making this request
+    current: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionsRevoked {
+    revoked: u64,
+}
+
+pub fn session_tokens(
+    jwt_config: &JwtConfig,
+    session: &SessionRecord,
+    refresh_token: String,
+) -> Result<SessionTokens, String> {
+    let claims = jwt::Claims::new(&session.unxid, &session.twitch_id, &session.id, ACCESS_TOKEN_LIFETIME);
+
+    let token = match jwt_config.generate_token(&claims) {
+        Ok(token) => token,
+        Err(_) => return Err("Failed to generate JWT Token".to_string()),
+    };
+
+    Ok(SessionTokens {
+        token,
+        refresh_token,
+        expires_in: ACCESS_TOKEN_LIFETIME.num_seconds(),
+    })
+}
+
+pub async fn refresh(
+    pool: web::Data<PgPool>,
+    jwt_config: web::Data<JwtConfig>,
+    data: web::Json<RefreshRequest>,
+) -> ApiResponse<SessionTokens> {
+    match sessions::refresh_session(&pool, &data.refresh_token).await {
+        Ok(Refreshed::Rotated { session, refresh_token }) => match session_tokens(&jwt_config, &session, refresh_token) {
+            Ok(tokens) => ApiResponse::new(Some(tokens), None, Some(StatusCode::OK)),
+            Err(e) => ApiResponse::new(None, Some(e), Some(StatusCode::INTERNAL_SERVER_ERROR)),
+        },
+        Ok(Refreshed::Reused) => {
+            println!("{}", "Refresh Token Reused, Session Revoked".bright_red());
+            ApiResponse::new(
+                None,
+                Some("Refresh token already used, please sign in again".to_string()),
+                Some(StatusCode::UNAUTHORIZED),
+            )
+        }
+        Ok(Refreshed::Invalid) => ApiResponse::new(
+            None,
+            Some("Invalid or expired refresh token".to_string()),
+            Some(StatusCode::UNAUTHORIZED),
+        ),
+        Err(e) => ApiResponse::db_error("Error Refreshing Session", e),
+    }
+}
+
+// Ends the current session and gives up Berry's access to the user's Twitch
+// account. A failure on Twitch's side doesn't stop the logout.
+pub async fn logout(user: AuthUser, pool: web::Data<PgPool>, reqwest_client: web::Data<Client>) -> ApiResponse<bool> {
+    if let Err(e) = sessions::revoke_session(&pool, &user.unxid, &user.session_id, "logout").await {
+        return ApiResponse::db_error("Error Logging Out", e);
+    }
+
+    revoke_twitch_access(&pool, &reqwest_client, &user.unxid).await;
+
+    ApiResponse::new(Some(true), None, Some(StatusCode::OK))
+}
+
+async fn revoke_twitch_access(pool: &PgPool, client: &Client, unxid: &str) {
+    let credentials = match Envelope::shared() {
+        Ok(envelope) => twitch_credentials::get_credentials(pool, &envelope, unxid).await,
+        Err(e) => Err(e.into()),
+    };
+
+    match credentials {
+        Ok(Some(credentials)) => {
+            if let Err(e) = twitch_access_token::revoke_access_token(&credentials.access_token, client).await {
+                eprintln!("Error Revoking Twitch Token: {}", e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Error Getting Twitch Credentials: {}", e),
+    }
+
+    if let Err(e) = twitch_credentials::delete_credentials(pool, unxid).await {
+        eprintln!("Error Deleting Twitch Credentials: {}", e);
+    }
+}
+
+pub async fn list_sessions(user: AuthUser, pool: web::Data<PgPool>) -> ApiResponse<Vec<SessionInfo>> {
+    match sessions::list_sessions(&pool, &user.unxid).await {
+        Ok(sessions) => {
+            let sessions = sessions
+                .into_iter()
+                .map(|session| SessionInfo {
+                    current: session.id == user.session_id,
+                    session,
+                })
+                .collect();
+
+            ApiResponse::new(Some(sessions), None, Some(StatusCode::OK))
+        }
+        Err(e) => ApiResponse::db_error("Error Getting Sessions", e),
+    }
+}
+
+pub async fn revoke_other_sessions(user: AuthUser, pool: web::Data<PgPool>) -> ApiResponse<SessionsRevoked> {
+    match sessions::revoke_other_sessions(&pool, &user.unxid, &user.session_id, "signed out elsewhere").await {
+        Ok(revoked) => ApiResponse::new(Some(SessionsRevoked { revoked }), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error("Error Revoking Sessions", e),
+    }
+}
+
+pub async fn revoke_session(user: AuthUser, pool: web::Data<PgPool>, path: web::Path<String>) -> ApiResponse<bool> {
+    match sessions::revoke_session(&pool, &user.unxid, &path.into_inner(), "signed out elsewhere").await {
+        Ok(true) => ApiResponse::new(Some(true), None, Some(StatusCode::OK)),
+        Ok(false) => ApiResponse::new(
+            None,
+            Some("Session not found".to_string()),
+            Some(StatusCode::NOT_FOUND),
+        ),
+        Err(e) => ApiResponse::db_error("Error Revoking Session", e),
+    }
+}
@@ -0,0 +1,116 @@
+//##############################################
+// TWITCH AUTHORIZE ROUTE
+// Endpoint: /auth/twitch/authorize
+// Method: GET
+// Query: scopes (space or comma separated, optional)
+// Starts a Twitch login. Send the user to `url`; Twitch redirects back with a
+// `code` and this `state`, which both go to POST /auth/login.
+// Also sets the login cookie, which POST /auth/login needs from the same browser.
+//##############################################
+
+use actix_web::cookie::{time, Cookie, SameSite};
+use actix_web::http::header::SET_COOKIE;
+use actix_web::http::StatusCode;
+use actix_web::web;
+use actix_web::{CustomizeResponder, Responder};
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::auth::jwt::{self, JwtConfig};
+use berry_lib::auth::oauth::{self, LoginState, Pkce, LOGIN_STATE_LIFETIME};
+use berry_lib::db::twitch_login_attempts;
+use berry_lib::twitch::twitch_access_token::{self, DEFAULT_SCOPES};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+// Holds the attempt's nonce, so a state only logs in the browser that started it.
+// Otherwise someone could send a victim a link that finishes their own login and
+// signs the victim into the attacker's account.
+pub const LOGIN_COOKIE: &str = "berry_login";
+const LOGIN_COOKIE_PATH: &str = "/auth/login";
+
+pub fn login_cookie(nonce: &str) -> Cookie<'static> {
+    Cookie::build(LOGIN_COOKIE, nonce.to_string())
+        .path(LOGIN_COOKIE_PATH)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(jwt::issuer().starts_with("https://"))
+        .max_age(time::Duration::seconds(LOGIN_STATE_LIFETIME.num_seconds()))
+        .finish()
+}
+
+// Replaces the login cookie with an expired one once the login is done
+pub fn expired_login_cookie() -> Cookie<'static> {
+    let mut cookie = login_cookie("");
+    cookie.make_removal();
+    cookie
+}
+
+#[derive(Deserialize)]
+pub struct AuthorizeQuery {
+    scopes: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuthorizeRes {
+    url: String,
+    state: String,
+    scopes: Vec<String>,
+    // Seconds left to finish the login
+    expires_in: i64,
+}
+
+pub async fn authorize_twitch(
+    pool: web::Data<PgPool>,
+    jwt_config: web::Data<JwtConfig>,
+    query: web::Query<AuthorizeQuery>,
+) -> CustomizeResponder<ApiResponse<AuthorizeRes>> {
+    let nonce = oauth::new_nonce();
+
+    match start_login(&pool, &jwt_config, &query, &nonce).await {
+        Ok(started) => ApiResponse::new(Some(started), None, Some(StatusCode::OK))
+            .customize()
+            .append_header((SET_COOKIE, login_cookie(&nonce).to_string())),
+        Err(e) => ApiResponse::new(None, Some(e), Some(StatusCode::INTERNAL_SERVER_ERROR)).customize(),
+    }
+}
+
+async fn start_login(
+    pool: &PgPool,
+    jwt_config: &JwtConfig,
+    query: &AuthorizeQuery,
+    nonce: &str,
+) -> Result<AuthorizeRes, String> {
+    let scopes: Vec<String> = match &query.scopes {
+        Some(scopes) => scopes
+            .split([' ', ','])
+            .filter(|scope| !scope.is_empty())
+            .map(str::to_string)
+            .collect(),
+        None => DEFAULT_SCOPES.iter().map(|scope| scope.to_string()).collect(),
+    };
+
+    let pkce = Pkce::new();
+
+    let state = match LoginState::new(nonce).sign(jwt_config) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("Error Signing Login State: {}", e);
+            return Err("Error Starting Login".to_string());
+        }
+    };
+
+    if let Err(e) =
+        twitch_login_attempts::create_attempt(pool, nonce, &pkce.verifier, &scopes, LOGIN_STATE_LIFETIME).await
+    {
+        eprintln!("Error Saving Login Attempt: {}", e);
+        return Err("Error Starting Login".to_string());
+    }
+
+    let url = twitch_access_token::authorize_url(&scopes, &state, nonce, &pkce.challenge);
+
+    Ok(AuthorizeRes {
+        url,
+        state,
+        scopes,
+        expires_in: LOGIN_STATE_LIFETIME.num_seconds(),
+    })
+}
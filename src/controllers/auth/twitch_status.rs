@@ -0,0 +1,49 @@
+//##############################################
+// TWITCH CONNECTION ROUTE
+// Endpoint: /auth/twitch
+// Method: GET
+// Whether Berry can still act on the caller's Twitch account. When
+// `reauthenticate` is true the user has to sign in with Twitch again.
+//##############################################
+
+use crate::middleware::auth_user::AuthUser;
+use actix_web::http::StatusCode;
+use actix_web::web;
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::db::twitch_credentials::{self, CredentialsStatus};
+use serde::Serialize;
+use sqlx::PgPool;
+
+#[derive(Debug, Serialize)]
+pub struct TwitchConnection {
+    reauthenticate: bool,
+    // None when no tokens are stored
+    credentials: Option<CredentialsStatus>,
+}
+
+pub async fn get_twitch_connection(user: AuthUser, pool: web::Data<PgPool>) -> ApiResponse<TwitchConnection> {
+    match twitch_credentials::get_status(&pool, &user.unxid).await {
+        Ok(credentials) => {
+            let reauthenticate = credentials
+                .as_ref()
+                .is_none_or(|credentials| credentials.revoked_at.is_some());
+
+            ApiResponse::new(
+                Some(TwitchConnection {
+                    reauthenticate,
+                    credentials,
+                }),
+                None,
+                Some(StatusCode::OK),
+            )
+        }
+        Err(e) => {
+            eprintln!("Error Getting Twitch Credentials: {}", e);
+            ApiResponse::new(
+                None,
+                Some("Error Getting Twitch Credentials".to_string()),
+                Some(StatusCode::INTERNAL_SERVER_ERROR),
+            )
+        }
+    }
+}
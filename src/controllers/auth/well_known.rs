@@ -0,0 +1,44 @@
+//##############################################
+// WELL-KNOWN ROUTES
+// Endpoint: /.well-known/jwks.json
+// Method: GET
+// Endpoint: /.well-known/openid-configuration
+// Method: GET
+// No authentication. Both are served as standard documents rather than in the
+// usual { data, error } envelope, so off-the-shelf JWT libraries can read them.
+//##############################################
+
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::HttpResponse;
+use berry_lib::auth::jwks::OpenIdConfiguration;
+use berry_lib::auth::jwt;
+use berry_lib::auth::keys::JwtKeys;
+use colored::*;
+
+// Verifiers cache for this long, so a new key should be published at least
+// this long before it starts signing
+const MAX_AGE_SECS: u32 = 5 * 60;
+
+pub async fn jwks() -> HttpResponse {
+    match JwtKeys::shared() {
+        Ok(keys) => HttpResponse::Ok()
+            .insert_header(CacheControl(vec![CacheDirective::Public, CacheDirective::MaxAge(MAX_AGE_SECS)]))
+            .json(keys.jwks()),
+        Err(e) => {
+            eprintln!("{} {}", "Error Loading JWT Keys:".bright_red(), e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+pub async fn openid_configuration() -> HttpResponse {
+    match JwtKeys::shared() {
+        Ok(keys) => HttpResponse::Ok()
+            .insert_header(CacheControl(vec![CacheDirective::Public, CacheDirective::MaxAge(MAX_AGE_SECS)]))
+            .json(OpenIdConfiguration::new(&jwt::issuer(), &keys)),
+        Err(e) => {
+            eprintln!("{} {}", "Error Loading JWT Keys:".bright_red(), e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
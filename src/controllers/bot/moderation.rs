@@ -0,0 +1,74 @@
+//##############################################
+// MODERATION ROUTES
+// Endpoint: /moderation/audit
+// Method: GET, what the bot's moderation did, newest first
+// Query: user (String, optional, only actions against this user), limit (i64, optional, default 20)
+// Endpoint: /moderation/strikes/{user}
+// Method: GET, how many strikes the user has in the channel
+//##############################################
+
+use crate::controllers::auth::caller::resolve_caller;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest};
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::db::moderation_audit::{self, AuditRecord};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+const DB_ERROR: &str = "Error Accessing Moderation Audit Log";
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    user: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Strikes {
+    user: String,
+    strikes: i64,
+}
+
+pub async fn list_audit_log(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    query: web::Query<AuditQuery>,
+) -> ApiResponse<Vec<AuditRecord>> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let user = query.user.as_deref().map(login_from);
+
+    match moderation_audit::list_entries(&pool, &caller.channel, user, limit).await {
+        Ok(entries) => ApiResponse::new(Some(entries), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+pub async fn get_strikes(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+) -> ApiResponse<Strikes> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let user = login_from(&path).to_lowercase();
+
+    match moderation_audit::count_strikes(&pool, &caller.channel, &user).await {
+        Ok(strikes) => ApiResponse::new(Some(Strikes { user, strikes }), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+// Logins are stored without the @ chat mentions carry
+fn login_from(user: &str) -> &str {
+    user.trim().trim_start_matches('@')
+}
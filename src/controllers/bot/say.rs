@@ -0,0 +1,87 @@
+//##############################################
+// BOT SAY ROUTE
+// Endpoint: /bot/say
+// Method: POST
+// Request Body: message (String)
+// Posts a message in the channel's chat as the bot, for overlays, Stream Deck
+// buttons and scripts.
+//##############################################
+
+use crate::controllers::auth::caller::resolve_caller;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest};
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::twitch::bot_registry::{BotControl, BotRegistry};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+// Twitch drops longer chat messages
+const MAX_MESSAGE_LEN: usize = 500;
+
+#[derive(Deserialize)]
+pub struct SayRequest {
+    message: String,
+}
+
+pub async fn say(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    data: web::Json<SayRequest>,
+) -> ApiResponse<bool> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let message = match check_message(&data.message) {
+        Ok(message) => message,
+        Err(e) => return ApiResponse::new(None, Some(e), Some(StatusCode::BAD_REQUEST)),
+    };
+
+    if !bot_registry.send(&caller.channel, BotControl::Say(message.to_string())) {
+        return ApiResponse::new(
+            None,
+            Some("The bot isn't running in this channel".to_string()),
+            Some(StatusCode::CONFLICT),
+        );
+    }
+
+    ApiResponse::new(Some(true), None, Some(StatusCode::ACCEPTED))
+}
+
+// The message goes straight onto the IRC connection, so line breaks would let
+// it send commands of its own. Chat commands like /ban are refused too.
+fn check_message(message: &str) -> Result<&str, String> {
+    let message = message.trim();
+
+    if message.is_empty() {
+        return Err("Message can't be empty".to_string());
+    }
+    if message.chars().count() > MAX_MESSAGE_LEN {
+        return Err(format!("Message can be at most {} characters", MAX_MESSAGE_LEN));
+    }
+    if message.chars().any(char::is_control) {
+        return Err("Message can't contain line breaks or control characters".to_string());
+    }
+    if message.starts_with('/') || message.starts_with('.') {
+        return Err("Chat commands can't be sent".to_string());
+    }
+
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_messages_that_could_escape_chat() {
+        assert_eq!(check_message("  hello chat "), Ok("hello chat"));
+        assert!(check_message("hi\r\nPRIVMSG #other :spam").is_err());
+        assert!(check_message("/ban someone").is_err());
+        assert!(check_message(".timeout someone").is_err());
+        assert!(check_message("   ").is_err());
+        assert!(check_message(&"a".repeat(MAX_MESSAGE_LEN + 1)).is_err());
+    }
+}
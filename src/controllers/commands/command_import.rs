@@ -0,0 +1,100 @@
+//##############################################
+// COMMAND IMPORT/EXPORT ROUTES
+// Endpoint: /commands/import
+// Method: POST
+// Query: format (berry, nightbot, streamelements, fossabot or streamlabs),
+//   overwrite (bool, optional), dry_run (bool, optional)
+// Request Body: the other bot's export, as JSON or CSV
+// Endpoint: /commands/export
+// Method: GET
+//##############################################
+
+use crate::controllers::auth::caller::resolve_caller;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest};
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::db::custom_commands;
+use berry_lib::import::export::{self, CommandExport};
+use berry_lib::import::{self, ImportError, ImportFormat, ImportReport};
+use berry_lib::twitch::bot_registry::{BotControl, BotRegistry};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+// Exports with hundreds of long commands run past actix's 256kB default
+pub const MAX_IMPORT_SIZE: usize = 2 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    format: String,
+    #[serde(default)]
+    overwrite: bool,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+pub async fn import_commands(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    query: web::Query<ImportQuery>,
+    body: String,
+) -> ApiResponse<ImportReport> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let format = match query.format.parse::<ImportFormat>() {
+        Ok(format) => format,
+        Err(e) => return ApiResponse::new(None, Some(e), Some(StatusCode::BAD_REQUEST)),
+    };
+
+    let result = import::import_commands(
+        &pool,
+        &caller.channel,
+        &caller.login,
+        format,
+        &body,
+        query.overwrite,
+        query.dry_run,
+    )
+    .await;
+
+    match result {
+        Ok(report) => {
+            if !report.dry_run {
+                bot_registry.send(&caller.channel, BotControl::ReloadCommands);
+            }
+            ApiResponse::new(Some(report), None, Some(StatusCode::OK))
+        }
+        Err(ImportError::Unreadable(e)) => ApiResponse::new(
+            None,
+            Some(format!("Could not read the export: {}", e)),
+            Some(StatusCode::BAD_REQUEST),
+        ),
+        Err(e) => {
+            eprintln!("Error Importing Commands: {}", e);
+            ApiResponse::new(
+                None,
+                Some("Error Importing Commands".to_string()),
+                Some(StatusCode::INTERNAL_SERVER_ERROR),
+            )
+        }
+    }
+}
+
+pub async fn export_commands(req: HttpRequest, pool: web::Data<PgPool>) -> ApiResponse<CommandExport> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    match custom_commands::list_commands(&pool, &caller.channel).await {
+        Ok(commands) => ApiResponse::new(
+            Some(export::export_commands(&caller.channel, commands)),
+            None,
+            Some(StatusCode::OK),
+        ),
+        Err(e) => ApiResponse::db_error("Error Exporting Commands", e),
+    }
+}
@@ -0,0 +1,178 @@
+//##############################################
+// COUNTER ROUTES
+// Endpoint: /counters
+// Methods: GET, POST
+// Request Body (POST): name (String), value (i64, optional)
+// Endpoint: /counters/{name}
+// Methods: PUT, DELETE
+// Request Body (PUT): value (i64) to set it, or delta (i64) to add to it
+// Endpoint: /overlay/{channel}/counters, /overlay/{channel}/counters/{name}
+// Method: GET (no authentication, for stream overlays)
+//##############################################
+
+use crate::controllers::auth::caller::resolve_caller;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest};
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::db::counters::{self, normalize_counter_name, CounterInput, CounterRecord};
+use berry_lib::twitch::bot_registry::{BotControl, BotRegistry};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+const DB_ERROR: &str = "Error Accessing Counters";
+const CONFLICT: &str = "A counter with that name already exists";
+
+#[derive(Deserialize)]
+pub struct CounterUpdate {
+    value: Option<i64>,
+    delta: Option<i64>,
+}
+
+pub async fn list_counters(req: HttpRequest, pool: web::Data<PgPool>) -> ApiResponse<Vec<CounterRecord>> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    match counters::list_counters(&pool, &caller.channel).await {
+        Ok(counters) => ApiResponse::new(Some(counters), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+pub async fn create_counter(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    data: web::Json<CounterInput>,
+) -> ApiResponse<CounterRecord> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let mut input = data.into_inner();
+    input.name = match normalize_counter_name(&input.name) {
+        Some(name) => name,
+        None => return invalid_name(),
+    };
+
+    match counters::create_counter(&pool, &caller.channel, &input).await {
+        Ok(counter) => {
+            bot_registry.send(&caller.channel, BotControl::ReloadCommands);
+            ApiResponse::new(Some(counter), None, Some(StatusCode::CREATED))
+        }
+        Err(e) => ApiResponse::db_error_or_conflict(DB_ERROR, CONFLICT, e),
+    }
+}
+
+pub async fn update_counter(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    name: web::Path<String>,
+    data: web::Json<CounterUpdate>,
+) -> ApiResponse<CounterRecord> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let name = match normalize_counter_name(&name) {
+        Some(name) => name,
+        None => return invalid_name(),
+    };
+
+    // Only existing counters are updated here, chat and templates create them on the fly
+    match counters::get_counter(&pool, &caller.channel, &name).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return not_found(),
+        Err(e) => return ApiResponse::db_error_or_conflict(DB_ERROR, CONFLICT, e),
+    }
+
+    let result = match (data.value, data.delta) {
+        (Some(value), None) => counters::set_counter(&pool, &caller.channel, &name, value).await,
+        (None, Some(delta)) => counters::add_to_counter(&pool, &caller.channel, &name, delta).await,
+        _ => {
+            return ApiResponse::new(
+                None,
+                Some("Send either value or delta".to_string()),
+                Some(StatusCode::BAD_REQUEST),
+            )
+        }
+    };
+
+    match result {
+        Ok(counter) => ApiResponse::new(Some(counter), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error_or_conflict(DB_ERROR, CONFLICT, e),
+    }
+}
+
+pub async fn delete_counter(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    name: web::Path<String>,
+) -> ApiResponse<bool> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let name = match normalize_counter_name(&name) {
+        Some(name) => name,
+        None => return invalid_name(),
+    };
+
+    match counters::delete_counter(&pool, &caller.channel, &name).await {
+        Ok(true) => {
+            bot_registry.send(&caller.channel, BotControl::ReloadCommands);
+            ApiResponse::new(Some(true), None, Some(StatusCode::OK))
+        }
+        Ok(false) => not_found(),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+pub async fn get_overlay_counters(
+    pool: web::Data<PgPool>,
+    channel: web::Path<String>,
+) -> ApiResponse<Vec<CounterRecord>> {
+    match counters::list_counters(&pool, &channel.to_lowercase()).await {
+        Ok(counters) => ApiResponse::new(Some(counters), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+pub async fn get_overlay_counter(
+    pool: web::Data<PgPool>,
+    path: web::Path<(String, String)>,
+) -> ApiResponse<CounterRecord> {
+    let (channel, name) = path.into_inner();
+
+    let name = match normalize_counter_name(&name) {
+        Some(name) => name,
+        None => return invalid_name(),
+    };
+
+    match counters::get_counter(&pool, &channel.to_lowercase(), &name).await {
+        Ok(Some(counter)) => ApiResponse::new(Some(counter), None, Some(StatusCode::OK)),
+        Ok(None) => not_found(),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+fn invalid_name<T: serde::Serialize + std::fmt::Debug>() -> ApiResponse<T> {
+    ApiResponse::new(
+        None,
+        Some("Counter names must be up to 30 letters, digits or underscores".to_string()),
+        Some(StatusCode::BAD_REQUEST),
+    )
+}
+
+fn not_found<T: serde::Serialize + std::fmt::Debug>() -> ApiResponse<T> {
+    ApiResponse::new(
+        None,
+        Some("Counter not found".to_string()),
+        Some(StatusCode::NOT_FOUND),
+    )
+}
@@ -0,0 +1,118 @@
+//##############################################
+// CUSTOM COMMAND ROUTES
+// Endpoint: /commands
+// Methods: GET, POST
+// Endpoint: /commands/{id}
+// Methods: PUT, DELETE
+// Request Body (POST/PUT): name (String), response (String), enabled (bool, optional),
+//   aliases ([String], optional), permission (String, optional),
+//   global_cooldown_secs (i32, optional), user_cooldown_secs (i32, optional)
+//##############################################
+
+use crate::controllers::auth::caller::resolve_caller;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest};
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::db::custom_commands::{self, validate_command_input, CustomCommandInput, CustomCommandRecord};
+use berry_lib::twitch::bot_registry::{BotControl, BotRegistry};
+use sqlx::PgPool;
+
+const DB_ERROR: &str = "Error Accessing Custom Commands";
+const CONFLICT: &str = "A command with that name already exists";
+
+pub async fn list_commands(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> ApiResponse<Vec<CustomCommandRecord>> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    match custom_commands::list_commands(&pool, &caller.channel).await {
+        Ok(commands) => ApiResponse::new(Some(commands), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+pub async fn create_command(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    data: web::Json<CustomCommandInput>,
+) -> ApiResponse<CustomCommandRecord> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let input = match validate_command_input(data.into_inner()) {
+        Ok(input) => input,
+        Err(e) => return ApiResponse::new(None, Some(e), Some(StatusCode::BAD_REQUEST)),
+    };
+
+    match custom_commands::create_command(&pool, &caller.channel, &caller.login, &input).await {
+        Ok(command) => {
+            bot_registry.send(&caller.channel, BotControl::ReloadCommands);
+            ApiResponse::new(Some(command), None, Some(StatusCode::CREATED))
+        }
+        Err(e) => ApiResponse::db_error_or_conflict(DB_ERROR, CONFLICT, e),
+    }
+}
+
+pub async fn update_command(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    id: web::Path<i64>,
+    data: web::Json<CustomCommandInput>,
+) -> ApiResponse<CustomCommandRecord> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let input = match validate_command_input(data.into_inner()) {
+        Ok(input) => input,
+        Err(e) => return ApiResponse::new(None, Some(e), Some(StatusCode::BAD_REQUEST)),
+    };
+
+    match custom_commands::update_command(&pool, &caller.channel, id.into_inner(), &input).await {
+        Ok(Some(command)) => {
+            bot_registry.send(&caller.channel, BotControl::ReloadCommands);
+            ApiResponse::new(Some(command), None, Some(StatusCode::OK))
+        }
+        Ok(None) => not_found(),
+        Err(e) => ApiResponse::db_error_or_conflict(DB_ERROR, CONFLICT, e),
+    }
+}
+
+pub async fn delete_command(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    id: web::Path<i64>,
+) -> ApiResponse<bool> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    match custom_commands::delete_command(&pool, &caller.channel, id.into_inner()).await {
+        Ok(true) => {
+            bot_registry.send(&caller.channel, BotControl::ReloadCommands);
+            ApiResponse::new(Some(true), None, Some(StatusCode::OK))
+        }
+        Ok(false) => not_found(),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+// Command names are stored lowercase without the leading "!"
+fn not_found<T: serde::Serialize + std::fmt::Debug>() -> ApiResponse<T> {
+    ApiResponse::new(
+        None,
+        Some("Command not found".to_string()),
+        Some(StatusCode::NOT_FOUND),
+    )
+}
@@ -0,0 +1,228 @@
+//##############################################
+// GIVEAWAY ROUTES
+// Endpoint: /giveaways
+// Methods: GET, POST
+// Query (GET): limit (i64, optional, default 20)
+// Request Body (POST): keyword (String), subscriber_weight (i32, optional, default 2)
+// Endpoint: /giveaways/{id}
+// Method: GET, the giveaway with its entry count and draws
+// Endpoint: /giveaways/{id}/close, /giveaways/{id}/end
+// Method: POST, stops entries / ends the giveaway
+// Endpoint: /giveaways/{id}/draw
+// Method: POST, has the channel's bot draw a winner (needs the bot running)
+// Endpoint: /overlay/{channel}/giveaway
+// Method: GET (no authentication, for stream overlays)
+//##############################################
+
+use crate::controllers::auth::caller::resolve_caller;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest};
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::db::giveaways::{self, GiveawayRecord, GiveawaySummary};
+use berry_lib::twitch::bot_registry::{BotControl, BotRegistry};
+use berry_lib::twitch::giveaway_commands::{normalize_keyword, DEFAULT_SUBSCRIBER_WEIGHT, MAX_SUBSCRIBER_WEIGHT};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+const DB_ERROR: &str = "Error Accessing Giveaways";
+const CONFLICT: &str = "A giveaway is already running";
+
+#[derive(Deserialize)]
+pub struct GiveawaysQuery {
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct GiveawayInput {
+    keyword: String,
+    subscriber_weight: Option<i32>,
+}
+
+pub async fn list_giveaways(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    query: web::Query<GiveawaysQuery>,
+) -> ApiResponse<Vec<GiveawayRecord>> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    match giveaways::list_giveaways(&pool, &caller.channel, limit).await {
+        Ok(giveaways) => ApiResponse::new(Some(giveaways), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+pub async fn create_giveaway(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    data: web::Json<GiveawayInput>,
+) -> ApiResponse<GiveawayRecord> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let keyword = match normalize_keyword(&data.keyword) {
+        Some(keyword) => keyword,
+        None => {
+            return ApiResponse::new(
+                None,
+                Some("The keyword must be a single word of up to 25 characters".to_string()),
+                Some(StatusCode::BAD_REQUEST),
+            )
+        }
+    };
+
+    let subscriber_weight = data.subscriber_weight.unwrap_or(DEFAULT_SUBSCRIBER_WEIGHT);
+    if !(1..=MAX_SUBSCRIBER_WEIGHT).contains(&subscriber_weight) {
+        return ApiResponse::new(
+            None,
+            Some(format!("subscriber_weight must be between 1 and {}", MAX_SUBSCRIBER_WEIGHT)),
+            Some(StatusCode::BAD_REQUEST),
+        );
+    }
+
+    match giveaways::create_giveaway(&pool, &caller.channel, &keyword, subscriber_weight, &caller.login).await {
+        Ok(giveaway) => {
+            bot_registry.send(&caller.channel, BotControl::GiveawayChanged);
+            ApiResponse::new(Some(giveaway), None, Some(StatusCode::CREATED))
+        }
+        Err(e) => ApiResponse::db_error_or_conflict(DB_ERROR, CONFLICT, e),
+    }
+}
+
+pub async fn get_giveaway(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    id: web::Path<i64>,
+) -> ApiResponse<GiveawaySummary> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    match giveaways::get_giveaway(&pool, &caller.channel, *id).await {
+        Ok(Some(giveaway)) => summary(&pool, giveaway, StatusCode::OK).await,
+        Ok(None) => not_found(),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+pub async fn close_giveaway(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    id: web::Path<i64>,
+) -> ApiResponse<GiveawaySummary> {
+    set_status(req, pool, bot_registry, *id, "closed").await
+}
+
+pub async fn end_giveaway(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    id: web::Path<i64>,
+) -> ApiResponse<GiveawaySummary> {
+    set_status(req, pool, bot_registry, *id, "ended").await
+}
+
+// The bot draws, since it checks the winner is still in chat. The draw shows up
+// on GET /giveaways/{id} and the overlay once the bot has made it.
+pub async fn draw_giveaway(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    id: web::Path<i64>,
+) -> ApiResponse<GiveawaySummary> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let giveaway = match giveaways::get_giveaway(&pool, &caller.channel, *id).await {
+        Ok(Some(giveaway)) if giveaway.status != "ended" => giveaway,
+        Ok(_) => {
+            return ApiResponse::new(
+                None,
+                Some("No active giveaway with that id".to_string()),
+                Some(StatusCode::NOT_FOUND),
+            )
+        }
+        Err(e) => return ApiResponse::db_error(DB_ERROR, e),
+    };
+
+    let control = BotControl::DrawGiveaway {
+        id: giveaway.id,
+        drawn_by: caller.login.clone(),
+    };
+
+    if !bot_registry.send(&caller.channel, control) {
+        return ApiResponse::new(
+            None,
+            Some("The bot isn't running in this channel".to_string()),
+            Some(StatusCode::CONFLICT),
+        );
+    }
+
+    summary(&pool, giveaway, StatusCode::ACCEPTED).await
+}
+
+// The channel's active giveaway, or its most recent one
+pub async fn get_overlay_giveaway(
+    pool: web::Data<PgPool>,
+    channel: web::Path<String>,
+) -> ApiResponse<GiveawaySummary> {
+    match giveaways::latest_giveaway(&pool, &channel.to_lowercase()).await {
+        Ok(Some(giveaway)) => summary(&pool, giveaway, StatusCode::OK).await,
+        Ok(None) => not_found(),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+async fn set_status(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    id: i64,
+    status: &str,
+) -> ApiResponse<GiveawaySummary> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    match giveaways::set_status(&pool, &caller.channel, id, status).await {
+        Ok(Some(giveaway)) => {
+            bot_registry.send(&caller.channel, BotControl::GiveawayChanged);
+            summary(&pool, giveaway, StatusCode::OK).await
+        }
+        Ok(None) => ApiResponse::new(
+            None,
+            Some(format!("No giveaway with that id that can be {}", status)),
+            Some(StatusCode::NOT_FOUND),
+        ),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+async fn summary(pool: &PgPool, giveaway: GiveawayRecord, status: StatusCode) -> ApiResponse<GiveawaySummary> {
+    match giveaways::giveaway_summary(pool, giveaway).await {
+        Ok(summary) => ApiResponse::new(Some(summary), None, Some(status)),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+fn not_found<T: serde::Serialize + std::fmt::Debug>() -> ApiResponse<T> {
+    ApiResponse::new(
+        None,
+        Some("Giveaway not found".to_string()),
+        Some(StatusCode::NOT_FOUND),
+    )
+}
@@ -0,0 +1,8 @@
+pub mod command_import;
+pub mod counters;
+pub mod custom_commands;
+pub mod giveaways;
+pub mod points;
+pub mod polls;
+pub mod quotes;
+pub mod timers;
@@ -0,0 +1,123 @@
+//##############################################
+// POINTS ROUTES
+// Endpoint: /points
+// Method: GET
+// Query: limit (i64, optional, default 20), the channel's leaderboard
+// Endpoint: /points/ledger
+// Method: GET
+// Query: login (String, optional), limit (i64, optional, default 50)
+// Endpoint: /points/adjust
+// Method: POST
+// Request Body: login (String), amount (i64, negative to take points away), reason (String, optional)
+//##############################################
+
+use crate::controllers::auth::caller::resolve_caller;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest};
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::db::points::{self, LedgerEntry, PointsBalance, PointsError};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+const DEFAULT_LEADERBOARD_LIMIT: i64 = 20;
+const DEFAULT_LEDGER_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 500;
+const DB_ERROR: &str = "Error Accessing Points";
+
+#[derive(Deserialize)]
+pub struct LeaderboardQuery {
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct LedgerQuery {
+    login: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct PointsAdjustment {
+    login: String,
+    amount: i64,
+    reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdjustedBalance {
+    login: String,
+    balance: i64,
+}
+
+pub async fn get_leaderboard(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    query: web::Query<LeaderboardQuery>,
+) -> ApiResponse<Vec<PointsBalance>> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_LEADERBOARD_LIMIT).clamp(1, MAX_LIMIT);
+
+    match points::leaderboard(&pool, &caller.channel, limit).await {
+        Ok(balances) => ApiResponse::new(Some(balances), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+pub async fn get_ledger(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    query: web::Query<LedgerQuery>,
+) -> ApiResponse<Vec<LedgerEntry>> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_LEDGER_LIMIT).clamp(1, MAX_LIMIT);
+    let login = query.login.as_deref().map(|login| login.trim().to_lowercase());
+
+    match points::ledger(&pool, &caller.channel, login.as_deref(), limit).await {
+        Ok(entries) => ApiResponse::new(Some(entries), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+pub async fn adjust_points(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    data: web::Json<PointsAdjustment>,
+) -> ApiResponse<AdjustedBalance> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let login = data.login.trim().trim_start_matches('@').to_lowercase();
+    if login.is_empty() || data.amount == 0 {
+        return ApiResponse::new(
+            None,
+            Some("A login and a non-zero amount are required".to_string()),
+            Some(StatusCode::BAD_REQUEST),
+        );
+    }
+
+    let reason = data
+        .reason
+        .as_deref()
+        .map(str::trim)
+        .filter(|reason| !reason.is_empty())
+        .unwrap_or("adjusted from the dashboard");
+
+    match points::adjust(&pool, &caller.channel, &login, data.amount, reason).await {
+        Ok(balance) => ApiResponse::new(Some(AdjustedBalance { login, balance }), None, Some(StatusCode::OK)),
+        Err(PointsError::InsufficientPoints) => ApiResponse::new(
+            None,
+            Some(format!("{} doesn't have enough points", login)),
+            Some(StatusCode::BAD_REQUEST),
+        ),
+        Err(PointsError::Database(e)) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
@@ -0,0 +1,148 @@
+//##############################################
+// POLL ROUTES
+// Endpoint: /polls
+// Methods: GET, POST
+// Query (GET): limit (i64, optional, default 20)
+// Request Body (POST): question (String), options (Vec<String>, 2 to 10)
+// Endpoint: /polls/{id}
+// Method: GET, the poll with its vote counts
+// Endpoint: /polls/{id}/end
+// Method: POST, closes the poll and has the bot post the results
+// Endpoint: /overlay/{channel}/poll
+// Method: GET (no authentication, for stream overlays)
+//##############################################
+
+use crate::controllers::auth::caller::resolve_caller;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest};
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::db::polls::{self, PollRecord, PollResults};
+use berry_lib::twitch::bot_registry::{BotControl, BotRegistry};
+use berry_lib::twitch::poll_commands::validate_poll;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+const DB_ERROR: &str = "Error Accessing Polls";
+const CONFLICT: &str = "A poll is already running";
+
+#[derive(Deserialize)]
+pub struct PollsQuery {
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct PollInput {
+    question: String,
+    options: Vec<String>,
+}
+
+pub async fn list_polls(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    query: web::Query<PollsQuery>,
+) -> ApiResponse<Vec<PollRecord>> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    match polls::list_polls(&pool, &caller.channel, limit).await {
+        Ok(polls) => ApiResponse::new(Some(polls), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+pub async fn create_poll(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    data: web::Json<PollInput>,
+) -> ApiResponse<PollRecord> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let (question, options) = match validate_poll(&data.question, &data.options) {
+        Ok(poll) => poll,
+        Err(e) => return ApiResponse::new(None, Some(e), Some(StatusCode::BAD_REQUEST)),
+    };
+
+    match polls::create_poll(&pool, &caller.channel, &question, &options, &caller.login).await {
+        Ok(poll) => {
+            bot_registry.send(&caller.channel, BotControl::PollStarted(poll.id));
+            ApiResponse::new(Some(poll), None, Some(StatusCode::CREATED))
+        }
+        Err(e) => ApiResponse::db_error_or_conflict(DB_ERROR, CONFLICT, e),
+    }
+}
+
+pub async fn get_poll(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    id: web::Path<i64>,
+) -> ApiResponse<PollResults> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    match polls::get_poll(&pool, &caller.channel, *id).await {
+        Ok(Some(poll)) => results(&pool, poll).await,
+        Ok(None) => not_found(),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+pub async fn end_poll(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    id: web::Path<i64>,
+) -> ApiResponse<PollResults> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    match polls::close_poll(&pool, &caller.channel, *id).await {
+        Ok(Some(poll)) => {
+            bot_registry.send(&caller.channel, BotControl::PollEnded(poll.id));
+            results(&pool, poll).await
+        }
+        Ok(None) => ApiResponse::new(
+            None,
+            Some("No open poll with that id".to_string()),
+            Some(StatusCode::NOT_FOUND),
+        ),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+// The channel's open poll, or its most recent one, with live vote counts
+pub async fn get_overlay_poll(pool: web::Data<PgPool>, channel: web::Path<String>) -> ApiResponse<PollResults> {
+    match polls::latest_poll(&pool, &channel.to_lowercase()).await {
+        Ok(Some(poll)) => results(&pool, poll).await,
+        Ok(None) => not_found(),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+async fn results(pool: &PgPool, poll: PollRecord) -> ApiResponse<PollResults> {
+    match polls::poll_results(pool, poll).await {
+        Ok(results) => ApiResponse::new(Some(results), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+fn not_found<T: serde::Serialize + std::fmt::Debug>() -> ApiResponse<T> {
+    ApiResponse::new(
+        None,
+        Some("Poll not found".to_string()),
+        Some(StatusCode::NOT_FOUND),
+    )
+}
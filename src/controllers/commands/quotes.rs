@@ -0,0 +1,42 @@
+//##############################################
+// CHANNEL QUOTES ROUTE
+// Endpoint: /quotes/{channel}
+// Method: GET (no authentication, for the website)
+// Query: page (i64, optional, default 1), per_page (i64, optional, default 20),
+//   search (String, optional)
+//##############################################
+
+use actix_web::http::StatusCode;
+use actix_web::web;
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::db::quotes::{self, QuotePage};
+use sqlx::PgPool;
+
+const DEFAULT_PER_PAGE: i64 = 20;
+const MAX_PER_PAGE: i64 = 100;
+
+#[derive(serde::Deserialize)]
+pub struct QuotesQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+    search: Option<String>,
+}
+
+pub async fn get_channel_quotes(
+    pool: web::Data<PgPool>,
+    channel: web::Path<String>,
+    query: web::Query<QuotesQuery>,
+) -> ApiResponse<QuotePage> {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+    let search = query
+        .search
+        .as_deref()
+        .map(str::trim)
+        .filter(|search| !search.is_empty());
+
+    match quotes::list_quotes(&pool, &channel.to_lowercase(), search, page, per_page).await {
+        Ok(page) => ApiResponse::new(Some(page), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error("Error Getting Quotes", e),
+    }
+}
@@ -0,0 +1,156 @@
+//##############################################
+// TIMER ROUTES
+// Endpoint: /timers
+// Methods: GET, POST
+// Endpoint: /timers/{id}
+// Methods: PUT, DELETE
+// Request Body (POST/PUT): name (String), messages ([String]), interval_minutes (i32),
+//   min_lines (i32, optional), enabled (bool, optional)
+//##############################################
+
+use crate::controllers::auth::caller::resolve_caller;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest};
+use berry_lib::api::api_response::ApiResponse;
+use berry_lib::db::timers::{self, TimerInput, TimerRecord};
+use berry_lib::twitch::bot_registry::{BotControl, BotRegistry};
+use sqlx::PgPool;
+
+const MAX_NAME_LENGTH: usize = 50;
+const MAX_MESSAGES: usize = 20;
+const MAX_MESSAGE_LENGTH: usize = 500;
+const MAX_INTERVAL_MINUTES: i32 = 24 * 60;
+const MAX_MIN_LINES: i32 = 1000;
+const DB_ERROR: &str = "Error Accessing Timers";
+const CONFLICT: &str = "A timer with that name already exists";
+
+pub async fn list_timers(req: HttpRequest, pool: web::Data<PgPool>) -> ApiResponse<Vec<TimerRecord>> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    match timers::list_timers(&pool, &caller.channel).await {
+        Ok(timers) => ApiResponse::new(Some(timers), None, Some(StatusCode::OK)),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+pub async fn create_timer(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    data: web::Json<TimerInput>,
+) -> ApiResponse<TimerRecord> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let input = match validate_input(data.into_inner()) {
+        Ok(input) => input,
+        Err(e) => return ApiResponse::new(None, Some(e), Some(StatusCode::BAD_REQUEST)),
+    };
+
+    match timers::create_timer(&pool, &caller.channel, &input).await {
+        Ok(timer) => {
+            bot_registry.send(&caller.channel, BotControl::ReloadTimers);
+            ApiResponse::new(Some(timer), None, Some(StatusCode::CREATED))
+        }
+        Err(e) => ApiResponse::db_error_or_conflict(DB_ERROR, CONFLICT, e),
+    }
+}
+
+pub async fn update_timer(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    id: web::Path<i64>,
+    data: web::Json<TimerInput>,
+) -> ApiResponse<TimerRecord> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    let input = match validate_input(data.into_inner()) {
+        Ok(input) => input,
+        Err(e) => return ApiResponse::new(None, Some(e), Some(StatusCode::BAD_REQUEST)),
+    };
+
+    match timers::update_timer(&pool, &caller.channel, id.into_inner(), &input).await {
+        Ok(Some(timer)) => {
+            bot_registry.send(&caller.channel, BotControl::ReloadTimers);
+            ApiResponse::new(Some(timer), None, Some(StatusCode::OK))
+        }
+        Ok(None) => not_found(),
+        Err(e) => ApiResponse::db_error_or_conflict(DB_ERROR, CONFLICT, e),
+    }
+}
+
+pub async fn delete_timer(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bot_registry: web::Data<BotRegistry>,
+    id: web::Path<i64>,
+) -> ApiResponse<bool> {
+    let caller = match resolve_caller(&req, &pool).await {
+        Ok(caller) => caller,
+        Err((status, e)) => return ApiResponse::new(None, Some(e), Some(status)),
+    };
+
+    match timers::delete_timer(&pool, &caller.channel, id.into_inner()).await {
+        Ok(true) => {
+            bot_registry.send(&caller.channel, BotControl::ReloadTimers);
+            ApiResponse::new(Some(true), None, Some(StatusCode::OK))
+        }
+        Ok(false) => not_found(),
+        Err(e) => ApiResponse::db_error(DB_ERROR, e),
+    }
+}
+
+fn validate_input(mut input: TimerInput) -> Result<TimerInput, String> {
+    input.name = input.name.trim().to_string();
+    input.messages = input
+        .messages
+        .iter()
+        .map(|message| message.trim().to_string())
+        .collect();
+
+    if input.name.is_empty() || input.name.len() > MAX_NAME_LENGTH {
+        return Err(format!("Timer name must be 1 to {} characters", MAX_NAME_LENGTH));
+    }
+
+    if input.messages.is_empty() || input.messages.len() > MAX_MESSAGES {
+        return Err(format!("A timer needs 1 to {} messages", MAX_MESSAGES));
+    }
+
+    if input
+        .messages
+        .iter()
+        .any(|message| message.is_empty() || message.len() > MAX_MESSAGE_LENGTH)
+    {
+        return Err(format!("Timer messages must be 1 to {} characters", MAX_MESSAGE_LENGTH));
+    }
+
+    if !(1..=MAX_INTERVAL_MINUTES).contains(&input.interval_minutes) {
+        return Err(format!("Interval must be 1 to {} minutes", MAX_INTERVAL_MINUTES));
+    }
+
+    if input
+        .min_lines
+        .is_some_and(|min_lines| !(0..=MAX_MIN_LINES).contains(&min_lines))
+    {
+        return Err(format!("Minimum chat lines must be 0 to {}", MAX_MIN_LINES));
+    }
+
+    Ok(input)
+}
+
+fn not_found<T: serde::Serialize + std::fmt::Debug>() -> ApiResponse<T> {
+    ApiResponse::new(
+        None,
+        Some("Timer not found".to_string()),
+        Some(StatusCode::NOT_FOUND),
+    )
+}
@@ -1 +1,4 @@
+pub mod analytics;
 pub mod auth;
+pub mod bot;
+pub mod commands;
@@ -1,10 +1,18 @@
 use actix_cors::Cors;
 use actix_web::{http::header, middleware::Logger, web, App, HttpServer};
+use berry_lib::analytics::trends::TrendRegistry;
+use berry_lib::auth::envelope::Envelope;
+use berry_lib::auth::jwt::JwtConfig;
+use berry_lib::auth::rate_limit::RateLimiter;
+use berry_lib::twitch::bot_registry::BotRegistry;
+use berry_lib::twitch::token_refresher;
 use dotenv::dotenv;
 use reqwest::Client;
 use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
 use colored::*;
+use std::sync::Arc;
 
+pub mod cli;
 pub mod controllers;
 pub mod models;
 pub mod routes;
@@ -54,36 +62,81 @@ async fn main() -> std::io::Result<()> {
         Ok(pool) => pool,
         Err(e) => {
             println!("Error: {:?}", e);
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to start server",
-            ));
+            return Err(std::io::Error::other("Failed to start server"));
         }
     };
 
     // START SERVICES:
     println!("{}", "Starting Services".bright_blue().bold().underline());
+    if let Err(e) = services::init_db::run_migrations(&db_pool).await {
+        println!("Error: {:?}", e);
+        return Err(std::io::Error::other("Failed to start server"));
+    }
+
     match services::init_db::db_table_check(&db_pool).await {
         Ok(()) => {
             println!("{}", "Database Tables Checked".bright_green());
         },
         Err(e) => {
             println!("Error: {:?}", e);
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to start server",
-            ));
+            return Err(std::io::Error::other("Failed to start server"));
+        }
+    };
+
+    // Commands only need the database
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match cli::run(&db_pool, &args).await {
+        Ok(true) => return Ok(()),
+        Ok(false) => {}
+        Err(e) => {
+            eprintln!("{} {}", "Error:".bright_red(), e);
+            return Err(std::io::Error::other("Command failed"));
+        }
+    }
+
+    let jwt_config = match JwtConfig::from_env() {
+        Ok(config) => {
+            let kids: Vec<&str> = config.keys().keys().iter().map(|key| key.kid.as_str()).collect();
+            println!(
+                "{} {} ({:?})",
+                "JWT Keys Loaded:".bright_green(),
+                kids.join(", "),
+                config.algorithm()
+            );
+            web::Data::new(config)
+        }
+        Err(e) => {
+            println!("Error: {}", e);
+            return Err(std::io::Error::other("Failed to start server"));
         }
     };
 
+    if let Err(e) = Envelope::shared() {
+        println!("Error: {}", e);
+        return Err(std::io::Error::other("Failed to start server"));
+    }
+
+    let trends = web::Data::new(TrendRegistry::default());
+    let rate_limiter = web::Data::new(RateLimiter::default());
+    let bot_registry = web::Data::new(BotRegistry::default());
+
+    tokio::spawn(token_refresher::run(
+        db_pool.clone(),
+        Client::new(),
+        bot_registry.get_ref().clone(),
+    ));
+
+    let auth_rules = Arc::new(routes::auth_rules());
+
     let server = HttpServer::new(move || {
         let cors = Cors::default()
             .allowed_origin("http://localhost:5173")
-            .allowed_methods(vec!["GET", "POST", "OPTIONS"])
+            .allowed_methods(vec!["GET", "POST", "PUT", "DELETE", "OPTIONS"])
             .allowed_headers(vec![
                 header::AUTHORIZATION,
                 header::ACCEPT,
                 header::CONTENT_TYPE,
+                header::HeaderName::from_static("x-berry-channel"),
             ])
             .supports_credentials()
             .max_age(3600);
@@ -93,10 +146,26 @@ async fn main() -> std::io::Result<()> {
         App::new()
             .wrap(cors)
             .wrap(Logger::default())
-            .wrap(middleware::auth_middleware::AuthMiddleware)
+            .wrap(middleware::auth_middleware::AuthMiddleware::new(auth_rules.clone()))
             .app_data(web::Data::new(db_pool.clone()))
             .app_data(web::Data::new(reqwest_client.clone()))
+            .app_data(jwt_config.clone())
+            .app_data(trends.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(bot_registry.clone())
             .configure(routes::auth_rotues::init_routes)
+            .configure(routes::well_known_routes::init_routes)
+            .configure(routes::command_routes::init_routes)
+            .configure(routes::counter_routes::init_routes)
+            .configure(routes::timer_routes::init_routes)
+            .configure(routes::quote_routes::init_routes)
+            .configure(routes::points_routes::init_routes)
+            .configure(routes::poll_routes::init_routes)
+            .configure(routes::giveaway_routes::init_routes)
+            .configure(routes::analytics_routes::init_routes)
+            .configure(routes::access_routes::init_routes)
+            .configure(routes::bot_routes::init_routes)
+            .configure(routes::moderation_routes::init_routes)
     });
 
     let server_address = format!("127.0.0.1:{}", port);
@@ -104,14 +173,12 @@ async fn main() -> std::io::Result<()> {
 
     server
         .bind(server_address)
-        .map_err(|e| {
+        .inspect_err(|e| {
             println!("Error Binding: {:?}", e);
-            e
         })?
         .run()
         .await
-        .map_err(|e| {
+        .inspect_err(|_| {
             println!("Failed to start server");
-            e
         })
 }
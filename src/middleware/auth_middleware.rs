@@ -1,18 +1,50 @@
 use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
 
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::InternalError,
     Error,
-    http::header::{HeaderValue, AUTHORIZATION},
-    error::ErrorUnauthorized,
+    http::{
+        header::{HeaderName, HeaderValue, ACCESS_CONTROL_REQUEST_METHOD, AUTHORIZATION, RETRY_AFTER},
+        Method, StatusCode,
+    },
+    web, HttpMessage, HttpRequest, Responder,
 };
 use futures_util::future::LocalBoxFuture;
+use sqlx::PgPool;
 
-use berry_lib::auth::jwt::{JwtConfig, JwtAlgorithm};
+use berry_lib::api::api_response::{db_error, ApiResponse};
+use berry_lib::auth::jwt::{Claims, JwtConfig};
+use berry_lib::auth::rate_limit::RateLimiter;
+use berry_lib::db::api_keys;
 
 use colored::*;
 
-pub struct AuthMiddleware;
+use super::auth_rules::{Access, AuthRules};
+use super::auth_user::api_error;
+use super::channel_access::channel_access;
+
+const RATE_LIMIT_LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+const RATE_LIMIT_REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+
+// Checks every request against the routes' auth rules (see auth_rules and
+// routes::auth_rules). A valid token's claims are kept in the request
+// extensions for handlers, see AuthUser.
+//
+// An API key can stand in for the token on routes declared with require(), and
+// is held to its own rate limit. The key is kept in the extensions too.
+pub struct AuthMiddleware {
+    rules: Arc<AuthRules>,
+}
+
+impl AuthMiddleware {
+    pub fn new(rules: Arc<AuthRules>) -> Self {
+        AuthMiddleware { rules }
+    }
+}
 
 impl<S, B> Transform<S, ServiceRequest> for AuthMiddleware
 where
@@ -26,18 +58,21 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ready(Ok(AuthMiddlewareService { service }))
+        ready(Ok(AuthMiddlewareService {
+            service: Rc::new(service),
+            rules: self.rules.clone(),
+        }))
     }
 }
 
 pub struct AuthMiddlewareService<S> {
-    service: S,
+    service: Rc<S>,
+    rules: Arc<AuthRules>,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthMiddlewareService<S>
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
-    S::Future: 'static,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     B: 'static,
 {
     type Response = ServiceResponse<B>;
@@ -47,68 +82,195 @@ where
     forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        let auth_result = check_authentication(req.headers().get(AUTHORIZATION), &req);
-
-        let fut = self.service.call(req);
+        let service = self.service.clone();
+        let rules = self.rules.clone();
 
         Box::pin(async move {
-            match auth_result {
-                Some(true) => {
-                    println!("{} {}", "Auth Middleware".green(), "Authentication Passed".bright_green().bold()); // !REMOVE
-                    fut.await
-                },
-                _ => {
-                    println!("{} {}", "Auth Middleware".green(), "Authentication Failed".bright_red().bold()); // !REMOVE
-                    Err(ErrorUnauthorized("Unauthorized"))
-                },
+            match check_authentication(&req, &rules).await {
+                Ok(None) => service.call(req).await,
+                Ok(Some(quota)) => {
+                    let mut res = service.call(req).await?;
+                    let headers = res.headers_mut();
+                    headers.insert(RATE_LIMIT_LIMIT, HeaderValue::from(quota.limit));
+                    headers.insert(RATE_LIMIT_REMAINING, HeaderValue::from(quota.remaining));
+                    Ok(res)
+                }
+                Err(rejected) => {
+                    println!("{} {} {}", "Auth Middleware".green(), "Authentication Failed".bright_red().bold(), req.path()); // !REMOVE
+                    Err(rejected.into_error(req.request()))
+                }
             }
         })
     }
 }
 
-fn check_authentication(header_value: Option<&HeaderValue>, req: &ServiceRequest) -> Option<bool> {
-    println!("{} {}", "Auth Middleware".green(), "Checking Authentication FN".cyan().bold()); // !REMOVE
+// What's left of an API key's rate limit
+struct Quota {
+    limit: u32,
+    remaining: u32,
+}
 
+struct Rejected {
+    status: StatusCode,
+    error: String,
+    retry_after: Option<Duration>,
+}
 
-    println!("{}, {}", "Auth Middleware".green(), "Checking Whitelisted Routes".cyan().bold()); // !REMOVE
+impl From<(StatusCode, String)> for Rejected {
+    fn from((status, error): (StatusCode, String)) -> Self {
+        Rejected {
+            status,
+            error,
+            retry_after: None,
+        }
+    }
+}
 
-    println!("{}, {:?}", "Request Path".cyan(), req.path()); // !REMOVE
+impl Rejected {
+    fn into_error(self, req: &HttpRequest) -> Error {
+        let Some(retry_after) = self.retry_after else {
+            return api_error(req, self.status, self.error);
+        };
 
-    let whitelisted_routes = vec!["/auth/login", "/auth/register"];
+        let mut response = ApiResponse::<()>::new(None, Some(self.error.clone()), Some(self.status)).respond_to(req);
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(retry_after.as_secs().max(1)));
+        InternalError::from_response(self.error, response).into()
+    }
+}
 
-    if whitelisted_routes.contains(&req.path()) {
-        println!("{} {}", "Whitelisted Route".green(), "Skipping Authentication".cyan().bold()); // !REMOVE
-        return Some(true);
+async fn check_authentication(req: &ServiceRequest, rules: &AuthRules) -> Result<Option<Quota>, Rejected> {
+    // CORS preflights never carry credentials, the CORS middleware answers them
+    if req.method() == Method::OPTIONS && req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD) {
+        return Ok(None);
     }
 
-    let header_str = match header_value {
-        Some(value) => match value.to_str() {
-            Ok(str) => str,
-            Err(err) => {
-                println!("{} {}", "Header Value Error".red(), err);
-                return Some(false);
+    let unauthorized = || Rejected::from((StatusCode::UNAUTHORIZED, "Unauthorized".to_string()));
+
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .map(|value| value.to_str().map(bearer_token).unwrap_or_default());
+
+    let resolved = rules.resolve(req.method(), req.path());
+
+    if let Some(key) = token.filter(|token| api_keys::is_api_key(token)) {
+        return match resolved.access {
+            Access::Public => Ok(None),
+            Access::Optional | Access::Required => Err(Rejected::from((
+                StatusCode::FORBIDDEN,
+                "API keys can't be used here, sign in instead".to_string(),
+            ))),
+            Access::Permission(permission) => {
+                let quota = check_api_key(req, key).await?;
+                let access = channel_access(req.request(), permission, resolved.channel).await?;
+                req.extensions_mut().insert(access);
+                Ok(Some(quota))
             }
-        },
-        None => {
-            println!("{}", "Authorization Header Missing".yellow());
-            return Some(false);
+        };
+    }
+
+    // None without a header, Some(None) when the token doesn't check out
+    let claims = match token {
+        Some(token) => {
+            let jwt_config = req.app_data::<web::Data<JwtConfig>>().ok_or_else(|| {
+                Rejected::from((StatusCode::INTERNAL_SERVER_ERROR, "JWT not configured".to_string()))
+            })?;
+
+            Some(
+                jwt_config
+                    .validate_token(token)
+                    .inspect_err(|err| println!("{} {:?}", "JWT Error".red(), err)) // !REMOVE
+                    .ok()
+                    .map(|token_data| token_data.claims),
+            )
         }
+        None => None,
     };
 
+    let authenticated = matches!(claims, Some(Some(_)));
+    let invalid_token = matches!(claims, Some(None));
+    if let Some(Some(claims)) = claims {
+        req.extensions_mut().insert(claims);
+    }
 
-    let jwt_algorithm = JwtAlgorithm::HS256; // Specify the desired algorithm here
-
-    let jwt_config = JwtConfig::new(jwt_algorithm.into());
+    match resolved.access {
+        Access::Public => Ok(None),
+        Access::Optional if invalid_token => Err(unauthorized()),
+        Access::Optional => Ok(None),
+        Access::Required if authenticated => Ok(None),
+        Access::Required => Err(unauthorized()),
+        Access::Permission(_) if !authenticated => Err(unauthorized()),
+        Access::Permission(permission) => {
+            let access = channel_access(req.request(), permission, resolved.channel).await?;
+            req.extensions_mut().insert(access);
+            Ok(None)
+        }
+    }
+}
 
-    let validation_result = jwt_config.validate_token(header_str);
+// Looks the key up, counts the request against its limit and keeps it in the
+// request extensions for channel_access
+async fn check_api_key(req: &ServiceRequest, key: &str) -> Result<Quota, Rejected> {
+    let not_configured = |what: &str| Rejected::from((StatusCode::INTERNAL_SERVER_ERROR, format!("{} not configured", what)));
+    let pool = req.app_data::<web::Data<PgPool>>().ok_or_else(|| not_configured("Database"))?;
+    let limiter = req.app_data::<web::Data<RateLimiter>>().ok_or_else(|| not_configured("Rate limiter"))?;
 
-    println!("{}, {:?}", "Validation Result".green(), validation_result); // !REMOVE
+    let api_key = match api_keys::authenticate(pool, key).await {
+        Ok(Some(api_key)) => api_key,
+        Ok(None) => {
+            return Err(Rejected::from((
+                StatusCode::UNAUTHORIZED,
+                "Invalid or expired API key".to_string(),
+            )))
+        }
+        Err(e) => return Err(Rejected::from(db_error("Error Checking API Key", e))),
+    };
 
-    match validation_result {
-        Ok(_) => Some(true),
-        Err(err) => {
-            println!("{} {:?}", "JWT Error".red(), err); // !REMOVE
-            Some(false)
+    let limit = api_key.rate_limit.max(0) as u32;
+    let remaining = match limiter.check(&api_key.id, limit) {
+        Ok(remaining) => remaining,
+        Err(limited) => {
+            return Err(Rejected {
+                status: StatusCode::TOO_MANY_REQUESTS,
+                error: format!(
+                    "This API key is limited to {} requests a minute, try again in {}s",
+                    limited.limit,
+                    limited.retry_after.as_secs().max(1)
+                ),
+                retry_after: Some(limited.retry_after),
+            })
         }
+    };
+
+    req.extensions_mut().insert(api_key);
+    Ok(Quota { limit, remaining })
+}
+
+// The token from `Bearer <token>`. A bare token is still accepted, as older
+// clients send it that way.
+fn bearer_token(header: &str) -> &str {
+    let header = header.trim();
+    match header.split_once(' ') {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => token.trim(),
+        _ => header,
     }
-}
\ No newline at end of file
+}
+
+// Claims of the JWT the request was authenticated with
+pub fn request_claims(req: &HttpRequest) -> Option<Claims> {
+    req.extensions().get::<Claims>().cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_bearer_and_bare_tokens() {
+        assert_eq!(bearer_token("Bearer abc.def.ghi"), "abc.def.ghi");
+        assert_eq!(bearer_token("bearer  abc.def.ghi "), "abc.def.ghi");
+        assert_eq!(bearer_token("abc.def.ghi"), "abc.def.ghi");
+    }
+}
@@ -0,0 +1,167 @@
+use actix_web::dev::{Path, ResourceDef};
+use actix_web::http::Method;
+use berry_lib::auth::rbac::Permission;
+
+// What a route asks of the caller. Anything no rule covers is Required.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    // No token needed; a bad one is ignored
+    Public,
+    // Works either way, handlers take Option<AuthUser>. A bad token is rejected.
+    Optional,
+    Required,
+    // Required, plus the permission on the channel acted on, see require()
+    Permission(Permission),
+}
+
+// Needs `permission` on the channel acted on: the route's `{channel}` segment,
+// the X-Berry-Channel header or the caller's own, in that order
+pub fn require(permission: Permission) -> Access {
+    Access::Permission(permission)
+}
+
+// Which requests a rule covers, declared with the routes it's for:
+//
+//     rules.add(AuthRule::path("/auth/login", Access::Public).methods(&[Method::POST]));
+//     rules.add(AuthRule::prefix("/analytics/{channel}", require(Permission::ViewAnalytics)));
+//
+// Patterns are actix patterns. A prefix covers whole segments, so "/quotes"
+// covers "/quotes/berry" but not "/quotesx".
+#[derive(Debug, Clone)]
+pub struct AuthRule {
+    resource: ResourceDef,
+    methods: Vec<Method>,
+    access: Access,
+}
+
+impl AuthRule {
+    pub fn path(pattern: &str, access: Access) -> Self {
+        AuthRule {
+            resource: ResourceDef::new(pattern),
+            methods: Vec::new(),
+            access,
+        }
+    }
+
+    pub fn prefix(pattern: &str, access: Access) -> Self {
+        AuthRule {
+            resource: ResourceDef::prefix(pattern),
+            methods: Vec::new(),
+            access,
+        }
+    }
+
+    // Only these methods. Without, the rule covers every method.
+    pub fn methods(mut self, methods: &[Method]) -> Self {
+        self.methods = methods.to_vec();
+        self
+    }
+
+    fn covers(&self, method: &Method) -> bool {
+        self.methods.is_empty() || self.methods.contains(method)
+    }
+
+    // Exact paths beat prefixes, longer patterns beat shorter ones, and rules
+    // naming the method beat those that don't
+    fn specificity(&self) -> (bool, usize, bool) {
+        (
+            !self.resource.is_prefix(),
+            self.resource.pattern().map_or(0, str::len),
+            !self.methods.is_empty(),
+        )
+    }
+}
+
+// The access a request needs, and the channel its path names, if any
+#[derive(Debug, PartialEq, Eq)]
+pub struct Resolved {
+    pub access: Access,
+    pub channel: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AuthRules {
+    rules: Vec<AuthRule>,
+}
+
+impl AuthRules {
+    pub fn add(&mut self, rule: AuthRule) -> &mut Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn resolve(&self, method: &Method, path: &str) -> Resolved {
+        // "/auth/login/" is "/auth/login"
+        let path = match path.trim_end_matches('/') {
+            "" => "/",
+            trimmed => trimmed,
+        };
+
+        let rule = self
+            .rules
+            .iter()
+            .filter(|rule| rule.covers(method) && rule.resource.is_match(path))
+            .max_by_key(|rule| rule.specificity());
+
+        let Some(rule) = rule else {
+            return Resolved {
+                access: Access::Required,
+                channel: None,
+            };
+        };
+
+        let mut matched = Path::new(path);
+        rule.resource.capture_match_info(&mut matched);
+
+        Resolved {
+            access: rule.access,
+            channel: matched.get("channel").map(str::to_string),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> AuthRules {
+        let mut rules = AuthRules::default();
+        rules
+            .add(AuthRule::path("/auth/login", Access::Public).methods(&[Method::POST]))
+            .add(AuthRule::prefix("/overlay", Access::Public))
+            .add(AuthRule::prefix("/commands", require(Permission::ManageCommands)))
+            .add(AuthRule::path("/commands/export", Access::Optional).methods(&[Method::GET]))
+            .add(AuthRule::prefix("/analytics/{channel}", require(Permission::ViewAnalytics)));
+        rules
+    }
+
+    #[test]
+    fn matches_paths_prefixes_and_methods() {
+        let rules = rules();
+        let access = |method: Method, path: &str| rules.resolve(&method, path).access;
+
+        assert_eq!(access(Method::POST, "/auth/login"), Access::Public);
+        assert_eq!(access(Method::POST, "/auth/login/"), Access::Public);
+        assert_eq!(access(Method::GET, "/auth/login"), Access::Required);
+        assert_eq!(access(Method::GET, "/overlay/berry/poll"), Access::Public);
+        assert_eq!(access(Method::GET, "/overlayx"), Access::Required);
+        assert_eq!(access(Method::GET, "/sessions"), Access::Required);
+
+        // The exact path wins over the prefix, for its method only
+        assert_eq!(access(Method::GET, "/commands/export"), Access::Optional);
+        assert_eq!(access(Method::POST, "/commands/export"), require(Permission::ManageCommands));
+        assert_eq!(access(Method::DELETE, "/commands/12"), require(Permission::ManageCommands));
+    }
+
+    #[test]
+    fn captures_the_channel_from_the_path() {
+        assert_eq!(
+            rules().resolve(&Method::GET, "/analytics/berry/trends"),
+            Resolved {
+                access: require(Permission::ViewAnalytics),
+                channel: Some("berry".to_string()),
+            }
+        );
+        assert_eq!(rules().resolve(&Method::GET, "/commands").channel, None);
+    }
+}
@@ -0,0 +1,76 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use actix_web::{
+    dev::Payload, error::InternalError, http::StatusCode, web, Error, FromRequest, HttpRequest, Responder,
+};
+use berry_lib::api::api_response::{db_error, ApiResponse};
+use berry_lib::auth::jwt::Claims;
+use berry_lib::db::sessions;
+use sqlx::PgPool;
+
+use super::auth_middleware::request_claims;
+
+// The signed-in user making the request, for handlers:
+//
+//     pub async fn handler(user: AuthUser) -> ...
+//     pub async fn handler(user: Option<AuthUser>) -> ...
+//
+// Extraction fails with 401 without a valid token or once the token's session
+// has ended. The Option form is None instead, for routes that work either way.
+// Requests made with an API key have no AuthUser; see resolve_caller.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub unxid: String,
+    pub twitch_id: String,
+    pub session_id: String,
+    pub claims: Claims,
+}
+
+impl AuthUser {
+    pub async fn from_request_with(req: &HttpRequest, pool: &PgPool) -> Result<AuthUser, (StatusCode, String)> {
+        let claims = request_claims(req).ok_or((StatusCode::UNAUTHORIZED, "Unauthorized".to_string()))?;
+
+        // The token outlives a logout or revocation by up to its lifetime otherwise
+        match sessions::is_active(pool, &claims.sid, &claims.sub).await {
+            Ok(true) => {}
+            Ok(false) => return Err((StatusCode::UNAUTHORIZED, "Session ended".to_string())),
+            Err(e) => return Err(db_error("Error Checking Session", e)),
+        }
+
+        Ok(AuthUser {
+            unxid: claims.sub.clone(),
+            twitch_id: claims.twitch_id.clone(),
+            session_id: claims.sid.clone(),
+            claims,
+        })
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+
+        Box::pin(async move {
+            let pool = req
+                .app_data::<web::Data<PgPool>>()
+                .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Database not configured".to_string()));
+
+            let result = match pool {
+                Ok(pool) => AuthUser::from_request_with(&req, pool).await,
+                Err(e) => Err(e),
+            };
+
+            result.map_err(|(status, e)| api_error(&req, status, e))
+        })
+    }
+}
+
+// An error that responds like our handlers do, for extractors and middleware
+pub fn api_error(req: &HttpRequest, status: StatusCode, error: String) -> Error {
+    let response = ApiResponse::<()>::new(None, Some(error.clone()), Some(status)).respond_to(req);
+    InternalError::from_response(error, response).into()
+}
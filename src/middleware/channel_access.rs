@@ -0,0 +1,119 @@
+use actix_web::{http::StatusCode, web, HttpMessage, HttpRequest};
+use berry_lib::api::api_response::db_error;
+use berry_lib::auth::rbac::{self, Permission, Role};
+use berry_lib::db::api_keys::ApiKey;
+use berry_lib::db::channel_access;
+use sqlx::PgPool;
+
+use super::auth_user::AuthUser;
+use crate::models::user::get_user_db::get_twitch_login;
+
+// Picks the channel to act on when the path doesn't name one. Without it the
+// caller acts on their own channel.
+pub const CHANNEL_HEADER: &str = "X-Berry-Channel";
+
+// Whose channel the request acts on and as what, kept in the request extensions
+#[derive(Debug, Clone)]
+pub struct ChannelAccess {
+    pub unxid: String,
+    pub twitch_id: String,
+    // The session signed in with, None when the request came with an API key
+    pub session_id: Option<String>,
+    pub api_key: Option<ApiKey>,
+    // The caller's own Twitch login
+    pub login: String,
+    pub channel: String,
+    pub role: Role,
+}
+
+// Checks the caller holds `permission` on the channel acted on, for routes
+// declared with auth_rules::require. The AuthMiddleware keeps the outcome in the
+// request extensions, where resolve_caller finds it.
+//
+// The channel is `path_channel`, the route's `{channel}` segment, then the
+// X-Berry-Channel header, then the caller's own channel. A request made with an
+// API key acts as the key's owner, and needs the permission among its scopes too.
+pub async fn channel_access(
+    req: &HttpRequest,
+    permission: Permission,
+    path_channel: Option<String>,
+) -> Result<ChannelAccess, (StatusCode, String)> {
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Database not configured".to_string()))?;
+
+    let api_key = req.extensions().get::<ApiKey>().cloned();
+    let (unxid, twitch_id, session_id) = match &api_key {
+        Some(key) if !key.allows(permission) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                format!("This API key doesn't have the {} scope", permission.as_str()),
+            ))
+        }
+        Some(key) => (key.unxid.clone(), key.twitch_id.clone(), None),
+        None => {
+            let user = AuthUser::from_request_with(req, pool).await?;
+            (user.unxid, user.twitch_id, Some(user.session_id))
+        }
+    };
+
+    let login = match get_twitch_login(pool, &unxid).await {
+        Ok(Some(login)) => login.to_lowercase(),
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "User not found".to_string())),
+        Err(e) => return Err(db_error("Error Getting User", e)),
+    };
+
+    let channel = target_channel(req, path_channel).unwrap_or_else(|| login.clone());
+
+    // A broadcaster on their own channel needs no lookups
+    let (is_admin, granted) = if login == channel {
+        (false, None)
+    } else {
+        let is_admin = channel_access::is_admin(pool, &unxid)
+            .await
+            .map_err(|e| db_error("Error Checking Access", e))?;
+        let granted = match is_admin {
+            true => None,
+            false => channel_access::granted_role(pool, &channel, &unxid)
+                .await
+                .map_err(|e| db_error("Error Checking Access", e))?,
+        };
+        (is_admin, granted)
+    };
+
+    let Some(role) = rbac::role_on_channel(&login, &channel, is_admin, granted) else {
+        return Err((StatusCode::FORBIDDEN, "You don't have access to this channel".to_string()));
+    };
+
+    if !role.allows(permission) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("A channel {} can't do this", role.as_str()),
+        ));
+    }
+
+    Ok(ChannelAccess {
+        unxid,
+        twitch_id,
+        session_id,
+        api_key,
+        login,
+        channel,
+        role,
+    })
+}
+
+fn target_channel(req: &HttpRequest, path_channel: Option<String>) -> Option<String> {
+    let channel = match path_channel {
+        Some(channel) => Some(channel),
+        None => req
+            .headers()
+            .get(CHANNEL_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+    };
+
+    channel
+        .map(|channel| channel.trim().trim_start_matches('#').to_lowercase())
+        .filter(|channel| !channel.is_empty())
+}
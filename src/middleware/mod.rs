@@ -1 +1,4 @@
-pub mod auth_middleware;
\ No newline at end of file
+pub mod auth_middleware;
+pub mod auth_rules;
+pub mod auth_user;
+pub mod channel_access;
@@ -0,0 +1,8 @@
+use sqlx::PgPool;
+
+pub async fn get_twitch_login(pool: &PgPool, unxid: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT twitch_login FROM user_data WHERE unxid = $1")
+        .bind(unxid)
+        .fetch_optional(pool)
+        .await
+}
@@ -1 +1,2 @@
+pub mod get_user_db;
 pub mod set_user_db;
@@ -0,0 +1,24 @@
+use actix_web::web;
+use berry_lib::auth::rbac::Permission;
+
+use crate::controllers;
+use crate::middleware::auth_rules::{require, AuthRule, AuthRules};
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/access")
+            .service(
+                web::resource("")
+                    .route(web::get().to(controllers::auth::channel_access::list_grants)),
+            )
+            .service(
+                web::resource("/{login}")
+                    .route(web::put().to(controllers::auth::channel_access::grant_access))
+                    .route(web::delete().to(controllers::auth::channel_access::revoke_access)),
+            ),
+    );
+}
+
+pub fn auth_rules(rules: &mut AuthRules) {
+    rules.add(AuthRule::prefix("/access", require(Permission::ManageAccess)));
+}
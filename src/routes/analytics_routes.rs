@@ -0,0 +1,19 @@
+use actix_web::web;
+use berry_lib::auth::rbac::Permission;
+
+use crate::controllers;
+use crate::middleware::auth_rules::{require, AuthRule, AuthRules};
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/analytics/{channel}")
+            .service(
+                web::resource("/trends")
+                    .route(web::get().to(controllers::analytics::trends::get_channel_trends)),
+            ),
+    );
+}
+
+pub fn auth_rules(rules: &mut AuthRules) {
+    rules.add(AuthRule::prefix("/analytics/{channel}", require(Permission::ViewAnalytics)));
+}
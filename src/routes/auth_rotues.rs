@@ -1,6 +1,7 @@
-use actix_web::web;
+use actix_web::{http::Method, web};
 
 use crate::controllers;
+use crate::middleware::auth_rules::{Access, AuthRule, AuthRules};
 
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -9,9 +10,52 @@ pub fn init_routes(cfg: &mut web::ServiceConfig) {
                 web::resource("/login")
                     .route(web::post().to(controllers::auth::login::login_twitch)),
             )
-            // .service(
-            //     web::resource("/logout")
-            //         .route(web::post().to(controllers::auth::logout::logout)),
-            // ),
+            .service(
+                web::resource("/twitch/authorize")
+                    .route(web::get().to(controllers::auth::twitch_authorize::authorize_twitch)),
+            )
+            .service(
+                web::resource("/twitch")
+                    .route(web::get().to(controllers::auth::twitch_status::get_twitch_connection)),
+            )
+            .service(
+                web::resource("/refresh")
+                    .route(web::post().to(controllers::auth::sessions::refresh)),
+            )
+            .service(
+                web::resource("/logout")
+                    .route(web::post().to(controllers::auth::sessions::logout)),
+            )
+            .service(
+                web::resource("/channels")
+                    .route(web::get().to(controllers::auth::channel_access::list_managed_channels)),
+            )
+            .service(
+                web::resource("/sessions")
+                    .route(web::get().to(controllers::auth::sessions::list_sessions))
+                    .route(web::delete().to(controllers::auth::sessions::revoke_other_sessions)),
+            )
+            .service(
+                web::resource("/sessions/{id}")
+                    .route(web::delete().to(controllers::auth::sessions::revoke_session)),
+            )
+            .service(
+                web::resource("/api-keys")
+                    .route(web::get().to(controllers::auth::api_keys::list_api_keys))
+                    .route(web::post().to(controllers::auth::api_keys::create_api_key)),
+            )
+            .service(
+                web::resource("/api-keys/{id}")
+                    .route(web::put().to(controllers::auth::api_keys::update_api_key))
+                    .route(web::delete().to(controllers::auth::api_keys::delete_api_key)),
+            )
     );
 }
+
+// Signing in and refreshing come before there's a token to send
+pub fn auth_rules(rules: &mut AuthRules) {
+    rules
+        .add(AuthRule::path("/auth/login", Access::Public).methods(&[Method::POST]))
+        .add(AuthRule::path("/auth/refresh", Access::Public).methods(&[Method::POST]))
+        .add(AuthRule::path("/auth/twitch/authorize", Access::Public).methods(&[Method::GET]));
+}
@@ -0,0 +1,16 @@
+use actix_web::web;
+use berry_lib::auth::rbac::Permission;
+
+use crate::controllers;
+use crate::middleware::auth_rules::{require, AuthRule, AuthRules};
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/bot")
+            .service(web::resource("/say").route(web::post().to(controllers::bot::say::say))),
+    );
+}
+
+pub fn auth_rules(rules: &mut AuthRules) {
+    rules.add(AuthRule::prefix("/bot/say", require(Permission::SendChat)));
+}
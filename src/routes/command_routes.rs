@@ -0,0 +1,35 @@
+use actix_web::web;
+use berry_lib::auth::rbac::Permission;
+
+use crate::controllers;
+use crate::middleware::auth_rules::{require, AuthRule, AuthRules};
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/commands")
+            .service(
+                web::resource("")
+                    .route(web::get().to(controllers::commands::custom_commands::list_commands))
+                    .route(web::post().to(controllers::commands::custom_commands::create_command)),
+            )
+            // Before /{id}, which would otherwise match these names
+            .service(
+                web::resource("/import")
+                    .app_data(web::PayloadConfig::new(controllers::commands::command_import::MAX_IMPORT_SIZE))
+                    .route(web::post().to(controllers::commands::command_import::import_commands)),
+            )
+            .service(
+                web::resource("/export")
+                    .route(web::get().to(controllers::commands::command_import::export_commands)),
+            )
+            .service(
+                web::resource("/{id}")
+                    .route(web::put().to(controllers::commands::custom_commands::update_command))
+                    .route(web::delete().to(controllers::commands::custom_commands::delete_command)),
+            ),
+    );
+}
+
+pub fn auth_rules(rules: &mut AuthRules) {
+    rules.add(AuthRule::prefix("/commands", require(Permission::ManageCommands)));
+}
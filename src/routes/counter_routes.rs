@@ -0,0 +1,37 @@
+use actix_web::{http::Method, web};
+use berry_lib::auth::rbac::Permission;
+
+use crate::controllers;
+use crate::middleware::auth_rules::{require, Access, AuthRule, AuthRules};
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/counters")
+            .service(
+                web::resource("")
+                    .route(web::get().to(controllers::commands::counters::list_counters))
+                    .route(web::post().to(controllers::commands::counters::create_counter)),
+            )
+            .service(
+                web::resource("/{name}")
+                    .route(web::put().to(controllers::commands::counters::update_counter))
+                    .route(web::delete().to(controllers::commands::counters::delete_counter)),
+            ),
+    )
+    // Resources rather than a scope, so other modules can add their own /overlay/{channel} routes
+    .service(
+        web::resource("/overlay/{channel}/counters")
+            .route(web::get().to(controllers::commands::counters::get_overlay_counters)),
+    )
+    .service(
+        web::resource("/overlay/{channel}/counters/{name}")
+            .route(web::get().to(controllers::commands::counters::get_overlay_counter)),
+    );
+}
+
+pub fn auth_rules(rules: &mut AuthRules) {
+    rules
+        .add(AuthRule::prefix("/counters", require(Permission::ManageCommands)))
+        .add(AuthRule::path("/overlay/{channel}/counters", Access::Public).methods(&[Method::GET]))
+        .add(AuthRule::path("/overlay/{channel}/counters/{name}", Access::Public).methods(&[Method::GET]));
+}
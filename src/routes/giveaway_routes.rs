@@ -0,0 +1,32 @@
+use actix_web::{http::Method, web};
+use berry_lib::auth::rbac::Permission;
+
+use crate::controllers;
+use crate::middleware::auth_rules::{require, Access, AuthRule, AuthRules};
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/giveaways")
+            .service(
+                web::resource("")
+                    .route(web::get().to(controllers::commands::giveaways::list_giveaways))
+                    .route(web::post().to(controllers::commands::giveaways::create_giveaway)),
+            )
+            .service(web::resource("/{id}").route(web::get().to(controllers::commands::giveaways::get_giveaway)))
+            .service(
+                web::resource("/{id}/close").route(web::post().to(controllers::commands::giveaways::close_giveaway)),
+            )
+            .service(web::resource("/{id}/draw").route(web::post().to(controllers::commands::giveaways::draw_giveaway)))
+            .service(web::resource("/{id}/end").route(web::post().to(controllers::commands::giveaways::end_giveaway))),
+    )
+    .service(
+        web::resource("/overlay/{channel}/giveaway")
+            .route(web::get().to(controllers::commands::giveaways::get_overlay_giveaway)),
+    );
+}
+
+pub fn auth_rules(rules: &mut AuthRules) {
+    rules
+        .add(AuthRule::prefix("/giveaways", require(Permission::ManageModeration)))
+        .add(AuthRule::path("/overlay/{channel}/giveaway", Access::Public).methods(&[Method::GET]));
+}
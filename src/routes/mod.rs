@@ -1 +1,35 @@
+use crate::middleware::auth_rules::AuthRules;
+
+pub mod access_routes;
+pub mod analytics_routes;
 pub mod auth_rotues;
+pub mod bot_routes;
+pub mod command_routes;
+pub mod counter_routes;
+pub mod giveaway_routes;
+pub mod moderation_routes;
+pub mod points_routes;
+pub mod poll_routes;
+pub mod quote_routes;
+pub mod timer_routes;
+pub mod well_known_routes;
+
+// What each route needs of the caller, declared next to the routes themselves.
+// Anything left out needs a signed-in user.
+pub fn auth_rules() -> AuthRules {
+    let mut rules = AuthRules::default();
+    auth_rotues::auth_rules(&mut rules);
+    well_known_routes::auth_rules(&mut rules);
+    command_routes::auth_rules(&mut rules);
+    counter_routes::auth_rules(&mut rules);
+    timer_routes::auth_rules(&mut rules);
+    quote_routes::auth_rules(&mut rules);
+    points_routes::auth_rules(&mut rules);
+    poll_routes::auth_rules(&mut rules);
+    giveaway_routes::auth_rules(&mut rules);
+    analytics_routes::auth_rules(&mut rules);
+    access_routes::auth_rules(&mut rules);
+    bot_routes::auth_rules(&mut rules);
+    moderation_routes::auth_rules(&mut rules);
+    rules
+}
@@ -0,0 +1,23 @@
+use actix_web::web;
+use berry_lib::auth::rbac::Permission;
+
+use crate::controllers;
+use crate::middleware::auth_rules::{require, AuthRule, AuthRules};
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/moderation")
+            .service(
+                web::resource("/audit")
+                    .route(web::get().to(controllers::bot::moderation::list_audit_log)),
+            )
+            .service(
+                web::resource("/strikes/{user}")
+                    .route(web::get().to(controllers::bot::moderation::get_strikes)),
+            ),
+    );
+}
+
+pub fn auth_rules(rules: &mut AuthRules) {
+    rules.add(AuthRule::prefix("/moderation", require(Permission::ManageModeration)));
+}
@@ -0,0 +1,18 @@
+use actix_web::web;
+use berry_lib::auth::rbac::Permission;
+
+use crate::controllers;
+use crate::middleware::auth_rules::{require, AuthRule, AuthRules};
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/points")
+            .service(web::resource("").route(web::get().to(controllers::commands::points::get_leaderboard)))
+            .service(web::resource("/ledger").route(web::get().to(controllers::commands::points::get_ledger)))
+            .service(web::resource("/adjust").route(web::post().to(controllers::commands::points::adjust_points))),
+    );
+}
+
+pub fn auth_rules(rules: &mut AuthRules) {
+    rules.add(AuthRule::prefix("/points", require(Permission::ManageModeration)));
+}
@@ -0,0 +1,28 @@
+use actix_web::{http::Method, web};
+use berry_lib::auth::rbac::Permission;
+
+use crate::controllers;
+use crate::middleware::auth_rules::{require, Access, AuthRule, AuthRules};
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/polls")
+            .service(
+                web::resource("")
+                    .route(web::get().to(controllers::commands::polls::list_polls))
+                    .route(web::post().to(controllers::commands::polls::create_poll)),
+            )
+            .service(web::resource("/{id}").route(web::get().to(controllers::commands::polls::get_poll)))
+            .service(web::resource("/{id}/end").route(web::post().to(controllers::commands::polls::end_poll))),
+    )
+    .service(
+        web::resource("/overlay/{channel}/poll")
+            .route(web::get().to(controllers::commands::polls::get_overlay_poll)),
+    );
+}
+
+pub fn auth_rules(rules: &mut AuthRules) {
+    rules
+        .add(AuthRule::prefix("/polls", require(Permission::ManageModeration)))
+        .add(AuthRule::path("/overlay/{channel}/poll", Access::Public).methods(&[Method::GET]));
+}
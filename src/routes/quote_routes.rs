@@ -0,0 +1,17 @@
+use actix_web::{http::Method, web};
+
+use crate::controllers;
+use crate::middleware::auth_rules::{Access, AuthRule, AuthRules};
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/quotes").service(
+            web::resource("/{channel}")
+                .route(web::get().to(controllers::commands::quotes::get_channel_quotes)),
+        ),
+    );
+}
+
+pub fn auth_rules(rules: &mut AuthRules) {
+    rules.add(AuthRule::prefix("/quotes", Access::Public).methods(&[Method::GET]));
+}
@@ -0,0 +1,25 @@
+use actix_web::web;
+use berry_lib::auth::rbac::Permission;
+
+use crate::controllers;
+use crate::middleware::auth_rules::{require, AuthRule, AuthRules};
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/timers")
+            .service(
+                web::resource("")
+                    .route(web::get().to(controllers::commands::timers::list_timers))
+                    .route(web::post().to(controllers::commands::timers::create_timer)),
+            )
+            .service(
+                web::resource("/{id}")
+                    .route(web::put().to(controllers::commands::timers::update_timer))
+                    .route(web::delete().to(controllers::commands::timers::delete_timer)),
+            ),
+    );
+}
+
+pub fn auth_rules(rules: &mut AuthRules) {
+    rules.add(AuthRule::prefix("/timers", require(Permission::ManageCommands)));
+}
@@ -0,0 +1,17 @@
+use actix_web::web;
+use berry_lib::auth::jwks::{DISCOVERY_PATH, JWKS_PATH};
+
+use crate::controllers;
+use crate::middleware::auth_rules::{Access, AuthRule, AuthRules};
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource(JWKS_PATH).route(web::get().to(controllers::auth::well_known::jwks)))
+        .service(
+            web::resource(DISCOVERY_PATH)
+                .route(web::get().to(controllers::auth::well_known::openid_configuration)),
+        );
+}
+
+pub fn auth_rules(rules: &mut AuthRules) {
+    rules.add(AuthRule::prefix("/.well-known", Access::Public));
+}
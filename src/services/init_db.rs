@@ -6,20 +6,24 @@ use colored::*;
 
 
 
+pub async fn run_migrations(pool: &PgPool) -> Result<(), sqlx::migrate::MigrateError> {
+    println!("{}", "Running DB Migrations...".purple().bold().underline());
+
+    sqlx::migrate!("./migrations").run(pool).await
+}
+
 pub async fn db_table_check(pool: &PgPool) -> Result<(), sqlx::Error> {
 
     println!("{}", "Starting DB Table Check...".purple().bold().underline());
 
-    let table_names = vec!["user_data", "user_twitch_credentials"]; // List of tables to check
+    let table_names = vec!["user_data", "user_twitch_credentials", "custom_commands", "counters", "timers", "quotes", "points_balances", "points_ledger", "polls", "poll_votes", "giveaways", "giveaway_entries", "giveaway_draws", "sessions", "session_refresh_tokens", "twitch_login_attempts", "user_roles", "channel_grants", "api_keys", "moderation_audit_log"]; // List of tables to check
     let schema_name = "public"; // Schema name
 
-    let query = format!(
-        "SELECT tablename
+    let query = "SELECT tablename
          FROM pg_tables
-         WHERE schemaname = $1 AND tablename = ANY($2);",
-    );
+         WHERE schemaname = $1 AND tablename = ANY($2);";
 
-    let results = sqlx::query(&query)
+    let results = sqlx::query(query)
         .bind(schema_name)
         .bind(&table_names)
         .fetch_all(pool)